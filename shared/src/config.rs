@@ -0,0 +1,278 @@
+use serde::{Deserialize, Serialize};
+
+/// Contents of `config.toml`, every field can be left out.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+#[serde(default)]
+pub struct AppConfig {
+    /// sqlite url, the platform data directory when not set
+    pub database_url: Option<String>,
+    /// no tracker means issues are only kept locally
+    pub tracker: Option<TrackerConfig>,
+    pub rounding: RoundingConfig,
+    pub idle: IdleConfig,
+    pub tray: TrayConfig,
+    pub calendar: CalendarConfig,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum TrackerConfig {
+    Jira(JiraConfig),
+    Github(GithubConfig),
+    Gitlab(GitlabConfig),
+}
+
+impl TrackerConfig {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            TrackerConfig::Jira(_) => "jira",
+            TrackerConfig::Github(_) => "github",
+            TrackerConfig::Gitlab(_) => "gitlab",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+#[serde(default)]
+pub struct JiraConfig {
+    pub url: String,
+    pub user: String,
+    pub api_key: String,
+    /// import query, my open issues when not set
+    pub jql: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+#[serde(default)]
+pub struct GithubConfig {
+    pub user: String,
+    pub token: String,
+    /// `owner/repo` the issues are imported from
+    pub repositories: Vec<String>,
+    /// for github enterprise, api.github.com when not set
+    pub api_url: Option<String>,
+    pub worklogs: GithubWorklogMode,
+}
+
+/// How finished worklogs end up on the issue, github has no worklogs of its own.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum GithubWorklogMode {
+    Off,
+    /// one comment per worklog
+    #[default]
+    Comment,
+    /// a single comment with the total, updated in place
+    Summary,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+#[serde(default)]
+pub struct GitlabConfig {
+    /// e.g. `https://gitlab.example.com`
+    pub url: String,
+    pub user: String,
+    pub token: String,
+}
+
+/// Rounding of the time tracked in a worklog, the start and end are kept as they were.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+#[serde(default)]
+pub struct RoundingConfig {
+    /// 0 turns rounding off
+    pub minutes: u64,
+    pub mode: RoundingMode,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum RoundingMode {
+    #[default]
+    Nearest,
+    Up,
+    Down,
+}
+
+impl RoundingConfig {
+    pub fn apply(&self, seconds: u64) -> u64 {
+        let step = self.minutes * 60;
+        if step == 0 {
+            return seconds;
+        }
+
+        let steps = match self.mode {
+            RoundingMode::Nearest => (seconds + step / 2) / step,
+            RoundingMode::Up => seconds.div_ceil(step),
+            RoundingMode::Down => seconds / step,
+        };
+        steps * step
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[serde(default)]
+pub struct IdleConfig {
+    pub enabled: bool,
+    /// minutes without input before asking what to do with the time
+    pub threshold_minutes: u64,
+}
+
+impl Default for IdleConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            threshold_minutes: 5,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+#[serde(default)]
+pub struct TrayConfig {
+    /// how often the elapsed time in the tray title and tooltip moves on
+    pub tick: TrayTick,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum TrayTick {
+    #[default]
+    Minute,
+    Second,
+}
+
+/// The feed calendar apps subscribe to, served on the loopback interface only.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[serde(default)]
+pub struct CalendarConfig {
+    pub feed: bool,
+    pub port: u16,
+    /// how many days back the feed goes, today included
+    pub days: u64,
+    /// issues of imported calendar events, the first rule matching the title wins
+    pub rules: Vec<CalendarRule>,
+}
+
+impl Default for CalendarConfig {
+    fn default() -> Self {
+        Self {
+            feed: false,
+            port: 47813,
+            days: 30,
+            rules: Vec::new(),
+        }
+    }
+}
+
+/// Logs events with the text in their title on the issue, e.g. `standup` on `OPS-12`.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+#[serde(default)]
+pub struct CalendarRule {
+    /// matched ignoring case
+    pub contains: String,
+    pub issue: String,
+}
+
+impl CalendarRule {
+    pub fn matches(&self, title: &str) -> bool {
+        let contains = self.contains.trim().to_lowercase();
+        !contains.is_empty() && title.to_lowercase().contains(&contains)
+    }
+}
+
+impl CalendarConfig {
+    pub fn url(&self) -> String {
+        format!("http://127.0.0.1:{}/worklogs.ics", self.port)
+    }
+}
+
+impl AppConfig {
+    /// Checks the values can be used, the error names the first wrong one.
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(url) = &self.database_url {
+            if url.trim().is_empty() {
+                return Err("database_url is empty".to_string());
+            }
+        }
+        if self.rounding.minutes > 60 {
+            return Err("rounding can be at most 60 minutes".to_string());
+        }
+        if self.idle.threshold_minutes == 0 {
+            return Err("idle threshold has to be at least a minute".to_string());
+        }
+        if self.calendar.port == 0 {
+            return Err("calendar port can't be 0".to_string());
+        }
+        if self.calendar.days == 0 {
+            return Err("the calendar feed has to cover at least a day".to_string());
+        }
+        if self.calendar.rules.iter().any(|rule| rule.contains.trim().is_empty() || rule.issue.trim().is_empty()) {
+            return Err("calendar rules need a text and an issue".to_string());
+        }
+
+        match &self.tracker {
+            Some(TrackerConfig::Jira(jira)) => {
+                check_url("jira url", &jira.url)?;
+                check_set("jira user", &jira.user)?;
+                check_set("jira api_key", &jira.api_key)
+            }
+            Some(TrackerConfig::Github(github)) => {
+                if let Some(api_url) = &github.api_url {
+                    check_url("github api_url", api_url)?;
+                }
+                check_set("github user", &github.user)?;
+                check_set("github token", &github.token)?;
+                match github.repositories.iter().any(|repo| !repo.contains('/')) {
+                    true => Err("github repositories have to look like owner/repo".to_string()),
+                    false => Ok(()),
+                }
+            }
+            Some(TrackerConfig::Gitlab(gitlab)) => {
+                check_url("gitlab url", &gitlab.url)?;
+                check_set("gitlab user", &gitlab.user)?;
+                check_set("gitlab token", &gitlab.token)
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+fn check_set(name: &str, value: &str) -> Result<(), String> {
+    match value.trim().is_empty() {
+        true => Err(format!("{} is not set", name)),
+        false => Ok(()),
+    }
+}
+
+fn check_url(name: &str, value: &str) -> Result<(), String> {
+    check_set(name, value)?;
+    match value.starts_with("http://") || value.starts_with("https://") {
+        true => Ok(()),
+        false => Err(format!("{} has to start with http:// or https://", name)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rounding(minutes: u64, mode: RoundingMode) -> RoundingConfig {
+        RoundingConfig { minutes, mode }
+    }
+
+    #[test]
+    fn rounds_to_steps_of_the_configured_minutes() {
+        let seconds = [0, 1, 449, 450, 900, 901, 1349];
+        let rounded = |config: RoundingConfig| seconds.map(|s| config.apply(s));
+
+        assert_eq!(rounded(rounding(15, RoundingMode::Nearest)), [0, 0, 0, 900, 900, 900, 900]);
+        assert_eq!(rounded(rounding(15, RoundingMode::Up)), [0, 900, 900, 900, 900, 1800, 1800]);
+        assert_eq!(rounded(rounding(15, RoundingMode::Down)), [0, 0, 0, 0, 900, 900, 900]);
+    }
+
+    #[test]
+    fn zero_minutes_keeps_the_time() {
+        assert_eq!(rounding(0, RoundingMode::Up).apply(1349), 1349);
+        assert_eq!(RoundingConfig::default().apply(61), 61);
+    }
+}
@@ -0,0 +1,110 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    Json,
+    /// one event per worklog, the columns, time zone and duration format don't apply
+    Ics,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+            ExportFormat::Ics => "ics",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportColumn {
+    Date,
+    IssueKey,
+    IssueSummary,
+    Project,
+    Start,
+    End,
+    Duration,
+    Synced,
+    WorklogId,
+}
+
+impl ExportColumn {
+    pub const ALL: [ExportColumn; 9] = [
+        ExportColumn::Date,
+        ExportColumn::IssueKey,
+        ExportColumn::IssueSummary,
+        ExportColumn::Project,
+        ExportColumn::Start,
+        ExportColumn::End,
+        ExportColumn::Duration,
+        ExportColumn::Synced,
+        ExportColumn::WorklogId,
+    ];
+
+    /// Header in the CSV file and key in the JSON objects.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ExportColumn::Date => "date",
+            ExportColumn::IssueKey => "issue_key",
+            ExportColumn::IssueSummary => "issue_summary",
+            ExportColumn::Project => "project",
+            ExportColumn::Start => "start",
+            ExportColumn::End => "end",
+            ExportColumn::Duration => "duration",
+            ExportColumn::Synced => "synced",
+            ExportColumn::WorklogId => "worklog_id",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum DurationFormat {
+    #[default]
+    Seconds,
+    /// `1.50`
+    DecimalHours,
+    /// `1h 30m`
+    HoursMinutes,
+}
+
+/// What `export_worklogs` writes, the finished worklogs started in `[from, to)`.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct ExportOptions {
+    pub from: u64,
+    pub to: u64,
+    pub format: ExportFormat,
+    pub columns: Vec<ExportColumn>,
+    /// `local`, `utc`, a name like `Europe/Berlin` or an offset like `+02:00`
+    pub time_zone: String,
+    pub duration_format: DurationFormat,
+    /// file to write, in the downloads directory when not set
+    pub path: Option<String>,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            from: 0,
+            to: 0,
+            format: ExportFormat::Csv,
+            columns: vec![
+                ExportColumn::Date,
+                ExportColumn::IssueKey,
+                ExportColumn::IssueSummary,
+                ExportColumn::Start,
+                ExportColumn::End,
+                ExportColumn::Duration,
+            ],
+            time_zone: "local".to_string(),
+            duration_format: DurationFormat::Seconds,
+            path: None,
+        }
+    }
+}
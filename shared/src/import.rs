@@ -0,0 +1,149 @@
+use serde::{Deserialize, Serialize};
+
+/// Ready-made mappings for the files other time trackers export.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportPreset {
+    #[default]
+    Custom,
+    Toggl,
+    Clockify,
+}
+
+impl ImportPreset {
+    pub const ALL: [ImportPreset; 3] = [ImportPreset::Custom, ImportPreset::Toggl, ImportPreset::Clockify];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ImportPreset::Custom => "custom",
+            ImportPreset::Toggl => "toggl",
+            ImportPreset::Clockify => "clockify",
+        }
+    }
+
+    /// The mapping for the detailed CSV report of the tracker, none for a custom file.
+    pub fn mapping(&self) -> Option<ImportMapping> {
+        match self {
+            ImportPreset::Custom => None,
+            ImportPreset::Toggl => Some(ImportMapping {
+                issue_key: "Description".to_string(),
+                extract_key: true,
+                summary: Some("Description".to_string()),
+                start_date: "Start date".to_string(),
+                start_time: Some("Start time".to_string()),
+                end_date: Some("End date".to_string()),
+                end_time: Some("End time".to_string()),
+                duration: Some("Duration".to_string()),
+                date_format: "%Y-%m-%d".to_string(),
+                ..ImportMapping::default()
+            }),
+            ImportPreset::Clockify => Some(ImportMapping {
+                issue_key: "Description".to_string(),
+                extract_key: true,
+                summary: Some("Description".to_string()),
+                start_date: "Start Date".to_string(),
+                start_time: Some("Start Time".to_string()),
+                end_date: Some("End Date".to_string()),
+                end_time: Some("End Time".to_string()),
+                duration: Some("Duration (h)".to_string()),
+                date_format: "%m/%d/%Y".to_string(),
+                ..ImportMapping::default()
+            }),
+        }
+    }
+}
+
+/// Which columns of a CSV file hold what, columns are named by their header.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct ImportMapping {
+    /// `,` or `;`
+    pub delimiter: char,
+    pub issue_key: String,
+    /// takes the first `ABC-123` out of the column instead of the whole value
+    pub extract_key: bool,
+    /// summary of the issues the import creates
+    pub summary: Option<String>,
+    /// a date, or a date and a time when there is no start time column
+    pub start_date: String,
+    pub start_time: Option<String>,
+    /// the start date when not set
+    pub end_date: Option<String>,
+    pub end_time: Option<String>,
+    /// used when there is no end, `1:30:00`, `1.5` or `1h 30m`
+    pub duration: Option<String>,
+    /// chrono format of the dates, e.g. `%Y-%m-%d` or `%d.%m.%Y`
+    pub date_format: String,
+    /// `local`, `utc`, a name like `Europe/Berlin` or an offset like `+02:00`
+    pub time_zone: String,
+    /// queues the imported worklogs for the tracker, otherwise they count as synced
+    pub push_to_tracker: bool,
+    /// imports worklogs overlapping others too, they are skipped otherwise
+    pub import_overlapping: bool,
+}
+
+impl Default for ImportMapping {
+    fn default() -> Self {
+        Self {
+            delimiter: ',',
+            issue_key: String::new(),
+            extract_key: false,
+            summary: None,
+            start_date: String::new(),
+            start_time: None,
+            end_date: None,
+            end_time: None,
+            duration: None,
+            date_format: "%Y-%m-%d".to_string(),
+            time_zone: "local".to_string(),
+            push_to_tracker: false,
+            import_overlapping: false,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub enum ImportRowStatus {
+    New,
+    Duplicate, // same issue, start and end as a worklog already there or earlier in the file
+    Overlap(String), // the worklog or the line of the file it overlaps
+    Invalid(String),
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct ImportRow {
+    pub line: u64, // in the file, the header is line 1
+    pub issue_key: String,
+    pub start: u64,
+    pub end: u64,
+    pub status: ImportRowStatus,
+}
+
+/// What an import of the file would do, nothing is written for it.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+pub struct ImportPreview {
+    pub headers: Vec<String>,
+    pub rows: Vec<ImportRow>,
+    pub new_issues: Vec<String>, // keys of the issues the import creates
+    pub error: Option<String>,   // why the mapping does not fit the file, there are no rows then
+}
+
+/// One import, undoing it removes its worklogs and the issues it created.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct ImportBatch {
+    pub id: String,
+    pub created: u64,
+    pub source: String, // name of the imported file
+    pub worklogs: u64,
+    pub issues: u64,
+}
+
+/// A worklog proposed for a calendar event that is over, written only once accepted.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct SuggestedWorklog {
+    pub id: String, // uid of the event and the start, recurring events share the uid
+    pub title: String,
+    pub start: u64,
+    pub end: u64,
+    pub issue_name: Option<String>, // from the first rule matching the title, the issue may not exist
+    pub conflict: Option<String>,   // the worklog it would overlap
+}
@@ -3,6 +3,16 @@ use std::fmt::Display;
 use implicit_clone::ImplicitClone;
 use serde::{Deserialize, Serialize};
 
+mod config;
+mod export;
+mod import;
+
+pub use config::*;
+pub use export::*;
+pub use import::*;
+
+pub const LOCAL_SOURCE: &str = "local";
+
 #[derive(Serialize, Deserialize, Debug, PartialEq, ImplicitClone, Clone)]
 pub struct Issue {
     pub id: String,
@@ -10,7 +20,15 @@ pub struct Issue {
     pub url: String,
     pub summary: String,
     pub assignee_email: String,
-    pub time_tracked_all: u64,
+    pub time_tracked_all: u64, // seconds in the finished local worklogs, kept by the storage
+    pub source: String, // "local" or the tracker it was imported from
+    pub stale: bool,    // no longer returned by the tracker
+    pub status: String,
+    pub project: String,
+    pub issue_type: String,
+    pub time_estimate: u64,     // seconds, as estimated in the tracker
+    pub remote_time_spent: u64, // seconds, as logged in the tracker
+    pub tags: Vec<String>,      // labels in the tracker
 }
 
 impl Issue {
@@ -29,6 +47,14 @@ impl Issue {
             summary,
             assignee_email,
             time_tracked_all,
+            source: LOCAL_SOURCE.to_string(),
+            stale: false,
+            status: String::new(),
+            project: String::new(),
+            issue_type: String::new(),
+            time_estimate: 0,
+            remote_time_spent: 0,
+            tags: Vec::new(),
         }
     }
     pub fn empty() -> Self {
@@ -39,6 +65,14 @@ impl Issue {
             summary: String::new(),
             assignee_email: String::new(),
             time_tracked_all: 0,
+            source: LOCAL_SOURCE.to_string(),
+            stale: false,
+            status: String::new(),
+            project: String::new(),
+            issue_type: String::new(),
+            time_estimate: 0,
+            remote_time_spent: 0,
+            tags: Vec::new(),
         }
     }
 }
@@ -56,6 +90,8 @@ pub struct Worklog {
     pub start: u64,
     pub end: u64,
     pub total_time: u64,
+    pub remote_id: Option<String>, // worklog id in jira
+    pub synced: bool,
 }
 
 impl Worklog {
@@ -66,6 +102,8 @@ impl Worklog {
             start,
             end,
             total_time,
+            remote_id: None,
+            synced: false,
         }
     }
 
@@ -76,10 +114,182 @@ impl Worklog {
             start: 0,
             end: 0,
             total_time: 0,
+            remote_id: None,
+            synced: false,
         }
     }
 }
 
+/// Formats seconds as `1h 30m`, durations under a minute as `45s`.
+pub fn format_duration(seconds: u64) -> String {
+    let hours = seconds / 3600;
+    let minutes = (seconds % 3600) / 60;
+
+    match (hours, minutes) {
+        (0, 0) => format!("{}s", seconds),
+        (0, m) => format!("{}m", m),
+        (h, 0) => format!("{}h", h),
+        (h, m) => format!("{}h {}m", h, m),
+    }
+}
+
+/// Reads durations as written by `format_duration`, also `1:30`, `1:30:00` and decimal hours
+/// like `1.5`.
+pub fn parse_duration(text: &str) -> Option<u64> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Some(0);
+    }
+    if text.contains(':') {
+        let parts = text
+            .split(':')
+            .map(|part| part.trim().parse::<u64>().ok())
+            .collect::<Option<Vec<_>>>()?;
+        return match parts[..] {
+            [hours, minutes] => Some(hours * 3600 + minutes * 60),
+            [hours, minutes, seconds] => Some(hours * 3600 + minutes * 60 + seconds),
+            _ => None,
+        };
+    }
+    if let Ok(hours) = text.parse::<f64>() {
+        return (hours >= 0.0).then_some((hours * 3600.0).round() as u64);
+    }
+
+    let mut seconds = 0;
+    for part in text.split_whitespace() {
+        let (number, unit) = part.split_at(part.find(|c: char| !c.is_ascii_digit())?);
+        let number = number.parse::<u64>().ok()?;
+        seconds += match unit {
+            "h" => number * 3600,
+            "m" => number * 60,
+            "s" => number,
+            _ => return None,
+        };
+    }
+    Some(seconds)
+}
+
+/// Time in the finished worklogs of an issue on one local day.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct DailyTotal {
+    pub issue_name: String,
+    pub issue_summary: String,
+    pub day: String, // YYYY-MM-DD
+    pub seconds: u64,
+}
+
+/// What the report adds time up by.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportGrouping {
+    #[default]
+    Day,
+    Week,
+    Month,
+    Issue,
+    Project,
+    Tag,
+    Assignee,
+}
+
+impl ReportGrouping {
+    pub const ALL: [ReportGrouping; 7] = [
+        ReportGrouping::Day,
+        ReportGrouping::Week,
+        ReportGrouping::Month,
+        ReportGrouping::Issue,
+        ReportGrouping::Project,
+        ReportGrouping::Tag,
+        ReportGrouping::Assignee,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ReportGrouping::Day => "day",
+            ReportGrouping::Week => "week",
+            ReportGrouping::Month => "month",
+            ReportGrouping::Issue => "issue",
+            ReportGrouping::Project => "project",
+            ReportGrouping::Tag => "tag",
+            ReportGrouping::Assignee => "assignee",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct ReportRow {
+    pub key: String,
+    pub seconds: u64,
+    pub percent: f64, // of the report total, rows by tag can add up to more than 100
+}
+
+/// Time in the finished worklogs started in `[from, to)`, grouped one way.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+pub struct Report {
+    pub from: u64,
+    pub to: u64,
+    pub grouping: ReportGrouping,
+    pub total: u64,
+    pub rows: Vec<ReportRow>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, ImplicitClone, Clone)]
+pub struct OutboxItem {
+    pub id: i64,
+    pub kind: String, // create, update or delete
+    pub worklog_id: String,
+    pub issue_key: String,
+    pub attempts: u64,
+    pub last_error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+pub struct OutboxStatus {
+    pub pending: u64,
+    pub failed: Vec<OutboxItem>,
+}
+
+/// Worklog left open by a run that ended without stopping it.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct OpenWorklog {
+    pub worklog: Worklog,
+    pub issue_name: String,
+    pub issue_summary: String,
+    pub last_heartbeat: u64, // last time the app saw it running, the start when never
+}
+
+/// Time nobody touched the computer while an issue was tracked.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct IdlePeriod {
+    pub start: u64,
+    pub end: Option<u64>, // None while still away
+}
+
+/// An issue whose cached total no longer matches its worklogs.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct TotalDrift {
+    pub issue_name: String,
+    pub recorded: u64,
+    pub worklogs: u64,
+}
+
+/// What is tracked right now, the payload of the tracking events.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+pub struct TrackingState {
+    pub issue_name: Option<String>,
+    pub elapsed: u64, // seconds of the session, without the pauses
+    pub paused: bool,
+    pub idle: Option<IdlePeriod>,
+}
+
+/// What to do with the idle time once the user is back.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub enum IdleAction {
+    Keep,
+    Discard,
+    Assign(String), // key of the issue the time was spent on
+}
+
 // #[cfg(test)]
 // mod tests {
 //     use super::*;
@@ -0,0 +1,3 @@
+-- Add migration script here
+alter table worklog add column remote_id text;
+alter table worklog add column synced integer not null default 0;
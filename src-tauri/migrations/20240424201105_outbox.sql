@@ -0,0 +1,14 @@
+-- Add migration script here
+create table if not exists outbox
+(
+    id           integer primary key autoincrement not null,
+    kind         text    not null,
+    worklog_id   text    not null,
+    issue_key    text    not null,
+    remote_id    text,
+    payload      text    not null,
+    attempts     integer not null default 0,
+    next_attempt integer not null default 0,
+    failed       integer not null default 0,
+    last_error   text
+);
@@ -0,0 +1,3 @@
+-- Add migration script here
+alter table issue add column source text not null default 'local';
+alter table issue add column stale integer not null default 0;
@@ -0,0 +1,6 @@
+-- Add migration script here
+alter table issue add column status text not null default '';
+alter table issue add column project text not null default '';
+alter table issue add column issue_type text not null default '';
+alter table issue add column time_estimate integer not null default 0;
+alter table issue add column remote_time_spent integer not null default 0;
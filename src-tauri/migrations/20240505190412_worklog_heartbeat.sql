@@ -0,0 +1,2 @@
+-- Add migration script here
+alter table worklog add column heartbeat integer not null default 0;
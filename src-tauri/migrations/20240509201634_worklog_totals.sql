@@ -0,0 +1,41 @@
+-- Add migration script here
+-- issue.time_tracked_all caches the time in the finished worklogs of the issue, the triggers keep it current
+update issue
+set time_tracked_all = (
+    select cast(coalesce(sum(cast(total_time as integer)), 0) as text)
+    from worklog
+    where worklog.issue_id = issue.id and worklog.end != '0'
+);
+
+create trigger if not exists worklog_total_insert after insert on worklog
+begin
+    update issue
+    set time_tracked_all = (
+        select cast(coalesce(sum(cast(total_time as integer)), 0) as text)
+        from worklog
+        where worklog.issue_id = new.issue_id and worklog.end != '0'
+    )
+    where id = new.issue_id;
+end;
+
+create trigger if not exists worklog_total_update after update of issue_id, end, total_time on worklog
+begin
+    update issue
+    set time_tracked_all = (
+        select cast(coalesce(sum(cast(total_time as integer)), 0) as text)
+        from worklog
+        where worklog.issue_id = issue.id and worklog.end != '0'
+    )
+    where id in (old.issue_id, new.issue_id);
+end;
+
+create trigger if not exists worklog_total_delete after delete on worklog
+begin
+    update issue
+    set time_tracked_all = (
+        select cast(coalesce(sum(cast(total_time as integer)), 0) as text)
+        from worklog
+        where worklog.issue_id = old.issue_id and worklog.end != '0'
+    )
+    where id = old.issue_id;
+end;
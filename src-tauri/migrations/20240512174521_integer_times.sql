@@ -0,0 +1,88 @@
+-- Add migration script here
+-- the times were stored as text, the tables are rebuilt with integer columns. sqlx runs this
+-- in a transaction with foreign keys on, so the worklogs move to a table pointing at the new
+-- issue table before the old tables go. Worklogs of missing issues get a placeholder issue
+-- named after the missing id, no tracked time is dropped
+create table issue_new
+(
+    id                text    primary key not null,
+    name              text    unique not null,
+    url               text    not null,
+    summary           text    not null,
+    assignee_email    text    not null,
+    time_tracked_all  integer not null default 0,
+    source            text    not null default 'local',
+    stale             integer not null default 0,
+    status            text    not null default '',
+    project           text    not null default '',
+    issue_type        text    not null default '',
+    time_estimate     integer not null default 0,
+    remote_time_spent integer not null default 0
+);
+
+insert into issue_new(id, name, url, summary, assignee_email, time_tracked_all, source, stale,
+                      status, project, issue_type, time_estimate, remote_time_spent)
+select id, name, url, summary, assignee_email, cast(time_tracked_all as integer), source, stale,
+       status, project, issue_type, time_estimate, remote_time_spent
+from issue;
+
+insert into issue_new(id, name, url, summary, assignee_email, time_tracked_all)
+select issue_id, 'missing-' || issue_id, '', 'Issue missing when the times were migrated', '',
+       coalesce(sum(case when end != '0' then cast(total_time as integer) end), 0)
+from worklog
+where issue_id not in (select id from issue)
+group by issue_id;
+
+create table worklog_new
+(
+    id         text    primary key not null,
+    issue_id   text    not null,
+    start      integer not null,
+    end        integer not null,
+    total_time integer not null,
+    remote_id  text,
+    synced     integer not null default 0,
+    heartbeat  integer not null default 0,
+    foreign key(issue_id) references issue_new(id)
+);
+
+insert into worklog_new(id, issue_id, start, end, total_time, remote_id, synced, heartbeat)
+select id, issue_id, cast(start as integer), cast(end as integer), cast(total_time as integer),
+       remote_id, synced, heartbeat
+from worklog;
+
+drop table worklog;
+drop table issue;
+alter table issue_new rename to issue;
+alter table worklog_new rename to worklog;
+
+create index if not exists worklog_issue_id on worklog(issue_id);
+create index if not exists worklog_start on worklog(start);
+
+-- issue.time_tracked_all caches the time in the finished worklogs of the issue
+create trigger if not exists worklog_total_insert after insert on worklog
+begin
+    update issue
+    set time_tracked_all = (
+        select coalesce(sum(total_time), 0) from worklog where worklog.issue_id = new.issue_id and worklog.end != 0
+    )
+    where id = new.issue_id;
+end;
+
+create trigger if not exists worklog_total_update after update of issue_id, end, total_time on worklog
+begin
+    update issue
+    set time_tracked_all = (
+        select coalesce(sum(total_time), 0) from worklog where worklog.issue_id = issue.id and worklog.end != 0
+    )
+    where id in (old.issue_id, new.issue_id);
+end;
+
+create trigger if not exists worklog_total_delete after delete on worklog
+begin
+    update issue
+    set time_tracked_all = (
+        select coalesce(sum(total_time), 0) from worklog where worklog.issue_id = old.issue_id and worklog.end != 0
+    )
+    where id = old.issue_id;
+end;
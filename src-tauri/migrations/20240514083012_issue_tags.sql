@@ -0,0 +1,3 @@
+-- Add migration script here
+-- labels from the tracker, comma separated
+alter table issue add column tags text not null default '';
@@ -0,0 +1,12 @@
+-- Add migration script here
+-- worklogs read from files, undone one batch at a time
+create table if not exists import_batch (
+    id text primary key not null,
+    created integer not null,
+    source text not null
+);
+
+alter table worklog add column import_batch text references import_batch(id);
+alter table issue add column import_batch text references import_batch(id);
+
+create index if not exists worklog_import_batch on worklog(import_batch);
@@ -0,0 +1,4 @@
+-- Add migration script here
+-- the tray lists the recent issues in the order they were tracked, rows were inserted in that order
+alter table recent_issue add column position integer not null default 0;
+update recent_issue set position = rowid;
@@ -0,0 +1,797 @@
+use std::{
+    collections::{HashMap, HashSet},
+    net::Ipv4Addr,
+    sync::Arc,
+    time::Duration,
+};
+
+use chrono::{Datelike, Days, Local, NaiveDate, NaiveDateTime, TimeZone, Utc, Weekday};
+use chrono_tz::Tz;
+use shared::{CalendarConfig, SuggestedWorklog};
+use tauri::State;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+use crate::{export::FileZone, storage::IssueWorklog, sync, worklogs, MangedState};
+
+const FEED_POLL: Duration = Duration::from_secs(5);
+const FEED_PATH: &str = "/worklogs.ics";
+
+/// An iCalendar file with one event per worklog, named `KEY: summary`.
+pub fn to_ics(worklogs: &[IssueWorklog], now: u64) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//trackTime//worklogs//EN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+        "X-WR-CALNAME:Tracked time".to_string(),
+    ];
+
+    for entry in worklogs {
+        lines.push("BEGIN:VEVENT".to_string());
+        lines.push(format!("UID:{}@tracktime", entry.worklog.id));
+        lines.push(format!("DTSTAMP:{}", utc_time(now)));
+        lines.push(format!("DTSTART:{}", utc_time(entry.worklog.start)));
+        lines.push(format!("DTEND:{}", utc_time(entry.worklog.end)));
+        lines.push(format!("SUMMARY:{}", escape(&format!("{}: {}", entry.issue.name, entry.issue.summary))));
+        if !entry.issue.url.is_empty() {
+            lines.push(format!("URL:{}", escape(&entry.issue.url)));
+        }
+        lines.push("END:VEVENT".to_string());
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    lines.iter().map(|line| fold(line)).collect()
+}
+
+// e.g. 20240517T083000Z
+fn utc_time(seconds: u64) -> String {
+    Utc.timestamp_opt(seconds as i64, 0)
+        .single()
+        .map(|time| time.format("%Y%m%dT%H%M%SZ").to_string())
+        .unwrap_or_default()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+// lines are at most 75 bytes, longer ones go on in lines starting with a space
+fn fold(line: &str) -> String {
+    let mut folded = String::new();
+    let mut length = 0;
+    for c in line.chars() {
+        if length + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            length = 1;
+        }
+        folded.push(c);
+        length += c.len_utf8();
+    }
+    folded.push_str("\r\n");
+    folded
+}
+
+/// Background task serving the worklogs of the last days to calendar apps while the feed is
+/// on, it follows the config when the feed is turned off or moved to another port.
+pub async fn run_calendar_feed(app_state: Arc<MangedState>) {
+    loop {
+        let config = app_state.0.lock().await.config.calendar.clone();
+        if !config.feed {
+            tokio::time::sleep(FEED_POLL).await;
+            continue;
+        }
+
+        // loopback only, nothing on the network can read the worklogs
+        let listener = match TcpListener::bind((Ipv4Addr::LOCALHOST, config.port)).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                log::error!("calendar feed on port {}: {}", config.port, e);
+                tokio::time::sleep(FEED_POLL).await;
+                continue;
+            }
+        };
+
+        loop {
+            tokio::select! {
+                accepted = listener.accept() => match accepted {
+                    Ok((stream, _)) => {
+                        tokio::spawn(serve(stream, app_state.clone(), config.clone()));
+                    }
+                    Err(e) => log::error!("calendar feed: {}", e),
+                },
+                _ = tokio::time::sleep(FEED_POLL) => {
+                    let current = app_state.0.lock().await.config.calendar.clone();
+                    if (current.feed, current.port, current.days) != (config.feed, config.port, config.days) {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+}
+
+async fn serve(mut stream: TcpStream, app_state: Arc<MangedState>, config: CalendarConfig) {
+    let response = match read_request(&mut stream).await {
+        Ok(request) => respond(&request, &app_state, &config).await,
+        Err(e) => reply("400 Bad Request", "text/plain", e),
+    };
+    if let Err(e) = stream.write_all(&response).await {
+        log::error!("calendar feed: {}", e);
+    }
+}
+
+// the head of the request, the feed takes no body
+async fn read_request(stream: &mut TcpStream) -> Result<String, String> {
+    let mut head = Vec::new();
+    let mut buffer = [0; 1024];
+    while !head.windows(4).any(|end| end == b"\r\n\r\n") {
+        if head.len() > 16 * 1024 {
+            return Err("the request is too large".to_string());
+        }
+        let read = stream.read(&mut buffer).await.map_err(|e| e.to_string())?;
+        if read == 0 {
+            return Err("the request ended early".to_string());
+        }
+        head.extend_from_slice(&buffer[..read]);
+    }
+    String::from_utf8(head).map_err(|e| e.to_string())
+}
+
+async fn respond(request: &str, app_state: &MangedState, config: &CalendarConfig) -> Vec<u8> {
+    let mut lines = request.lines();
+    let mut first = lines.next().unwrap_or_default().split_whitespace();
+    let (method, target) = (first.next().unwrap_or_default(), first.next().unwrap_or_default());
+
+    // a web page can't reach the feed through a name of its own pointing at 127.0.0.1
+    let host = lines
+        .find_map(|line| line.split_once(':').filter(|(name, _)| name.eq_ignore_ascii_case("host")))
+        .map(|(_, host)| host.trim())
+        .unwrap_or_default();
+    let (name, port) = host.split_once(':').unwrap_or((host, "80"));
+    if !["127.0.0.1", "localhost"].contains(&name) || port.parse::<u16>().ok() != Some(config.port) {
+        return reply("403 Forbidden", "text/plain", "use the feed url from the settings".to_string());
+    }
+    if method != "GET" {
+        return reply("405 Method Not Allowed", "text/plain", "only GET is supported".to_string());
+    }
+    if target.split('?').next() != Some(FEED_PATH) {
+        return reply("404 Not Found", "text/plain", format!("the feed is at {}", FEED_PATH));
+    }
+
+    match feed(app_state, config.days).await {
+        Ok(ics) => reply("200 OK", "text/calendar; charset=utf-8", ics),
+        Err(e) => reply("500 Internal Server Error", "text/plain", e),
+    }
+}
+
+// the finished worklogs from midnight `days - 1` days ago until now
+async fn feed(app_state: &MangedState, days: u64) -> Result<String, String> {
+    let now = sync::now()? as u64;
+    let from = (Local::now().date_naive() - Days::new(days.saturating_sub(1)))
+        .and_hms_opt(0, 0, 0)
+        .and_then(|midnight| midnight.and_local_timezone(Local).earliest())
+        .map(|midnight| midnight.timestamp() as u64)
+        .unwrap_or_default();
+
+    let worklogs = {
+        let m = app_state.0.lock().await;
+        m.storage.get_issue_worklogs_between(from, now + 1).await?
+    };
+    Ok(to_ics(&worklogs, now))
+}
+
+fn reply(status: &str, content_type: &str, body: String) -> Vec<u8> {
+    format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )
+    .into_bytes()
+}
+
+/// Worklogs for the events of an iCalendar file that started in `[from, to)` and are over, the
+/// issue comes from the calendar rules. Nothing is written, accepted ones are added like
+/// manual worklogs.
+#[tauri::command(async)]
+pub async fn suggest_worklogs(
+    contents: String,
+    from: u64,
+    to: u64,
+    app_state: State<'_, Arc<MangedState>>,
+) -> Result<Vec<SuggestedWorklog>, String> {
+    let m = app_state.0.lock().await;
+    let now = sync::now()? as u64;
+
+    let mut suggestions = Vec::new();
+    for event in read_events(&contents, from, to.min(now)) {
+        if event.end > now || event.end <= event.start {
+            continue;
+        }
+
+        let issue_name = m
+            .config
+            .calendar
+            .rules
+            .iter()
+            .find(|rule| rule.matches(&event.title))
+            .map(|rule| rule.issue.trim().to_string());
+        suggestions.push(SuggestedWorklog {
+            id: format!("{}/{}", event.uid, event.start),
+            title: event.title,
+            start: event.start,
+            end: event.end,
+            issue_name,
+            conflict: worklogs::check_overlap(&m, "", event.start, event.end).await.err(),
+        });
+    }
+
+    suggestions.sort_by_key(|suggestion| suggestion.start);
+    Ok(suggestions)
+}
+
+struct Event {
+    uid: String,
+    title: String,
+    start: u64,
+    end: u64,
+}
+
+struct Property {
+    name: String,
+    params: Vec<(String, String)>,
+    value: String,
+}
+
+/// A VEVENT as written in the file, recurring ones stand for all their occurrences.
+#[derive(Default)]
+struct VEvent {
+    uid: String,
+    title: String,
+    start: Option<IcsTime>,
+    end: Option<IcsTime>,
+    duration: Option<u64>,
+    rule: Option<String>,
+    excluded: HashSet<u64>,
+    excluded_days: HashSet<NaiveDate>, // from EXDATE;VALUE=DATE, any occurrence that day
+    recurrence_id: Option<u64>,        // replaces this occurrence of the recurring event with the uid
+    cancelled: bool,
+}
+
+#[derive(Clone, Copy)]
+struct IcsTime {
+    time: NaiveDateTime,
+    zone: FileZone, // UTC for times ending in Z, the TZID otherwise, floating times are local
+    all_day: bool,
+}
+
+impl IcsTime {
+    fn read(params: &[(String, String)], value: &str) -> Option<Self> {
+        let all_day = value.len() == 8 || params.iter().any(|(name, value)| name == "VALUE" && value == "DATE");
+        let zone = match params.iter().find(|(name, _)| name == "TZID") {
+            _ if value.ends_with('Z') => FileZone::Named(Tz::UTC),
+            // zones only defined in the file itself, e.g. Windows names, are read as local
+            Some((_, zone)) => zone.trim_start_matches('/').parse::<Tz>().map_or(FileZone::Local, FileZone::Named),
+            None => FileZone::Local,
+        };
+        let value = value.trim_end_matches('Z');
+
+        let time = match all_day {
+            true => NaiveDate::parse_from_str(&value[..value.len().min(8)], "%Y%m%d").ok()?.and_hms_opt(0, 0, 0)?,
+            false => NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()?,
+        };
+        Some(IcsTime { time, zone, all_day })
+    }
+
+    fn unix(&self, time: NaiveDateTime) -> Option<u64> {
+        self.zone.seconds(&time).map(|seconds| seconds as u64)
+    }
+
+    // the day on the clock of the zone
+    fn day(&self, seconds: u64) -> Option<NaiveDate> {
+        self.zone.time(seconds).map(|time| time.date_naive())
+    }
+}
+
+/// The timed events of the file started in `[from, to)`, recurring ones once per occurrence.
+/// All-day and cancelled events are left out.
+fn read_events(contents: &str, from: u64, to: u64) -> Vec<Event> {
+    let vevents = read_vevents(contents);
+    let replaced: HashSet<(String, u64)> = vevents
+        .iter()
+        .filter_map(|vevent| Some((vevent.uid.clone(), vevent.recurrence_id?)))
+        .collect();
+
+    let mut events = Vec::new();
+    for vevent in vevents {
+        let start = match vevent.start {
+            Some(start) if !start.all_day && !vevent.cancelled => start,
+            _ => continue,
+        };
+        let first = match start.unix(start.time) {
+            Some(first) => first,
+            None => continue,
+        };
+        let length = match (vevent.end.and_then(|end| end.unix(end.time)), vevent.duration) {
+            (Some(end), _) => end.saturating_sub(first),
+            (None, Some(duration)) => duration,
+            _ => continue,
+        };
+
+        let begins = match (&vevent.rule, vevent.recurrence_id) {
+            (Some(rule), None) => occurrences(&start, rule, from, to),
+            _ => vec![first],
+        };
+        for begin in begins {
+            if begin < from || begin >= to || vevent.excluded.contains(&begin) {
+                continue;
+            }
+            if start.day(begin).is_some_and(|day| vevent.excluded_days.contains(&day)) {
+                continue;
+            }
+            if vevent.recurrence_id.is_none() && replaced.contains(&(vevent.uid.clone(), begin)) {
+                continue;
+            }
+            events.push(Event {
+                uid: vevent.uid.clone(),
+                title: vevent.title.clone(),
+                start: begin,
+                end: begin + length,
+            });
+        }
+    }
+
+    events
+}
+
+fn read_vevents(contents: &str) -> Vec<VEvent> {
+    // long lines go on in lines starting with a space or a tab
+    let unfolded = contents.replace("\r\n", "\n").replace("\n ", "").replace("\n\t", "");
+
+    let mut vevents = Vec::new();
+    let mut current: Option<VEvent> = None;
+    let mut nested = 0; // alarms and the like inside the event
+    for line in unfolded.lines() {
+        let vevent = match (line, &mut current) {
+            ("BEGIN:VEVENT", _) => {
+                current = Some(VEvent::default());
+                nested = 0;
+                continue;
+            }
+            ("END:VEVENT", _) => {
+                vevents.extend(current.take());
+                continue;
+            }
+            (_, Some(vevent)) => vevent,
+            (_, None) => continue,
+        };
+        if line.starts_with("BEGIN:") {
+            nested += 1;
+        } else if line.starts_with("END:") {
+            nested -= 1;
+        }
+        if nested > 0 || line.starts_with("END:") {
+            continue;
+        }
+
+        let property = match read_property(line) {
+            Some(property) => property,
+            None => continue,
+        };
+        match property.name.as_str() {
+            "UID" => vevent.uid = property.value,
+            "SUMMARY" => vevent.title = unescape(&property.value),
+            "DTSTART" => vevent.start = IcsTime::read(&property.params, &property.value),
+            "DTEND" => vevent.end = IcsTime::read(&property.params, &property.value),
+            "DURATION" => vevent.duration = read_duration(&property.value),
+            "RRULE" => vevent.rule = Some(property.value),
+            "STATUS" => vevent.cancelled = property.value.eq_ignore_ascii_case("CANCELLED"),
+            "RECURRENCE-ID" => {
+                vevent.recurrence_id = IcsTime::read(&property.params, &property.value).and_then(|time| time.unix(time.time))
+            }
+            "EXDATE" => {
+                for time in property.value.split(',').filter_map(|value| IcsTime::read(&property.params, value)) {
+                    if time.all_day {
+                        vevent.excluded_days.insert(time.time.date());
+                    } else {
+                        vevent.excluded.extend(time.unix(time.time));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    vevents
+}
+
+// NAME;PARAM=value;PARAM="quoted: value":value
+fn read_property(line: &str) -> Option<Property> {
+    let mut quoted = false;
+    let colon = line.char_indices().find_map(|(i, c)| match c {
+        '"' => {
+            quoted = !quoted;
+            None
+        }
+        ':' if !quoted => Some(i),
+        _ => None,
+    })?;
+
+    let mut parts = line[..colon].split(';');
+    let name = parts.next()?.trim().to_uppercase();
+    let params = parts
+        .filter_map(|param| param.split_once('='))
+        .map(|(name, value)| (name.trim().to_uppercase(), value.trim_matches('"').to_string()))
+        .collect();
+
+    Some(Property {
+        name,
+        params,
+        value: line[colon + 1..].trim().to_string(),
+    })
+}
+
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('n' | 'N') => unescaped.push('\n'),
+                Some(other) => unescaped.push(other),
+                None => {}
+            },
+            c => unescaped.push(c),
+        }
+    }
+    unescaped
+}
+
+// seconds of e.g. PT1H30M or P1D
+fn read_duration(value: &str) -> Option<u64> {
+    let value = value.trim_start_matches(['+', '-']).strip_prefix('P')?;
+    let mut seconds = 0;
+    let mut number = String::new();
+    for c in value.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'T' => {}
+            unit => {
+                let n = number.parse::<u64>().ok()?;
+                number.clear();
+                seconds += n * match unit {
+                    'W' => 7 * 24 * 3600,
+                    'D' => 24 * 3600,
+                    'H' => 3600,
+                    'M' => 60,
+                    'S' => 1,
+                    _ => return None,
+                };
+            }
+        }
+    }
+    Some(seconds)
+}
+
+/// Unix starts of a recurring event until `to`. Daily and weekly rules and monthly ones on the
+/// day of the month are followed, other events keep only their first start. The days are walked
+/// from around `from`, or from the first start when the rule counts its occurrences.
+fn occurrences(start: &IcsTime, rule: &str, from: u64, to: u64) -> Vec<u64> {
+    let parts: HashMap<&str, &str> = rule.split(';').filter_map(|part| part.split_once('=')).collect();
+    let first_start = start.unix(start.time).into_iter().collect();
+    let supported = match parts.get("FREQ").copied() {
+        Some("DAILY" | "WEEKLY") => true,
+        Some("MONTHLY") => !parts.contains_key("BYDAY"),
+        _ => false,
+    };
+    if !supported {
+        return first_start;
+    }
+
+    let interval = parts.get("INTERVAL").and_then(|n| n.parse::<i64>().ok()).unwrap_or(1).max(1);
+    let count = parts.get("COUNT").and_then(|n| n.parse::<usize>().ok());
+    let until = parts.get("UNTIL").and_then(|until| {
+        let read = IcsTime::read(&[], until)?;
+        // a date includes its whole day, a time without Z is on the clock of the start
+        let time = match read.all_day {
+            true => read.time.date().and_hms_opt(23, 59, 59)?,
+            false => read.time,
+        };
+        match until.ends_with('Z') {
+            true => read.unix(time),
+            false => start.unix(time),
+        }
+    });
+    let week_days: Vec<Weekday> = match parts.get("BYDAY") {
+        Some(days) => days
+            .split(',')
+            .filter_map(|day| match day.trim_start_matches(|c: char| c == '+' || c == '-' || c.is_ascii_digit()) {
+                "MO" => Some(Weekday::Mon),
+                "TU" => Some(Weekday::Tue),
+                "WE" => Some(Weekday::Wed),
+                "TH" => Some(Weekday::Thu),
+                "FR" => Some(Weekday::Fri),
+                "SA" => Some(Weekday::Sat),
+                "SU" => Some(Weekday::Sun),
+                _ => None,
+            })
+            .collect(),
+        None => vec![start.time.weekday()],
+    };
+
+    let first = start.time.date();
+    let first_monday = first - Days::new(first.weekday().num_days_from_monday() as u64);
+    let follows = |day: NaiveDate| match parts.get("FREQ").copied() {
+        Some("DAILY") => (day - first).num_days() % interval == 0,
+        Some("WEEKLY") => ((day - first_monday).num_days() / 7) % interval == 0 && week_days.contains(&day.weekday()),
+        _ => {
+            let months = (day.year() - first.year()) as i64 * 12 + day.month() as i64 - first.month() as i64;
+            day.day() == first.day() && months % interval == 0
+        }
+    };
+
+    let mut day = match (count, start.day(from)) {
+        // a day early, the clock of the zone may be behind
+        (None, Some(from_day)) => first.max(from_day - Days::new(1)),
+        _ => first,
+    };
+    let mut found = Vec::new();
+    let mut counted = 0;
+    loop {
+        if count.is_some_and(|count| counted >= count) {
+            break;
+        }
+        // None in the hour skipped when the clocks go forward
+        if let Some(begin) = start.unix(day.and_time(start.time.time())) {
+            if begin >= to || until.is_some_and(|until| begin > until) {
+                break;
+            }
+            if follows(day) {
+                counted += 1;
+                found.push(begin);
+            }
+        }
+        day = match day.succ_opt() {
+            Some(next) => next,
+            None => break,
+        };
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use shared::{Issue, Worklog};
+
+    use super::*;
+
+    // unix seconds of a UTC time written like in the file
+    fn utc(time: &str) -> u64 {
+        NaiveDateTime::parse_from_str(time, "%Y%m%dT%H%M%S").unwrap().and_utc().timestamp() as u64
+    }
+
+    /// Title, start and end of a read event.
+    type Read = (String, u64, u64);
+
+    fn calendar(events: &[&str]) -> String {
+        let events: Vec<String> = events.iter().map(|event| format!("BEGIN:VEVENT\r\n{}\r\nEND:VEVENT\r\n", event)).collect();
+        format!("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n{}END:VCALENDAR\r\n", events.concat())
+    }
+
+    /// The events of May 2024.
+    fn events_of_may(events: &[&str]) -> Vec<Read> {
+        events_of_may_in(&calendar(events))
+    }
+
+    fn events_of_may_in(contents: &str) -> Vec<Read> {
+        read_events(contents, utc("20240501T000000"), utc("20240601T000000"))
+            .into_iter()
+            .map(|event| (event.title, event.start, event.end))
+            .collect()
+    }
+
+    fn event(title: &str, start: &str, end: &str) -> Read {
+        (title.to_string(), utc(start), utc(end))
+    }
+
+    #[test]
+    fn reads_events() {
+        let cases: Vec<(&str, Vec<&str>, Vec<Read>)> = vec![
+            (
+                "folded lines",
+                vec!["UID:1\r\nSUMMARY:Plan\r\n ning\\, rev\r\n\tiew\r\nDTSTART:20240502T090000Z\r\nDTEND:20240502T100000Z"],
+                vec![event("Planning, review", "20240502T090000", "20240502T100000")],
+            ),
+            (
+                "duration instead of an end",
+                vec!["UID:1\r\nSUMMARY:Standup\r\nDTSTART:20240502T090000Z\r\nDURATION:PT15M"],
+                vec![event("Standup", "20240502T090000", "20240502T091500")],
+            ),
+            (
+                "all-day events are left out",
+                vec![
+                    "UID:1\r\nSUMMARY:Holiday\r\nDTSTART;VALUE=DATE:20240501\r\nDTEND;VALUE=DATE:20240502",
+                    "UID:2\r\nSUMMARY:Offsite\r\nDTSTART:20240503\r\nDTEND:20240504",
+                ],
+                vec![],
+            ),
+            (
+                "cancelled events are left out",
+                vec!["UID:1\r\nSUMMARY:Gone\r\nSTATUS:CANCELLED\r\nDTSTART:20240502T090000Z\r\nDTEND:20240502T100000Z"],
+                vec![],
+            ),
+            (
+                "times in a named zone",
+                vec![
+                    "UID:1\r\nSUMMARY:Berlin\r\nDTSTART;TZID=Europe/Berlin:20240502T090000\r\nDTEND;TZID=Europe/Berlin:20240502T100000",
+                    "UID:2\r\nSUMMARY:New York\r\nDTSTART;TZID=\"America/New_York\":20240502T090000\r\nDTEND;TZID=America/New_York:20240502T093000",
+                ],
+                vec![
+                    event("Berlin", "20240502T070000", "20240502T080000"),
+                    event("New York", "20240502T130000", "20240502T133000"),
+                ],
+            ),
+            (
+                "weekly on two days, counted",
+                vec!["UID:1\r\nSUMMARY:Sync\r\nDTSTART:20240506T090000Z\r\nDTEND:20240506T093000Z\r\nRRULE:FREQ=WEEKLY;BYDAY=MO,WE;COUNT=3"],
+                vec![
+                    event("Sync", "20240506T090000", "20240506T093000"),
+                    event("Sync", "20240508T090000", "20240508T093000"),
+                    event("Sync", "20240513T090000", "20240513T093000"),
+                ],
+            ),
+            (
+                "weekly on two days, until a time",
+                vec!["UID:1\r\nSUMMARY:Sync\r\nDTSTART:20240506T090000Z\r\nDTEND:20240506T093000Z\r\nRRULE:FREQ=WEEKLY;BYDAY=MO,WE;UNTIL=20240513T090000Z"],
+                vec![
+                    event("Sync", "20240506T090000", "20240506T093000"),
+                    event("Sync", "20240508T090000", "20240508T093000"),
+                    event("Sync", "20240513T090000", "20240513T093000"),
+                ],
+            ),
+            (
+                "every other week until a date",
+                vec!["UID:1\r\nSUMMARY:Retro\r\nDTSTART:20240503T140000Z\r\nDTEND:20240503T150000Z\r\nRRULE:FREQ=WEEKLY;INTERVAL=2;UNTIL=20240531"],
+                vec![
+                    event("Retro", "20240503T140000", "20240503T150000"),
+                    event("Retro", "20240517T140000", "20240517T150000"),
+                    event("Retro", "20240531T140000", "20240531T150000"),
+                ],
+            ),
+            (
+                "an excluded occurrence",
+                vec!["UID:1\r\nSUMMARY:Standup\r\nDTSTART:20240506T090000Z\r\nDTEND:20240506T091500Z\r\nRRULE:FREQ=DAILY;COUNT=3\r\nEXDATE:20240507T090000Z"],
+                vec![
+                    event("Standup", "20240506T090000", "20240506T091500"),
+                    event("Standup", "20240508T090000", "20240508T091500"),
+                ],
+            ),
+            (
+                "an excluded day",
+                vec!["UID:1\r\nSUMMARY:Standup\r\nDTSTART:20240506T090000Z\r\nDTEND:20240506T091500Z\r\nRRULE:FREQ=DAILY;COUNT=2\r\nEXDATE;VALUE=DATE:20240506"],
+                vec![event("Standup", "20240507T090000", "20240507T091500")],
+            ),
+            (
+                "an overridden occurrence",
+                vec![
+                    "UID:1\r\nSUMMARY:Standup\r\nDTSTART:20240506T090000Z\r\nDTEND:20240506T091500Z\r\nRRULE:FREQ=DAILY;COUNT=3",
+                    "UID:1\r\nSUMMARY:Late standup\r\nRECURRENCE-ID:20240507T090000Z\r\nDTSTART:20240507T110000Z\r\nDTEND:20240507T111500Z",
+                ],
+                vec![
+                    event("Standup", "20240506T090000", "20240506T091500"),
+                    event("Standup", "20240508T090000", "20240508T091500"),
+                    event("Late standup", "20240507T110000", "20240507T111500"),
+                ],
+            ),
+            (
+                "a rule started years before",
+                vec!["UID:1\r\nSUMMARY:Monthly\r\nDTSTART;TZID=Europe/Berlin:20200115T100000\r\nDTEND;TZID=Europe/Berlin:20200115T110000\r\nRRULE:FREQ=MONTHLY"],
+                vec![event("Monthly", "20240515T080000", "20240515T090000")],
+            ),
+        ];
+
+        for (name, vevents, expected) in cases {
+            assert_eq!(events_of_may(&vevents), expected, "{}", name);
+        }
+    }
+
+    #[test]
+    fn daily_rule_follows_daylight_saving_time_of_its_zone() {
+        // Berlin goes to summer time on the 31st of March
+        let contents = calendar(&["UID:1\r\nSUMMARY:Standup\r\nDTSTART;TZID=Europe/Berlin:20240330T090000\r\nDTEND;TZID=Europe/Berlin:20240330T091500\r\nRRULE:FREQ=DAILY"]);
+        let starts: Vec<u64> = read_events(&contents, utc("20240330T000000"), utc("20240401T000000"))
+            .into_iter()
+            .map(|event| event.start)
+            .collect();
+        assert_eq!(starts, vec![utc("20240330T080000"), utc("20240331T070000")]);
+    }
+
+    fn issue_worklog(id: &str, summary: &str, url: &str, start: &str, end: &str) -> IssueWorklog {
+        let (start, end) = (utc(start), utc(end));
+        IssueWorklog {
+            worklog: Worklog::new(id.to_string(), "10".to_string(), start, end, end - start),
+            issue: Issue::new("10".to_string(), "ABC-1".to_string(), url.to_string(), summary.to_string(), String::new(), 0),
+        }
+    }
+
+    #[test]
+    fn writes_one_event_per_worklog() {
+        let worklogs = [
+            issue_worklog("w1", "Fix login", "https://tracker/ABC-1", "20240502T090000", "20240502T103000"),
+            issue_worklog("w2", "Fix login", "", "20240502T130000", "20240502T131500"),
+        ];
+        let ics = to_ics(&worklogs, utc("20240503T120000"));
+
+        assert_eq!(
+            ics,
+            "BEGIN:VCALENDAR\r\n\
+             VERSION:2.0\r\n\
+             PRODID:-//trackTime//worklogs//EN\r\n\
+             CALSCALE:GREGORIAN\r\n\
+             X-WR-CALNAME:Tracked time\r\n\
+             BEGIN:VEVENT\r\n\
+             UID:w1@tracktime\r\n\
+             DTSTAMP:20240503T120000Z\r\n\
+             DTSTART:20240502T090000Z\r\n\
+             DTEND:20240502T103000Z\r\n\
+             SUMMARY:ABC-1: Fix login\r\n\
+             URL:https://tracker/ABC-1\r\n\
+             END:VEVENT\r\n\
+             BEGIN:VEVENT\r\n\
+             UID:w2@tracktime\r\n\
+             DTSTAMP:20240503T120000Z\r\n\
+             DTSTART:20240502T130000Z\r\n\
+             DTEND:20240502T131500Z\r\n\
+             SUMMARY:ABC-1: Fix login\r\n\
+             END:VEVENT\r\n\
+             END:VCALENDAR\r\n"
+        );
+        // the uid stays the same when the feed is rebuilt later
+        let later = to_ics(&worklogs, utc("20240504T120000"));
+        assert_eq!(ics.replace("20240503T120000Z", "20240504T120000Z"), later);
+    }
+
+    #[test]
+    fn escapes_text() {
+        assert_eq!(escape("a,b;c\\d"), "a\\,b\\;c\\\\d");
+        assert_eq!(escape("one\r\ntwo\nthree"), "one\\ntwo\\nthree");
+
+        let worklogs = [issue_worklog("w1", "Fix, then; test\\\nagain", "https://tracker/a,b", "20240502T090000", "20240502T100000")];
+        let ics = to_ics(&worklogs, utc("20240503T120000"));
+        assert!(ics.contains("\r\nSUMMARY:ABC-1: Fix\\, then\\; test\\\\\\nagain\r\n"));
+        assert!(ics.contains("\r\nURL:https://tracker/a\\,b\r\n"));
+    }
+
+    #[test]
+    fn folds_long_lines_at_75_bytes() {
+        assert_eq!(fold(&"a".repeat(75)), format!("{}\r\n", "a".repeat(75)));
+        assert_eq!(fold(&"a".repeat(76)), format!("{}\r\n a\r\n", "a".repeat(75)));
+
+        // a character is never split, the line breaks before it instead
+        let line = format!("SUMMARY:{}", "äö€😀".repeat(20));
+        let folded = fold(&line);
+        assert!(folded.split("\r\n").all(|part| part.len() <= 75));
+        assert_eq!(fold(&format!("{}€", "a".repeat(73))), format!("{}\r\n €\r\n", "a".repeat(73)));
+        assert_eq!(folded.replace("\r\n ", "").trim_end(), line);
+    }
+
+    #[test]
+    fn written_events_read_back() {
+        let summary = ["Überprüfung, Teil 2; mit \\ und\nZeilen"; 3].join(" ");
+        let worklogs = [issue_worklog("w1", &summary, "", "20240502T090000", "20240502T103000")];
+        let ics = to_ics(&worklogs, utc("20240503T120000"));
+
+        assert_eq!(
+            events_of_may_in(&ics),
+            vec![(format!("ABC-1: {}", summary), utc("20240502T090000"), utc("20240502T103000"))]
+        );
+    }
+}
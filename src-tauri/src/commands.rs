@@ -1,11 +1,11 @@
-use crate::{storage::Storage, AppState, MangedState, TracingInfo};
-use shared::{Issue, Worklog};
+use crate::{events, sync, tracker::NO_TRACKER, AppState, MangedState, TracingInfo};
+use shared::{Issue, OutboxStatus, Worklog};
 use std::{borrow::BorrowMut, sync::{mpsc::channel, Arc}, time::{Duration, Instant, SystemTime, UNIX_EPOCH}};
 use tauri::State;
 use tokio::sync::MutexGuard;
 use uuid::Uuid;
 use tokio::runtime::Handle;
-const MAX_RECENT: i32 = 5;
+pub const MAX_RECENT: usize = 5;
 
 
 #[tauri::command(async)]
@@ -19,18 +19,20 @@ pub async fn add_issue(
     name: String,
     summary: String,
     app_state: State<'_, Arc<MangedState>>,
+    app_handle: tauri::AppHandle,
 ) -> Result<Issue, String> {
     let m = app_state.0.lock().await;
     let issue = Issue {
         id: Uuid::new_v4().to_string(),
-        name: name,
-        url: "".to_string(),
-        summary: summary,
-        assignee_email: m.jira_config.user.clone(),
-        time_tracked_all: 0,
+        name,
+        summary,
+        assignee_email: m.tracker.as_ref().map(|tracker| tracker.user().to_string()).unwrap_or_default(),
+        ..Issue::empty()
     };
 
-    m.storage.add_issue(issue).await
+    let issue = m.storage.add_issue(issue).await?;
+    events::issues_changed(&app_handle);
+    Ok(issue)
 }
 
 #[tauri::command(async)]
@@ -48,7 +50,7 @@ pub async fn get_tracing_issue_name(
 pub async fn get_elapsed_time(app_state: State<'_, Arc<MangedState>>) -> Result<Duration, String> {
     let m = app_state.0.lock().await;
     match &m.tracing_info {
-        Some(info) => Ok(info.start_time.elapsed()),
+        Some(info) => Ok(info.elapsed()),
         None => Ok(Duration::from_secs(0)),
     }
 }
@@ -59,24 +61,79 @@ pub async fn start_tracking_cmd(
     app_state: State<'_, Arc<MangedState>>,
     app_handle: tauri::AppHandle
 ) -> Result<(), String> {
-    println!("Start tracing {}", name);
+    log::info!("start tracking {}", name);
     stop_tracking(&app_state).await?;
-    start_tracking(name, &app_state, app_handle).await
+    let res = start_tracking(name, &app_state).await;
+    // what was tracked before is stopped either way
+    let event = match res {
+        Ok(_) => events::TRACKING_STARTED,
+        Err(_) => events::TRACKING_STOPPED,
+    };
+    events::tracking_changed(&app_handle, &app_state, event).await;
+    res
 }
 
 #[tauri::command(async)]
-pub async fn stop_tracking_cmd(app_state: State<'_, Arc<MangedState>>) -> Result<(), String> {
-    stop_tracking(&app_state).await
+pub async fn stop_tracking_cmd(
+    app_state: State<'_, Arc<MangedState>>,
+    app_handle: tauri::AppHandle,
+) -> Result<(), String> {
+    let res = stop_tracking(&app_state).await;
+    events::tracking_changed(&app_handle, &app_state, events::TRACKING_STOPPED).await;
+    res
 }
 
-pub async fn start_tracking(
-    name: String,
-    app_state: &State<'_, Arc<MangedState>>,
-    app_handle: tauri::AppHandle
+#[tauri::command(async)]
+pub async fn pause_tracking_cmd(
+    app_state: State<'_, Arc<MangedState>>,
+    app_handle: tauri::AppHandle,
+) -> Result<(), String> {
+    let res = pause_tracking(&app_state).await;
+    events::tracking_changed(&app_handle, &app_state, events::TRACKING_TICK).await;
+    res
+}
+
+#[tauri::command(async)]
+pub async fn resume_tracking_cmd(
+    app_state: State<'_, Arc<MangedState>>,
+    app_handle: tauri::AppHandle,
 ) -> Result<(), String> {
+    let res = resume_tracking(&app_state).await;
+    events::tracking_changed(&app_handle, &app_state, events::TRACKING_TICK).await;
+    res
+}
+
+#[tauri::command(async)]
+pub async fn sync_worklogs(app_state: State<'_, Arc<MangedState>>) -> Result<u64, String> {
+    let m = app_state.0.lock().await;
+    match &m.tracker {
+        Some(tracker) => sync::enqueue_unsynced(tracker.as_ref(), &m.storage).await,
+        None => Err(NO_TRACKER.to_string()),
+    }
+}
+
+#[tauri::command(async)]
+pub async fn get_outbox_status(app_state: State<'_, Arc<MangedState>>) -> Result<OutboxStatus, String> {
+    let m = app_state.0.lock().await;
+    m.storage.get_outbox_status().await
+}
+
+#[tauri::command(async)]
+pub async fn retry_outbox_item(id: i64, app_state: State<'_, Arc<MangedState>>) -> Result<(), String> {
+    let m = app_state.0.lock().await;
+    m.storage.retry_outbox_item(id).await
+}
+
+#[tauri::command(async)]
+pub async fn discard_outbox_item(id: i64, app_state: State<'_, Arc<MangedState>>) -> Result<(), String> {
+    let m = app_state.0.lock().await;
+    m.storage.delete_outbox_item(id).await
+}
+
+pub async fn start_tracking(name: String, app_state: &MangedState) -> Result<(), String> {
     let mut m = app_state.0.lock().await;
 
-    if let Some(_) = m.tracing_info {
+    if m.tracing_info.is_some() {
         return Err("Issue already tracked".to_string());
     }
 
@@ -93,116 +150,152 @@ pub async fn start_tracking(
         start: start_time,
         end: 0,
         total_time: 0,
+        remote_id: None,
+        synced: false,
     };
 
     m.storage.add_worklog(worklog.clone()).await?;
 
-    add_to_recent_issues(&issue.name, m.borrow_mut(), app_handle);
+    add_to_recent_issues(&issue.name, m.borrow_mut());
 
     let tracing_info = TracingInfo {
         time_tracing_issue: issue,
         current_worklog: worklog,
         start_time: Instant::now(),
+        session_time: Duration::ZERO,
+        session_logged: 0,
+        paused: false,
     };
 
     m.tracing_info = Some(tracing_info);
     Ok(())
 }
 
-pub async fn stop_tracking(app_state: &State<'_, Arc<MangedState>>) -> Result<(), String> {
+pub async fn stop_tracking(app_state: &MangedState) -> Result<(), String> {
     let mut m = app_state.0.lock().await;
 
-    match m.tracing_info.clone() {
-        Some(mut tracing_info) => {
-            let duration = tracing_info.start_time.elapsed();
-
-            let end_time = match SystemTime::now().duration_since(UNIX_EPOCH) {
-                Ok(d) => d.as_secs(),
-                Err(_) => return Err("time went backwards".to_string()),
-            };
+    save_tracing(&m).await?;
+    m.tracing_info = None;
+    m.idle = None;
 
-            tracing_info.current_worklog.end = end_time;
-            tracing_info.current_worklog.total_time = duration.as_secs();
+    Ok(())
+}
 
-            let mut issue = m
-                .storage
-                .get_issue(tracing_info.time_tracing_issue.name)
-                .await?;
-            issue.time_tracked_all += duration.as_secs();
+/// Ends the current worklog but keeps the session, paused time ends up in no worklog.
+pub async fn pause_tracking(app_state: &MangedState) -> Result<(), String> {
+    let mut m = app_state.0.lock().await;
+    let mut info = match m.tracing_info.clone() {
+        Some(info) if info.paused => return Ok(()),
+        Some(info) => info,
+        None => return Err("nothing is tracked".to_string()),
+    };
 
-            m.storage.edit_issue(issue).await?;
-            m.storage.edit_worklog(tracing_info.current_worklog).await?;
-        }
-        None => return Ok(()),
+    let tracked = info.start_time.elapsed();
+    if let Some(worklog) = save_tracing(&m).await? {
+        info.add_to_session(&worklog, tracked);
     }
+    info.paused = true;
 
-    m.tracing_info = None;
+    m.tracing_info = Some(info);
+    m.idle = None;
+    Ok(())
+}
+
+/// Goes on with the paused session in a new worklog.
+pub async fn resume_tracking(app_state: &MangedState) -> Result<(), String> {
+    let mut m = app_state.0.lock().await;
+    let mut info = match m.tracing_info.clone() {
+        Some(info) if info.paused => info,
+        Some(_) => return Ok(()),
+        None => return Err("nothing is tracked".to_string()),
+    };
 
+    info.current_worklog = m
+        .storage
+        .add_worklog(Worklog {
+            id: Uuid::new_v4().to_string(),
+            start: sync::now()? as u64,
+            end: 0,
+            total_time: 0,
+            remote_id: None,
+            synced: false,
+            ..info.current_worklog
+        })
+        .await?;
+    info.start_time = Instant::now();
+    info.paused = false;
+
+    m.tracing_info = Some(info);
     Ok(())
 }
 
-fn add_to_recent_issues(issue_name: &String, state_mutex: &mut MutexGuard<AppState>, app_handle: tauri::AppHandle) {
-    state_mutex.recent_issues.push_back(issue_name.clone());
-    if state_mutex.recent_issues.len() == MAX_RECENT as usize {
-        state_mutex.recent_issues.pop_front().unwrap();
+/// Pauses or resumes, answers whether the session is paused now.
+pub async fn toggle_pause(app_state: &MangedState) -> Result<bool, String> {
+    let paused = app_state.0.lock().await.tracing_info.as_ref().is_some_and(|info| info.paused);
+    match paused {
+        true => resume_tracking(app_state).await.map(|_| false),
+        false => pause_tracking(app_state).await.map(|_| true),
     }
+}
 
-    for (i ,issue) in state_mutex.recent_issues.iter().enumerate() {
-        app_handle.tray_handle().get_item(i.to_string().as_str()).set_title(issue).unwrap();
-    }
-    
+/// Moves the issue to the front of the recent issues, the tray lists them in this order.
+fn add_to_recent_issues(issue_name: &str, state_mutex: &mut MutexGuard<AppState>) {
+    state_mutex.recent_issues.retain(|name| name != issue_name);
+    state_mutex.recent_issues.push_front(issue_name.to_string());
+    state_mutex.recent_issues.truncate(MAX_RECENT);
 }
 
+async fn save_tracing(m: &MutexGuard<'_, AppState>) -> Result<Option<Worklog>, String> {
+    let tracing_info = match m.tracing_info.clone() {
+        // a pause already finished the worklog
+        Some(tracing_info) if tracing_info.paused => return Ok(None),
+        Some(tracing_info) => tracing_info,
+        None => return Ok(None),
+    };
 
-pub async fn save_progress(app_state: Arc<MangedState>) -> Result<(), String> {
-    let mut m = app_state.0.lock().await;
-    save_tracing(&m).await?;
-    m.tracing_info = None;
-    Ok(())
+    let duration = tracing_info.start_time.elapsed();
+    let end_time = sync::now()? as u64;
+    let total_time = tracing_info.worklog_total(&m.config.rounding, duration.as_secs());
+
+    let worklog = finish_worklog(m, tracing_info.current_worklog, end_time, total_time).await?;
+    Ok(Some(worklog))
 }
 
-async fn save_tracing<'a>(m: &MutexGuard<'a, AppState>) -> Result<(), String> {
-    Ok(match m.tracing_info.clone() {
-        Some(mut tracing_info) => {
-            let duration = tracing_info.start_time.elapsed();
-        
-            let end_time = match SystemTime::now().duration_since(UNIX_EPOCH) {
-                Ok(d) => d.as_secs(),
-                Err(_) => return Err("time went backwards".to_string()),
-            };
-        
-            tracing_info.current_worklog.end = end_time;
-            tracing_info.current_worklog.total_time = duration.as_secs();
-        
-            let mut issue = m
-                .storage
-                .get_issue(tracing_info.time_tracing_issue.name)
-                .await?;
-            issue.time_tracked_all += duration.as_secs();
-        
-            m.storage.edit_issue(issue).await?;
-            m.storage.edit_worklog(tracing_info.current_worklog).await?;
-        }
-        None => return Ok(()),
-    })
+/// Ends the worklog with its total time, which is added to the issue, and queues the worklog
+/// for the tracker.
+pub async fn finish_worklog(
+    m: &MutexGuard<'_, AppState>,
+    mut worklog: Worklog,
+    end: u64,
+    total_time: u64,
+) -> Result<Worklog, String> {
+    worklog.end = end;
+    worklog.total_time = total_time;
+
+    let worklog = m.storage.edit_worklog(worklog).await?;
+
+    sync::enqueue(m.tracker.as_deref(), &m.storage, sync::OutboxKind::Create, &worklog).await?;
+    Ok(worklog)
 }
 
+/// Finishes the tracked worklog and keeps the recent issues, for every way out of the app.
+pub async fn save_on_exit(app_state: &MangedState) -> Result<(), String> {
+    let mut m = app_state.0.lock().await;
 
+    save_tracing(&m).await?;
+
+    let res = m.storage.save_recent_issues(m.recent_issues.clone()).await;
+    m.tracing_info = None;
+    res
+}
+
+/// `save_on_exit` for callers outside of the async runtime, e.g. the tray and run event handlers.
 pub fn exit_save_process(app_state: Arc<MangedState>) -> Result<(), String> {
     let handle = Handle::current();
     let (tx, rx) = channel();
     std::thread::spawn( move || {
         handle.block_on(async {
-            let mut m = app_state.0.lock().await;
-
-            let mut res = save_tracing(&m).await;
-            if res.is_err() {
-                return;
-            }
-
-            res = m.storage.save_recent_issues(m.recent_issues.clone()).await;
-            m.tracing_info = None;
-            tx.send(res).unwrap();
+            tx.send(save_on_exit(&app_state).await).unwrap();
           })
     });
 
@@ -211,4 +304,53 @@ pub fn exit_save_process(app_state: Arc<MangedState>) -> Result<(), String> {
         Ok(res) => res,
         Err(e) => Err(e.to_string()),
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use shared::{RoundingConfig, RoundingMode};
+
+    use super::*;
+    use crate::tests::test_state;
+
+    /// Makes the current worklog look `seconds` long.
+    async fn work(app_state: &MangedState, seconds: u64) {
+        let mut m = app_state.0.lock().await;
+        let info = m.tracing_info.as_mut().unwrap();
+        info.start_time = Instant::now().checked_sub(Duration::from_secs(seconds)).unwrap();
+    }
+
+    #[tokio::test]
+    async fn pauses_do_not_round_the_session_again() {
+        let app_state = test_state().await;
+        {
+            let mut m = app_state.0.lock().await;
+            m.config.rounding = RoundingConfig {
+                minutes: 15,
+                mode: RoundingMode::Up,
+            };
+            let issue = Issue {
+                id: "i1".to_string(),
+                name: "ABC-1".to_string(),
+                ..Issue::empty()
+            };
+            m.storage.add_issue(issue).await.unwrap();
+        }
+        start_tracking("ABC-1".to_string(), &app_state).await.unwrap();
+
+        // three times 2 minutes, paused twice in between
+        for _ in 0..2 {
+            work(&app_state, 120).await;
+            pause_tracking(&app_state).await.unwrap();
+            resume_tracking(&app_state).await.unwrap();
+        }
+        work(&app_state, 120).await;
+        stop_tracking(&app_state).await.unwrap();
+
+        let m = app_state.0.lock().await;
+        let worklogs = m.storage.get_issue_worklogs("i1").await.unwrap();
+        assert_eq!(worklogs.len(), 3);
+        assert_eq!(worklogs.iter().map(|worklog| worklog.total_time).sum::<u64>(), 15 * 60);
+        assert_eq!(m.storage.get_issue("ABC-1".to_string()).await.unwrap().time_tracked_all, 15 * 60);
+    }
+}
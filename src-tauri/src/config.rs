@@ -0,0 +1,220 @@
+use std::{env, fs, path::PathBuf, sync::Arc};
+
+use shared::{AppConfig, GithubConfig, GitlabConfig, JiraConfig, TrackerConfig};
+use tauri::{
+    api::path::{config_dir, data_dir},
+    AppHandle, State,
+};
+
+use crate::{
+    events,
+    storage::Storage,
+    tracker::{setup_tracker, NO_TRACKER},
+    tray::sync_tray,
+    MangedState,
+};
+
+// same directory name tauri derives from the bundle identifier
+const APP_DIR: &str = "com.time.track";
+const CONFIG_FILE: &str = "config.toml";
+const DATABASE_FILE: &str = "storage.db";
+
+pub fn config_path() -> Result<PathBuf, String> {
+    config_dir()
+        .map(|dir| dir.join(APP_DIR).join(CONFIG_FILE))
+        .ok_or("no config directory on this platform".to_string())
+}
+
+/// Reads `config.toml` as it is, a missing file is the default config.
+pub fn read_config() -> Result<AppConfig, String> {
+    let path = config_path()?;
+    match fs::read_to_string(&path) {
+        Ok(text) => toml::from_str(&text).map_err(|e| format!("{}: {}", path.display(), e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(AppConfig::default()),
+        Err(e) => Err(format!("{}: {}", path.display(), e)),
+    }
+}
+
+/// The config in use, the file config with the environment overrides applied and validated.
+/// Only the file config is ever saved, so tokens set in the environment stay out of the file.
+pub fn with_env(mut config: AppConfig) -> Result<AppConfig, String> {
+    apply_env(&mut config)?;
+    config.validate()?;
+
+    Ok(config)
+}
+
+pub fn save_config(config: &AppConfig) -> Result<(), String> {
+    let path = config_path()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let text = toml::to_string_pretty(config).map_err(|e| e.to_string())?;
+
+    fs::write(&path, text).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// The configured database, `storage.db` in the platform data directory when not set.
+pub fn database_url(config: &AppConfig) -> Result<String, String> {
+    if let Some(url) = &config.database_url {
+        return Ok(url.clone());
+    }
+
+    let dir = data_dir()
+        .map(|dir| dir.join(APP_DIR))
+        .ok_or("no data directory on this platform".to_string())?;
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+
+    Ok(format!("sqlite:{}", dir.join(DATABASE_FILE).display()))
+}
+
+/// The environment variables win over the file, `TRACKER=none` turns the tracker off.
+fn apply_env(config: &mut AppConfig) -> Result<(), String> {
+    override_option(&mut config.database_url, "DATABASE_URL");
+
+    let kind = match env::var("TRACKER") {
+        Ok(kind) => kind,
+        Err(_) => match &config.tracker {
+            Some(tracker) => tracker.kind().to_string(),
+            None if env::var("JIRA_URL").is_ok() => "jira".to_string(),
+            None if env::var("GITHUB_TOKEN").is_ok() => "github".to_string(),
+            None if env::var("GITLAB_URL").is_ok() => "gitlab".to_string(),
+            None => return Ok(()),
+        },
+    };
+
+    config.tracker = match kind.as_str() {
+        "jira" => {
+            let mut jira = match config.tracker.take() {
+                Some(TrackerConfig::Jira(jira)) => jira,
+                _ => JiraConfig::default(),
+            };
+            override_value(&mut jira.url, "JIRA_URL");
+            override_value(&mut jira.user, "JIRA_USER");
+            override_value(&mut jira.api_key, "JIRA_KEY");
+            override_option(&mut jira.jql, "JIRA_JQL");
+            Some(TrackerConfig::Jira(jira))
+        }
+        "github" => {
+            let mut github = match config.tracker.take() {
+                Some(TrackerConfig::Github(github)) => github,
+                _ => GithubConfig::default(),
+            };
+            override_value(&mut github.user, "GITHUB_USER");
+            override_value(&mut github.token, "GITHUB_TOKEN");
+            override_option(&mut github.api_url, "GITHUB_API_URL");
+            if let Ok(repos) = env::var("GITHUB_REPOS") {
+                github.repositories = repos
+                    .split(',')
+                    .map(|repo| repo.trim().to_string())
+                    .filter(|repo| !repo.is_empty())
+                    .collect();
+            }
+            if let Ok(mode) = env::var("GITHUB_WORKLOGS") {
+                github.worklogs = toml::Value::String(mode.clone())
+                    .try_into()
+                    .map_err(|_| format!("unknown github worklog mode {}", mode))?;
+            }
+            Some(TrackerConfig::Github(github))
+        }
+        "gitlab" => {
+            let mut gitlab = match config.tracker.take() {
+                Some(TrackerConfig::Gitlab(gitlab)) => gitlab,
+                _ => GitlabConfig::default(),
+            };
+            override_value(&mut gitlab.url, "GITLAB_URL");
+            override_value(&mut gitlab.user, "GITLAB_USER");
+            override_value(&mut gitlab.token, "GITLAB_TOKEN");
+            Some(TrackerConfig::Gitlab(gitlab))
+        }
+        "none" => None,
+        other => return Err(format!("unknown tracker {}", other)),
+    };
+
+    Ok(())
+}
+
+fn override_value(value: &mut String, var: &str) {
+    if let Ok(set) = env::var(var) {
+        *value = set;
+    }
+}
+
+fn override_option(value: &mut Option<String>, var: &str) {
+    if let Ok(set) = env::var(var) {
+        *value = Some(set);
+    }
+}
+
+/// The config as `config.toml` has it, without the environment overrides. The defaults
+/// when the file does not parse.
+#[tauri::command(async)]
+pub async fn get_config(app_state: State<'_, Arc<MangedState>>) -> Result<AppConfig, String> {
+    Ok(app_state.0.lock().await.file_config.clone().unwrap_or_default())
+}
+
+/// Why the config could not be used on startup, the app runs without a tracker then.
+#[tauri::command(async)]
+pub async fn get_config_error(app_state: State<'_, Arc<MangedState>>) -> Result<Option<String>, String> {
+    Ok(app_state.0.lock().await.config_error.clone())
+}
+
+/// Saves the file config once it is valid with the environment overrides applied, then
+/// switches the tracker and the storage over to it. Answers with the saved config.
+#[tauri::command(async)]
+pub async fn update_config(
+    config: AppConfig,
+    app_state: State<'_, Arc<MangedState>>,
+    app_handle: AppHandle,
+) -> Result<AppConfig, String> {
+    let effective = with_env(config.clone())?;
+    let tracker = setup_tracker(effective.tracker.as_ref()).map_err(|e| e.to_string())?;
+
+    let mut m = app_state.0.lock().await;
+    // the form holds the defaults then, saving would throw away what the file has
+    if m.file_config.is_none() {
+        return Err(format!(
+            "the config file could not be read, fix or remove it and restart before saving: {}",
+            m.config_error.clone().unwrap_or_default()
+        ));
+    }
+    let url = database_url(&effective)?;
+    let storage = match url != database_url(&m.config)? {
+        true if m.tracing_info.is_some() => return Err("stop tracking before moving the database".to_string()),
+        true => Some(Storage::open(&url).await?),
+        false => None,
+    };
+
+    save_config(&config)?;
+
+    if let Some(storage) = storage {
+        m.storage.save_recent_issues(m.recent_issues.clone()).await?;
+        m.recent_issues = storage.get_recent_issues().await?;
+        m.storage = storage;
+    }
+    m.tracker = tracker;
+    m.config = effective;
+    m.file_config = Some(config.clone());
+    m.config_error = None;
+    drop(m);
+    // another database has another total for today
+    events::issues_changed(&app_handle);
+    sync_tray(&app_handle, &app_state).await;
+
+    Ok(config)
+}
+
+/// Connects with the given tracker settings without saving them, answers with the user name.
+#[tauri::command(async)]
+pub async fn test_tracker_connection(tracker: TrackerConfig) -> Result<String, String> {
+    AppConfig {
+        tracker: Some(tracker.clone()),
+        ..AppConfig::default()
+    }
+    .validate()?;
+
+    match setup_tracker(Some(&tracker)).map_err(|e| e.to_string())? {
+        Some(tracker) => tracker.check_connection().await.map_err(|e| e.to_string()),
+        None => Err(NO_TRACKER.to_string()),
+    }
+}
@@ -0,0 +1,79 @@
+use std::{sync::Arc, time::Duration};
+
+use serde::Serialize;
+use shared::TrackingState;
+use tauri::{AppHandle, Manager, State};
+
+use crate::{
+    tray::{forget_today_total, sync_tray},
+    AppState, MangedState,
+};
+
+pub const TRACKING_STARTED: &str = "tracking-started";
+pub const TRACKING_STOPPED: &str = "tracking-stopped";
+/// every second while tracking and whenever pausing or idling changes
+pub const TRACKING_TICK: &str = "tracking-tick";
+/// issues or their tracked time changed, the windows load them again
+pub const ISSUES_CHANGED: &str = "issues-changed";
+
+const TICK: Duration = Duration::from_secs(1);
+
+pub fn tracking_state(m: &AppState) -> TrackingState {
+    match &m.tracing_info {
+        Some(info) => TrackingState {
+            issue_name: Some(info.time_tracing_issue.name.clone()),
+            elapsed: info.elapsed().as_secs(),
+            paused: info.paused,
+            idle: m.idle.clone(),
+        },
+        None => TrackingState::default(),
+    }
+}
+
+fn emit<S: Serialize + Clone>(app: &AppHandle, event: &str, payload: S) {
+    if let Err(e) = app.emit_all(event, payload) {
+        log::warn!("{} can't be sent: {}", event, e);
+    }
+}
+
+/// Brings the tray and every window in line after what is tracked changed, a stop also
+/// changes the time tracked on the issue.
+pub async fn tracking_changed(app: &AppHandle, app_state: &MangedState, event: &str) {
+    if event == TRACKING_STOPPED {
+        forget_today_total();
+    }
+    sync_tray(app, app_state).await;
+
+    let state = tracking_state(&*app_state.0.lock().await);
+    emit(app, event, state);
+    if event == TRACKING_STOPPED {
+        issues_changed(app);
+    }
+}
+
+pub fn issues_changed(app: &AppHandle) {
+    forget_today_total();
+    emit(app, ISSUES_CHANGED, ());
+}
+
+/// Background task sending the elapsed time to the windows while tracking.
+pub async fn run_tracking_ticks(app_state: Arc<MangedState>, app: AppHandle) {
+    loop {
+        tokio::time::sleep(TICK).await;
+
+        let state = {
+            let m = app_state.0.lock().await;
+            match m.tracing_info {
+                Some(_) => tracking_state(&m),
+                None => continue,
+            }
+        };
+        emit(&app, TRACKING_TICK, state);
+    }
+}
+
+/// The state the tracking events carry, for windows that just opened.
+#[tauri::command(async)]
+pub async fn get_tracking_state(app_state: State<'_, Arc<MangedState>>) -> Result<TrackingState, String> {
+    Ok(tracking_state(&*app_state.0.lock().await))
+}
@@ -0,0 +1,328 @@
+use std::{path::PathBuf, sync::Arc};
+
+use chrono::{DateTime, FixedOffset, Local, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde_json::{Map, Value};
+use shared::{format_duration, DurationFormat, ExportColumn, ExportFormat, ExportOptions};
+use tauri::{api::path::download_dir, State};
+
+use crate::{calendar, storage::IssueWorklog, sync, MangedState};
+
+/// Writes the worklogs with their issue to a CSV, JSON or iCalendar file and answers with its path.
+#[tauri::command(async)]
+pub async fn export_worklogs(options: ExportOptions, app_state: State<'_, Arc<MangedState>>) -> Result<String, String> {
+    if options.columns.is_empty() && options.format != ExportFormat::Ics {
+        return Err("pick at least one column".to_string());
+    }
+    let zone = parse_time_zone(&options.time_zone)?;
+
+    let worklogs = {
+        let m = app_state.0.lock().await;
+        m.storage.get_issue_worklogs_between(options.from, options.to).await?
+    };
+
+    let contents = match options.format {
+        ExportFormat::Csv => to_csv(&options.columns, &rows(&worklogs, &options, zone)),
+        ExportFormat::Json => to_json(&options.columns, rows(&worklogs, &options, zone))?,
+        ExportFormat::Ics => calendar::to_ics(&worklogs, sync::now()? as u64),
+    };
+
+    let path = match &options.path {
+        Some(path) if !path.trim().is_empty() => PathBuf::from(path.trim()),
+        _ => download_dir()
+            .ok_or("there is no downloads directory, give a path".to_string())?
+            .join(format!("worklogs-{}-{}.{}", options.from, options.to, options.format.extension())),
+    };
+    tokio::fs::write(&path, contents).await.map_err(|e| e.to_string())?;
+
+    Ok(path.display().to_string())
+}
+
+/// Zone the times of a file are in, named zones follow their daylight saving time.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum FileZone {
+    Local,
+    Fixed(FixedOffset),
+    Named(Tz),
+}
+
+impl FileZone {
+    pub fn time(&self, seconds: u64) -> Option<DateTime<FixedOffset>> {
+        let utc = Utc.timestamp_opt(seconds as i64, 0).single()?;
+        Some(match self {
+            FileZone::Local => utc.with_timezone(&Local).fixed_offset(),
+            FileZone::Fixed(zone) => utc.with_timezone(zone),
+            FileZone::Named(zone) => utc.with_timezone(zone).fixed_offset(),
+        })
+    }
+
+    /// Unix seconds of a time on the clock of the zone, the first one when the clock was set back.
+    pub fn seconds(&self, naive: &NaiveDateTime) -> Option<i64> {
+        match self {
+            FileZone::Local => Local.from_local_datetime(naive).earliest().map(|time| time.timestamp()),
+            FileZone::Fixed(zone) => zone.from_local_datetime(naive).single().map(|time| time.timestamp()),
+            FileZone::Named(zone) => zone.from_local_datetime(naive).earliest().map(|time| time.timestamp()),
+        }
+    }
+}
+
+pub fn parse_time_zone(zone: &str) -> Result<FileZone, String> {
+    let invalid = || {
+        format!(
+            "unknown time zone {}, use local, utc, a name like Europe/Berlin or an offset like +02:00",
+            zone
+        )
+    };
+
+    match zone.trim().to_lowercase().as_str() {
+        "" | "local" => Ok(FileZone::Local),
+        "utc" | "z" => FixedOffset::east_opt(0).map(FileZone::Fixed).ok_or_else(invalid),
+        offset if offset.starts_with(['+', '-']) => {
+            let (sign, rest) = offset.split_at(1);
+            let sign = if sign == "-" { -1 } else { 1 };
+            let (hours, minutes) = rest.split_once(':').unwrap_or((rest, "0"));
+            let hours = hours.parse::<i32>().map_err(|_| invalid())?;
+            let minutes = minutes.parse::<i32>().map_err(|_| invalid())?;
+            FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+                .map(FileZone::Fixed)
+                .ok_or_else(invalid)
+        }
+        _ => zone.trim().parse::<Tz>().map(FileZone::Named).map_err(|_| invalid()),
+    }
+}
+
+fn rows(worklogs: &[IssueWorklog], options: &ExportOptions, zone: FileZone) -> Vec<Vec<Value>> {
+    worklogs
+        .iter()
+        .map(|entry| {
+            options
+                .columns
+                .iter()
+                .map(|column| value(*column, entry, zone, options.duration_format))
+                .collect()
+        })
+        .collect()
+}
+
+fn value(column: ExportColumn, entry: &IssueWorklog, zone: FileZone, duration_format: DurationFormat) -> Value {
+    let time = |seconds: u64, format: &str| {
+        zone.time(seconds)
+            .map(|time| Value::from(time.format(format).to_string()))
+            .unwrap_or(Value::Null)
+    };
+
+    match column {
+        ExportColumn::Date => time(entry.worklog.start, "%Y-%m-%d"),
+        ExportColumn::IssueKey => Value::from(entry.issue.name.clone()),
+        ExportColumn::IssueSummary => Value::from(entry.issue.summary.clone()),
+        ExportColumn::Project => Value::from(entry.issue.project.clone()),
+        ExportColumn::Start => time(entry.worklog.start, "%Y-%m-%dT%H:%M:%S%:z"),
+        ExportColumn::End => time(entry.worklog.end, "%Y-%m-%dT%H:%M:%S%:z"),
+        ExportColumn::Duration => match duration_format {
+            DurationFormat::Seconds => Value::from(entry.worklog.total_time),
+            DurationFormat::DecimalHours => {
+                Value::from((entry.worklog.total_time as f64 / 36.0).round() / 100.0)
+            }
+            DurationFormat::HoursMinutes => Value::from(format_duration(entry.worklog.total_time)),
+        },
+        ExportColumn::Synced => Value::from(entry.worklog.synced),
+        ExportColumn::WorklogId => Value::from(entry.worklog.id.clone()),
+    }
+}
+
+fn to_csv(columns: &[ExportColumn], rows: &[Vec<Value>]) -> String {
+    let mut csv = columns.iter().map(|column| column.name()).collect::<Vec<_>>().join(",");
+    csv.push_str("\r\n");
+
+    for row in rows {
+        let fields: Vec<String> = row
+            .iter()
+            .map(|value| match value {
+                Value::String(text) => csv_field(text),
+                Value::Null => String::new(),
+                other => other.to_string(),
+            })
+            .collect();
+        csv.push_str(&fields.join(","));
+        csv.push_str("\r\n");
+    }
+    csv
+}
+
+// quoted when it holds a separator, a quote or a line break, quotes are doubled
+fn csv_field(text: &str) -> String {
+    match text.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", text.replace('"', "\"\"")),
+        false => text.to_string(),
+    }
+}
+
+fn to_json(columns: &[ExportColumn], rows: Vec<Vec<Value>>) -> Result<String, String> {
+    let objects: Vec<Value> = rows
+        .into_iter()
+        .map(|row| {
+            let object: Map<String, Value> = columns
+                .iter()
+                .map(|column| column.name().to_string())
+                .zip(row)
+                .collect();
+            Value::Object(object)
+        })
+        .collect();
+
+    serde_json::to_string_pretty(&objects).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, Timelike};
+    use shared::{Issue, Worklog};
+
+    use super::*;
+
+    fn naive(month: u32, day: u32, hour: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, month, day).unwrap().and_hms_opt(hour, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn reads_names_offsets_and_keywords() {
+        assert_eq!(parse_time_zone(" Local "), Ok(FileZone::Local));
+        assert_eq!(parse_time_zone("UTC"), Ok(FileZone::Fixed(FixedOffset::east_opt(0).unwrap())));
+        assert_eq!(parse_time_zone("-05:30"), Ok(FileZone::Fixed(FixedOffset::west_opt(5 * 3600 + 1800).unwrap())));
+        assert_eq!(parse_time_zone("+2"), Ok(FileZone::Fixed(FixedOffset::east_opt(7200).unwrap())));
+        assert_eq!(parse_time_zone("Europe/Berlin"), Ok(FileZone::Named(Tz::Europe__Berlin)));
+        assert!(parse_time_zone("Mars/Olympus").is_err());
+        assert!(parse_time_zone("+2x").is_err());
+    }
+
+    #[test]
+    fn named_zones_follow_daylight_saving_time() {
+        let berlin = parse_time_zone("Europe/Berlin").unwrap();
+
+        let winter = berlin.seconds(&naive(1, 15, 9)).unwrap();
+        let summer = berlin.seconds(&naive(7, 15, 9)).unwrap();
+        assert_eq!(winter, Utc.with_ymd_and_hms(2024, 1, 15, 8, 0, 0).unwrap().timestamp());
+        assert_eq!(summer, Utc.with_ymd_and_hms(2024, 7, 15, 7, 0, 0).unwrap().timestamp());
+
+        let format = |seconds: i64| berlin.time(seconds as u64).unwrap().format("%Y-%m-%dT%H:%M:%S%:z").to_string();
+        assert_eq!(format(winter), "2024-01-15T09:00:00+01:00");
+        assert_eq!(format(summer), "2024-07-15T09:00:00+02:00");
+
+        // 2:30 is skipped when the clocks go forward, 2:30 happens twice when they go back
+        assert_eq!(berlin.seconds(&naive(3, 31, 2).with_minute(30).unwrap()), None);
+        let twice = berlin.seconds(&naive(10, 27, 2).with_minute(30).unwrap()).unwrap();
+        assert_eq!(twice, Utc.with_ymd_and_hms(2024, 10, 27, 0, 30, 0).unwrap().timestamp());
+    }
+
+    /// Two worklogs on the day Berlin goes back to winter time, the first one across the change.
+    fn worklogs() -> Vec<IssueWorklog> {
+        // hours and minutes after the start of the 26th in UTC
+        let day = Utc.with_ymd_and_hms(2024, 10, 26, 0, 0, 0).unwrap().timestamp() as u64;
+        let utc = |hour: u64, minute: u64| day + hour * 3600 + minute * 60;
+        let summary = "Fix \"login\", then\nretry".to_string();
+        let mut issue = Issue::new("10".to_string(), "ABC-1".to_string(), String::new(), summary, String::new(), 0);
+        let login = IssueWorklog {
+            worklog: Worklog::new("w1".to_string(), "10".to_string(), utc(22, 30), utc(25, 30), 3 * 3600),
+            issue: issue.clone(),
+        };
+        issue.project = "Web".to_string();
+        let mut review = Worklog::new("w2".to_string(), "10".to_string(), utc(32, 0), utc(33, 30), 5430);
+        review.synced = true;
+        vec![login, IssueWorklog { worklog: review, issue }]
+    }
+
+    fn options(duration_format: DurationFormat) -> ExportOptions {
+        ExportOptions {
+            columns: vec![
+                ExportColumn::WorklogId,
+                ExportColumn::Duration,
+                ExportColumn::IssueKey,
+                ExportColumn::IssueSummary,
+                ExportColumn::Start,
+                ExportColumn::End,
+                ExportColumn::Date,
+                ExportColumn::Synced,
+                ExportColumn::Project,
+            ],
+            time_zone: "Europe/Berlin".to_string(),
+            duration_format,
+            ..ExportOptions::default()
+        }
+    }
+
+    fn csv(duration_format: DurationFormat) -> String {
+        let options = options(duration_format);
+        let zone = parse_time_zone(&options.time_zone).unwrap();
+        to_csv(&options.columns, &rows(&worklogs(), &options, zone))
+    }
+
+    #[test]
+    fn quotes_csv_fields_when_needed() {
+        assert_eq!(csv_field("plain text"), "plain text");
+        assert_eq!(csv_field(""), "");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("one\ntwo"), "\"one\ntwo\"");
+        assert_eq!(csv_field("one\r\ntwo"), "\"one\r\ntwo\"");
+    }
+
+    #[test]
+    fn writes_csv_in_the_column_order() {
+        assert_eq!(
+            csv(DurationFormat::Seconds),
+            "worklog_id,duration,issue_key,issue_summary,start,end,date,synced,project\r\n\
+             w1,10800,ABC-1,\"Fix \"\"login\"\", then\nretry\",2024-10-27T00:30:00+02:00,2024-10-27T02:30:00+01:00,2024-10-27,false,\r\n\
+             w2,5430,ABC-1,\"Fix \"\"login\"\", then\nretry\",2024-10-27T09:00:00+01:00,2024-10-27T10:30:00+01:00,2024-10-27,true,Web\r\n"
+        );
+    }
+
+    #[test]
+    fn formats_durations() {
+        let durations = |duration_format| -> Vec<Value> {
+            let zone = parse_time_zone("utc").unwrap();
+            worklogs().iter().map(|entry| value(ExportColumn::Duration, entry, zone, duration_format)).collect()
+        };
+        assert_eq!(durations(DurationFormat::Seconds), vec![Value::from(10800), Value::from(5430)]);
+        assert_eq!(durations(DurationFormat::DecimalHours), vec![Value::from(3.0), Value::from(1.51)]);
+        assert_eq!(durations(DurationFormat::HoursMinutes), vec![Value::from("3h"), Value::from("1h 30m")]);
+    }
+
+    #[test]
+    fn writes_json_in_the_column_order() {
+        let options = options(DurationFormat::HoursMinutes);
+        let zone = parse_time_zone(&options.time_zone).unwrap();
+        let json = to_json(&options.columns, rows(&worklogs()[..1], &options, zone)).unwrap();
+
+        assert_eq!(
+            json,
+            r#"[
+  {
+    "worklog_id": "w1",
+    "duration": "3h",
+    "issue_key": "ABC-1",
+    "issue_summary": "Fix \"login\", then\nretry",
+    "start": "2024-10-27T00:30:00+02:00",
+    "end": "2024-10-27T02:30:00+01:00",
+    "date": "2024-10-27",
+    "synced": false,
+    "project": ""
+  }
+]"#
+        );
+    }
+
+    #[test]
+    fn converts_times_to_the_file_zone() {
+        let entry = &worklogs()[0];
+        let in_zone = |column, zone: &str| value(column, entry, parse_time_zone(zone).unwrap(), DurationFormat::Seconds);
+        let start = |zone| in_zone(ExportColumn::Start, zone);
+        let date = |zone| in_zone(ExportColumn::Date, zone);
+
+        assert_eq!(start("utc"), Value::from("2024-10-26T22:30:00+00:00"));
+        assert_eq!(start("-05:00"), Value::from("2024-10-26T17:30:00-05:00"));
+        assert_eq!(start("Europe/Berlin"), Value::from("2024-10-27T00:30:00+02:00"));
+        // the date is the day the worklog starts on in the zone
+        assert_eq!(date("utc"), Value::from("2024-10-26"));
+        assert_eq!(date("Europe/Berlin"), Value::from("2024-10-27"));
+    }
+}
@@ -0,0 +1,578 @@
+use std::collections::BTreeMap;
+
+use async_trait::async_trait;
+use chrono::{TimeZone, Utc};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use shared::{format_duration, GithubConfig, GithubWorklogMode, Issue, Worklog};
+use tauri::{
+    api::http::{Body, Client, ClientBuilder, HttpRequestBuilder},
+    http::header::{ACCEPT, AUTHORIZATION, USER_AGENT},
+    Error,
+};
+use uuid::Uuid;
+
+use crate::tracker::{execute, parse, Tracker, TrackerError, WorklogReceipt};
+
+pub const GITHUB_SOURCE: &str = "github";
+pub const DEFAULT_API_URL: &str = "https://api.github.com";
+const PAGE_SIZE: usize = 100;
+// hidden in the summary comment, keeps the seconds of every worklog it sums up
+const SUMMARY_MARKER: &str = "<!-- time-track summary";
+
+pub struct GithubTracker {
+    pub api_url: String,
+    pub user: String,
+    pub token: String,
+    pub repositories: Vec<String>,
+    pub worklog_mode: GithubWorklogMode,
+    pub client: Client,
+}
+
+pub fn setup_github(config: &GithubConfig) -> Result<GithubTracker, Error> {
+    let client = ClientBuilder::new().build()?;
+
+    Ok(GithubTracker {
+        api_url: config.api_url.clone().unwrap_or(DEFAULT_API_URL.to_string()),
+        user: config.user.clone(),
+        token: config.token.clone(),
+        repositories: config.repositories.clone(),
+        worklog_mode: config.worklogs,
+        client,
+    })
+}
+
+#[derive(Deserialize, Debug)]
+struct GithubIssue {
+    number: u64,
+    title: String,
+    html_url: String,
+    state: String,
+    assignee: Option<GithubUser>,
+    pull_request: Option<Value>,
+    #[serde(default)]
+    labels: Vec<GithubLabel>,
+}
+
+#[derive(Deserialize, Debug)]
+struct GithubLabel {
+    name: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct GithubUser {
+    login: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct GithubComment {
+    id: u64,
+    body: String,
+    user: GithubUser,
+}
+
+impl GithubTracker {
+    fn request(&self, method: &str, path: String) -> Result<HttpRequestBuilder, TrackerError> {
+        let link = format!("{}{}", self.api_url, path);
+        let invalid = |err: tauri::api::Error| TrackerError::Rejected(0, err.to_string());
+
+        HttpRequestBuilder::new(method, link)
+            .map_err(invalid)?
+            .header(AUTHORIZATION, format!("Bearer {}", self.token))
+            .map_err(invalid)?
+            .header(ACCEPT, "application/vnd.github+json")
+            .map_err(invalid)?
+            // github refuses requests without one
+            .header(USER_AGENT, "time-track")
+            .map_err(invalid)
+    }
+
+    async fn send(&self, method: &str, path: String, body: Option<Value>) -> Result<Value, TrackerError> {
+        let mut request = self.request(method, path)?;
+        if let Some(body) = body {
+            request = request.body(Body::Json(body));
+        }
+
+        execute(&self.client, request).await
+    }
+
+    async fn get_page<T: serde::de::DeserializeOwned>(&self, path: &str, page: usize) -> Result<Vec<T>, TrackerError> {
+        let separator = if path.contains('?') { '&' } else { '?' };
+        let path = format!("{}{}per_page={}&page={}", path, separator, PAGE_SIZE, page);
+
+        parse(self.send("GET", path, None).await?)
+    }
+
+    async fn get_comments(&self, repository: &str, number: u64) -> Result<Vec<GithubComment>, TrackerError> {
+        let path = format!("/repos/{}/issues/{}/comments", repository, number);
+        let mut comments = Vec::new();
+
+        for page in 1.. {
+            let found: Vec<GithubComment> = self.get_page(&path, page).await?;
+            let last = found.len() < PAGE_SIZE;
+            comments.extend(found);
+            if last {
+                break;
+            }
+        }
+
+        Ok(comments)
+    }
+
+    async fn create_comment(&self, repository: &str, number: u64, body: String) -> Result<String, TrackerError> {
+        let data = self
+            .send(
+                "POST",
+                format!("/repos/{}/issues/{}/comments", repository, number),
+                Some(json!({ "body": body })),
+            )
+            .await?;
+
+        parse::<GithubComment>(data).map(|comment| comment.id.to_string())
+    }
+
+    async fn update_comment(&self, repository: &str, id: &str, body: String) -> Result<(), TrackerError> {
+        self.send(
+            "PATCH",
+            format!("/repos/{}/issues/comments/{}", repository, id),
+            Some(json!({ "body": body })),
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn delete_comment(&self, repository: &str, id: &str) -> Result<(), TrackerError> {
+        self.send(
+            "DELETE",
+            format!("/repos/{}/issues/comments/{}", repository, id),
+            None,
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn find_summary(
+        &self,
+        repository: &str,
+        number: u64,
+    ) -> Result<Option<(String, BTreeMap<String, u64>)>, TrackerError> {
+        let summary = self
+            .get_comments(repository, number)
+            .await?
+            .into_iter()
+            .filter(|comment| comment.user.login.eq_ignore_ascii_case(&self.user))
+            .find_map(|comment| {
+                summary_entries(&comment.body).map(|entries| (comment.id.to_string(), entries))
+            });
+
+        Ok(summary)
+    }
+
+    /// Applies the change to the summary comment of the issue and returns the comment id.
+    async fn update_summary(
+        &self,
+        issue_key: &str,
+        change: impl FnOnce(&mut BTreeMap<String, u64>),
+    ) -> Result<String, TrackerError> {
+        let (repository, number) = split_key(issue_key)?;
+        let summary = self.find_summary(repository, number).await?;
+        let mut entries = summary.as_ref().map(|(_, entries)| entries.clone()).unwrap_or_default();
+        change(&mut entries);
+
+        match summary {
+            Some((id, _)) if entries.is_empty() => {
+                self.delete_comment(repository, &id).await?;
+                Ok(id)
+            }
+            Some((id, _)) => {
+                self.update_comment(repository, &id, summary_comment(&entries)?).await?;
+                Ok(id)
+            }
+            None => {
+                self.create_comment(repository, number, summary_comment(&entries)?)
+                    .await
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Tracker for GithubTracker {
+    fn source(&self) -> &'static str {
+        GITHUB_SOURCE
+    }
+
+    fn user(&self) -> &str {
+        &self.user
+    }
+
+    fn default_query(&self) -> &str {
+        // the query is the comma separated list of repositories to import from
+        ""
+    }
+
+    fn pushes_worklogs(&self) -> bool {
+        self.worklog_mode != GithubWorklogMode::Off
+    }
+
+    async fn check_connection(&self) -> Result<String, TrackerError> {
+        let user: GithubUser = parse(self.send("GET", "/user".to_string(), None).await?)?;
+
+        Ok(user.login)
+    }
+
+    async fn get_issue(&self, key: &str) -> Result<Issue, TrackerError> {
+        let (repository, number) = split_key(key)?;
+        let data = self
+            .send("GET", format!("/repos/{}/issues/{}", repository, number), None)
+            .await?;
+
+        Ok(to_issue(repository, parse(data)?))
+    }
+
+    async fn search_issues(&self, query: &str) -> Result<Vec<Issue>, TrackerError> {
+        let repositories: Vec<&str> = match query.trim().is_empty() {
+            true => self.repositories.iter().map(|repo| repo.as_str()).collect(),
+            false => query.split(',').map(|repo| repo.trim()).filter(|repo| !repo.is_empty()).collect(),
+        };
+
+        let mut issues = Vec::new();
+        for repository in repositories {
+            let path = format!("/repos/{}/issues?state=open&assignee={}", repository, self.user);
+            for page in 1.. {
+                let found: Vec<GithubIssue> = self.get_page(&path, page).await?;
+                let last = found.len() < PAGE_SIZE;
+                issues.extend(
+                    found
+                        .into_iter()
+                        .filter(|issue| issue.pull_request.is_none())
+                        .map(|issue| to_issue(repository, issue)),
+                );
+                if last {
+                    break;
+                }
+            }
+        }
+
+        Ok(issues)
+    }
+
+    async fn create_worklog(&self, issue_key: &str, worklog: &Worklog) -> Result<WorklogReceipt, TrackerError> {
+        let remote_id = match self.worklog_mode {
+            GithubWorklogMode::Summary => {
+                self.update_summary(issue_key, |entries| {
+                    entries.insert(worklog.id.clone(), worklog.total_time);
+                })
+                .await?
+            }
+            _ => {
+                let (repository, number) = split_key(issue_key)?;
+                self.create_comment(repository, number, worklog_comment(worklog)?)
+                    .await?
+            }
+        };
+
+        Ok(WorklogReceipt::new(remote_id))
+    }
+
+    async fn update_worklog(
+        &self,
+        issue_key: &str,
+        remote_id: &str,
+        worklog: &Worklog,
+    ) -> Result<WorklogReceipt, TrackerError> {
+        let remote_id = match self.worklog_mode {
+            GithubWorklogMode::Summary => {
+                self.update_summary(issue_key, |entries| {
+                    entries.insert(worklog.id.clone(), worklog.total_time);
+                })
+                .await?
+            }
+            _ => {
+                let (repository, _) = split_key(issue_key)?;
+                self.update_comment(repository, remote_id, worklog_comment(worklog)?)
+                    .await?;
+                remote_id.to_string()
+            }
+        };
+
+        Ok(WorklogReceipt::new(remote_id))
+    }
+
+    async fn delete_worklog(
+        &self,
+        issue_key: &str,
+        remote_id: &str,
+        worklog: &Worklog,
+    ) -> Result<WorklogReceipt, TrackerError> {
+        match self.worklog_mode {
+            GithubWorklogMode::Summary => {
+                self.update_summary(issue_key, |entries| {
+                    entries.remove(&worklog.id);
+                })
+                .await?;
+            }
+            _ => {
+                let (repository, _) = split_key(issue_key)?;
+                self.delete_comment(repository, remote_id).await?;
+            }
+        }
+
+        Ok(WorklogReceipt::new(remote_id.to_string()))
+    }
+
+    async fn find_worklog(
+        &self,
+        issue_key: &str,
+        worklog: &Worklog,
+    ) -> Result<Option<String>, TrackerError> {
+        let (repository, number) = split_key(issue_key)?;
+
+        match self.worklog_mode {
+            GithubWorklogMode::Summary => {
+                let found = self
+                    .find_summary(repository, number)
+                    .await?
+                    .filter(|(_, entries)| entries.contains_key(&worklog.id))
+                    .map(|(id, _)| id);
+                Ok(found)
+            }
+            _ => {
+                let body = worklog_comment(worklog)?;
+                let found = self
+                    .get_comments(repository, number)
+                    .await?
+                    .into_iter()
+                    .find(|comment| comment.user.login.eq_ignore_ascii_case(&self.user) && comment.body == body)
+                    .map(|comment| comment.id.to_string());
+                Ok(found)
+            }
+        }
+    }
+}
+
+/// Issue keys look like `owner/repo#12`.
+fn split_key(key: &str) -> Result<(&str, u64), TrackerError> {
+    key.rsplit_once('#')
+        .and_then(|(repository, number)| Some((repository, number.parse::<u64>().ok()?)))
+        .ok_or_else(|| TrackerError::Rejected(0, format!("{} is not a github issue key", key)))
+}
+
+fn to_issue(repository: &str, issue: GithubIssue) -> Issue {
+    Issue {
+        id: Uuid::new_v4().to_string(),
+        name: format!("{}#{}", repository, issue.number),
+        url: issue.html_url,
+        summary: issue.title,
+        assignee_email: issue.assignee.map(|user| user.login).unwrap_or_default(),
+        source: GITHUB_SOURCE.to_string(),
+        status: issue.state,
+        project: repository.to_string(),
+        issue_type: "issue".to_string(),
+        tags: issue.labels.into_iter().map(|label| label.name).collect(),
+        ..Issue::empty()
+    }
+}
+
+fn worklog_comment(worklog: &Worklog) -> Result<String, TrackerError> {
+    let time = |seconds: u64| {
+        Utc.timestamp_opt(seconds as i64, 0)
+            .single()
+            .ok_or_else(|| TrackerError::Rejected(0, format!("invalid worklog time {}", seconds)))
+    };
+
+    Ok(format!(
+        "⏱ Logged **{}** ({} – {} UTC)",
+        format_duration(worklog.total_time),
+        time(worklog.start)?.format("%Y-%m-%d %H:%M"),
+        time(worklog.end)?.format("%H:%M"),
+    ))
+}
+
+fn summary_comment(entries: &BTreeMap<String, u64>) -> Result<String, TrackerError> {
+    let total: u64 = entries.values().sum();
+    let entries = serde_json::to_string(entries).map_err(|err| TrackerError::Rejected(0, err.to_string()))?;
+
+    Ok(format!(
+        "⏱ Time spent: **{}**\n\n{} {} -->",
+        format_duration(total),
+        SUMMARY_MARKER,
+        entries
+    ))
+}
+
+fn summary_entries(body: &str) -> Option<BTreeMap<String, u64>> {
+    let (_, rest) = body.split_once(SUMMARY_MARKER)?;
+    let (entries, _) = rest.split_once("-->")?;
+
+    serde_json::from_str(entries.trim()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::tracker::tests::{MockRequest, MockServer};
+
+    const ISSUE: &str = "owner/repo#1";
+
+    #[derive(Default)]
+    struct Repository {
+        issues: Vec<Value>,
+        comments: Vec<Value>,
+        next_comment: u64,
+    }
+
+    fn page_of(items: &[Value], query: &str) -> Value {
+        let param = |name: &str| {
+            query
+                .split('&')
+                .find_map(|pair| pair.strip_prefix(name)?.strip_prefix('='))
+                .and_then(|value| value.parse::<usize>().ok())
+        };
+        let (per_page, page) = (param("per_page").unwrap_or(30), param("page").unwrap_or(1));
+
+        items.iter().skip((page - 1) * per_page).take(per_page).cloned().collect()
+    }
+
+    fn answer(repository: &Mutex<Repository>, request: &MockRequest) -> (u16, Value) {
+        let mut repository = repository.lock().unwrap();
+        let (path, query) = request.path.split_once('?').unwrap_or((&request.path, ""));
+        let comment = path
+            .strip_prefix("/repos/owner/repo/issues/comments/")
+            .and_then(|id| id.parse::<u64>().ok())
+            .and_then(|id| repository.comments.iter().position(|comment| comment["id"] == id));
+
+        match (request.method.as_str(), path, comment) {
+            ("GET", "/repos/owner/repo/issues", _) => (200, page_of(&repository.issues, query)),
+            ("GET", "/repos/owner/repo/issues/1/comments", _) => (200, page_of(&repository.comments, query)),
+            ("POST", "/repos/owner/repo/issues/1/comments", _) => {
+                repository.next_comment += 1;
+                // github keeps the case the user signed up with
+                let comment = json!({ "id": repository.next_comment, "body": request.body["body"], "user": { "login": "Me" } });
+                repository.comments.push(comment.clone());
+                (201, comment)
+            }
+            ("PATCH", _, Some(i)) => {
+                repository.comments[i]["body"] = request.body["body"].clone();
+                (200, repository.comments[i].clone())
+            }
+            ("DELETE", _, Some(i)) => {
+                repository.comments.remove(i);
+                (204, Value::Null)
+            }
+            _ => (404, json!({ "message": "Not Found" })),
+        }
+    }
+
+    async fn github(worklog_mode: GithubWorklogMode, repository: Repository) -> (GithubTracker, MockServer, Arc<Mutex<Repository>>) {
+        let repository = Arc::new(Mutex::new(repository));
+        let served = repository.clone();
+        let server = MockServer::start(move |request| answer(&served, request)).await;
+        let tracker = GithubTracker {
+            api_url: server.url.clone(),
+            user: "me".to_string(),
+            token: "token".to_string(),
+            repositories: vec!["owner/repo".to_string()],
+            worklog_mode,
+            client: ClientBuilder::new().build().unwrap(),
+        };
+
+        (tracker, server, repository)
+    }
+
+    fn worklog(id: &str, total_time: u64) -> Worklog {
+        Worklog::new(id.to_string(), "issue".to_string(), 1715000000, 1715000000 + total_time, total_time)
+    }
+
+    fn bodies(repository: &Mutex<Repository>) -> Vec<String> {
+        let repository = repository.lock().unwrap();
+        repository.comments.iter().map(|comment| comment["body"].as_str().unwrap().to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn search_reads_every_page_and_skips_pull_requests() {
+        let issues = (1..=150)
+            .map(|number| match number % 10 {
+                0 => json!({ "number": number, "title": "pr", "html_url": "", "state": "open", "pull_request": {} }),
+                _ => json!({ "number": number, "title": "issue", "html_url": "", "state": "open" }),
+            })
+            .collect();
+        let (tracker, server, _) = github(GithubWorklogMode::Comment, Repository { issues, ..Repository::default() }).await;
+
+        let found = tracker.search_issues("").await.unwrap();
+
+        assert_eq!(found.len(), 135);
+        assert_eq!(found[0].name, "owner/repo#1");
+        assert!(found.iter().all(|issue| issue.summary == "issue"));
+        let paths: Vec<String> = server.requests().into_iter().map(|request| request.path).collect();
+        assert_eq!(paths, vec![
+            "/repos/owner/repo/issues?state=open&assignee=me&per_page=100&page=1",
+            "/repos/owner/repo/issues?state=open&assignee=me&per_page=100&page=2",
+        ]);
+    }
+
+    #[tokio::test]
+    async fn comment_mode_writes_a_comment_per_worklog() {
+        let (tracker, _server, repository) = github(GithubWorklogMode::Comment, Repository::default()).await;
+
+        let created = tracker.create_worklog(ISSUE, &worklog("a", 3600)).await.unwrap();
+        tracker.create_worklog(ISSUE, &worklog("b", 60)).await.unwrap();
+        assert_eq!(bodies(&repository).len(), 2);
+        assert!(bodies(&repository)[0].contains("**1h**"));
+
+        let updated = tracker.update_worklog(ISSUE, &created.remote_id, &worklog("a", 5400)).await.unwrap();
+        assert_eq!(updated.remote_id, created.remote_id);
+        assert!(bodies(&repository)[0].contains("**1h 30m**"));
+
+        tracker.delete_worklog(ISSUE, &created.remote_id, &worklog("a", 5400)).await.unwrap();
+        assert_eq!(bodies(&repository).len(), 1);
+        assert!(bodies(&repository)[0].contains("**1m**"));
+    }
+
+    #[tokio::test]
+    async fn comment_mode_finds_a_created_comment() {
+        let (tracker, _server, _) = github(GithubWorklogMode::Comment, Repository::default()).await;
+        assert_eq!(tracker.find_worklog(ISSUE, &worklog("a", 3600)).await.unwrap(), None);
+
+        let created = tracker.create_worklog(ISSUE, &worklog("a", 3600)).await.unwrap();
+
+        let found = tracker.find_worklog(ISSUE, &worklog("a", 3600)).await.unwrap();
+        assert_eq!(found, Some(created.remote_id));
+    }
+
+    #[tokio::test]
+    async fn summary_mode_keeps_one_comment_for_every_worklog() {
+        // a summary of somebody else is left alone
+        let foreign = json!({
+            "id": 100,
+            "body": format!("{} {{\"x\":60}} -->", SUMMARY_MARKER),
+            "user": { "login": "someone" },
+        });
+        let repository = Repository { comments: vec![foreign], ..Repository::default() };
+        let (tracker, _server, repository) = github(GithubWorklogMode::Summary, repository).await;
+
+        let a = tracker.create_worklog(ISSUE, &worklog("a", 3600)).await.unwrap();
+        let b = tracker.create_worklog(ISSUE, &worklog("b", 1800)).await.unwrap();
+        assert_eq!(a.remote_id, b.remote_id);
+        assert_eq!(bodies(&repository).len(), 2);
+        assert!(bodies(&repository)[1].contains("**1h 30m**"));
+        assert_eq!(summary_entries(&bodies(&repository)[1]).unwrap().len(), 2);
+
+        tracker.update_worklog(ISSUE, &a.remote_id, &worklog("a", 7200)).await.unwrap();
+        assert!(bodies(&repository)[1].contains("**2h 30m**"));
+        let found = tracker.find_worklog(ISSUE, &worklog("b", 1800)).await.unwrap();
+        assert_eq!(found, Some(b.remote_id.clone()));
+
+        tracker.delete_worklog(ISSUE, &a.remote_id, &worklog("a", 7200)).await.unwrap();
+        assert!(bodies(&repository)[1].contains("**30m**"));
+        tracker.delete_worklog(ISSUE, &b.remote_id, &worklog("b", 1800)).await.unwrap();
+        assert_eq!(bodies(&repository).len(), 1);
+        assert!(bodies(&repository)[0].contains("\"x\":60"));
+    }
+}
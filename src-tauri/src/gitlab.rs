@@ -0,0 +1,460 @@
+use async_trait::async_trait;
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{json, Value};
+use shared::{GitlabConfig, Issue, Worklog};
+use tauri::{
+    api::http::{Body, Client, ClientBuilder, HttpRequestBuilder},
+    Error,
+};
+use uuid::Uuid;
+
+use crate::tracker::{execute, parse, Tracker, TrackerError, WorklogReceipt};
+
+pub const GITLAB_SOURCE: &str = "gitlab";
+const PAGE_SIZE: usize = 100;
+
+pub struct GitlabTracker {
+    pub base_url: String,
+    pub user: String,
+    pub token: String,
+    pub client: Client,
+}
+
+pub fn setup_gitlab(config: &GitlabConfig) -> Result<GitlabTracker, Error> {
+    let client = ClientBuilder::new().build()?;
+
+    Ok(GitlabTracker {
+        base_url: config.url.trim_end_matches('/').to_string(),
+        user: config.user.clone(),
+        token: config.token.clone(),
+        client,
+    })
+}
+
+#[derive(Deserialize, Debug)]
+struct GitlabIssue {
+    title: String,
+    web_url: String,
+    state: String,
+    issue_type: Option<String>,
+    assignee: Option<GitlabUser>,
+    time_stats: GitlabTimeStats,
+    references: GitlabReferences,
+    #[serde(default)]
+    labels: Vec<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct GitlabUser {
+    username: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct GitlabTimeStats {
+    time_estimate: u64,
+    total_time_spent: u64,
+}
+
+#[derive(Deserialize, Debug)]
+struct GitlabReferences {
+    full: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct GitlabTimelog {
+    time_spent: i64,
+    summary: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct GitlabPageInfo {
+    has_next_page: bool,
+    end_cursor: Option<String>,
+}
+
+// the rest api only has the total time spent, the single timelogs are in graphql
+const TIMELOGS_QUERY: &str = r#"
+query($project: ID!, $iid: String!, $after: String) {
+  project(fullPath: $project) {
+    issue(iid: $iid) {
+      timelogs(first: 100, after: $after) {
+        nodes { timeSpent summary }
+        pageInfo { hasNextPage endCursor }
+      }
+    }
+  }
+}
+"#;
+
+impl GitlabTracker {
+    fn request(&self, method: &str, path: String) -> Result<HttpRequestBuilder, TrackerError> {
+        let link = format!("{}/api/v4{}", self.base_url, path);
+        let invalid = |err: tauri::api::Error| TrackerError::Rejected(0, err.to_string());
+
+        HttpRequestBuilder::new(method, link)
+            .map_err(invalid)?
+            .header("PRIVATE-TOKEN", self.token.as_str())
+            .map_err(invalid)
+    }
+
+    async fn send(&self, method: &str, path: String, body: Option<Value>) -> Result<Value, TrackerError> {
+        let mut request = self.request(method, path)?;
+        if let Some(body) = body {
+            request = request.body(Body::Json(body));
+        }
+
+        execute(&self.client, request).await
+    }
+
+    async fn get_all<T: DeserializeOwned>(&self, path: &str) -> Result<Vec<T>, TrackerError> {
+        let separator = if path.contains('?') { '&' } else { '?' };
+        let mut found = Vec::new();
+
+        for page in 1.. {
+            let path = format!("{}{}per_page={}&page={}", path, separator, PAGE_SIZE, page);
+            let items: Vec<T> = parse(self.send("GET", path, None).await?)?;
+            let last = items.len() < PAGE_SIZE;
+            found.extend(items);
+            if last {
+                break;
+            }
+        }
+
+        Ok(found)
+    }
+
+    async fn graphql(&self, query: &str, variables: Value) -> Result<Value, TrackerError> {
+        let link = format!("{}/api/graphql", self.base_url);
+        let invalid = |err: tauri::api::Error| TrackerError::Rejected(0, err.to_string());
+        let request = HttpRequestBuilder::new("POST", link)
+            .map_err(invalid)?
+            .header("Authorization", format!("Bearer {}", self.token))
+            .map_err(invalid)?
+            .body(Body::Json(json!({ "query": query, "variables": variables })));
+
+        let mut data = execute(&self.client, request).await?;
+        // graphql answers 200 with the errors in the body
+        if let Some(errors) = data.get("errors") {
+            return Err(TrackerError::Rejected(0, errors.to_string()));
+        }
+
+        Ok(data["data"].take())
+    }
+
+    /// Adds the seconds, negative ones included, to the time spent on the issue and
+    /// returns the time now spent on it.
+    async fn add_spent_time(&self, issue_key: &str, seconds: i64, worklog: &Worklog) -> Result<u64, TrackerError> {
+        let (project, iid) = split_key(issue_key)?;
+        let data = self
+            .send(
+                "POST",
+                format!("/projects/{}/issues/{}/add_spent_time", encode_path(project), iid),
+                Some(json!({
+                    "duration": gitlab_duration(seconds),
+                    "summary": worklog_summary(worklog),
+                })),
+            )
+            .await?;
+
+        parse::<GitlabTimeStats>(data).map(|stats| stats.total_time_spent)
+    }
+
+    /// Seconds the timelogs of the worklog add up to, every spend of it is a timelog
+    /// with its summary, corrections included.
+    async fn logged_seconds(&self, issue_key: &str, worklog: &Worklog) -> Result<i64, TrackerError> {
+        let (project, iid) = split_key(issue_key)?;
+        let summary = worklog_summary(worklog);
+        let mut after: Option<String> = None;
+        let mut seconds = 0;
+
+        loop {
+            let variables = json!({ "project": project, "iid": iid.to_string(), "after": after });
+            let data = self.graphql(TIMELOGS_QUERY, variables).await?;
+            let timelogs = &data["project"]["issue"]["timelogs"];
+            if timelogs.is_null() {
+                return Err(TrackerError::Rejected(404, format!("gitlab has no issue {}", issue_key)));
+            }
+
+            let nodes: Vec<GitlabTimelog> = parse(timelogs["nodes"].clone())?;
+            seconds += nodes
+                .iter()
+                .filter(|timelog| timelog.summary.as_deref() == Some(summary.as_str()))
+                .map(|timelog| timelog.time_spent)
+                .sum::<i64>();
+
+            let page: GitlabPageInfo = parse(timelogs["pageInfo"].clone())?;
+            match page.end_cursor.filter(|_| page.has_next_page) {
+                Some(cursor) => after = Some(cursor),
+                None => return Ok(seconds),
+            }
+        }
+    }
+
+    /// Spends the difference between the time the worklog has logged and the wanted one.
+    async fn log_seconds(&self, issue_key: &str, seconds: i64, worklog: &Worklog) -> Result<WorklogReceipt, TrackerError> {
+        let difference = seconds - self.logged_seconds(issue_key, worklog).await?;
+        let issue_time_spent = match difference {
+            0 => None,
+            _ => Some(self.add_spent_time(issue_key, difference, worklog).await?),
+        };
+
+        Ok(WorklogReceipt {
+            remote_id: worklog_summary(worklog),
+            issue_time_spent,
+        })
+    }
+}
+
+#[async_trait]
+impl Tracker for GitlabTracker {
+    fn source(&self) -> &'static str {
+        GITLAB_SOURCE
+    }
+
+    fn user(&self) -> &str {
+        &self.user
+    }
+
+    fn default_query(&self) -> &str {
+        // the query is a comma separated list of project paths, empty for every project
+        ""
+    }
+
+    async fn check_connection(&self) -> Result<String, TrackerError> {
+        let user: GitlabUser = parse(self.send("GET", "/user".to_string(), None).await?)?;
+
+        Ok(user.username)
+    }
+
+    async fn get_issue(&self, key: &str) -> Result<Issue, TrackerError> {
+        let (project, iid) = split_key(key)?;
+        let data = self
+            .send("GET", format!("/projects/{}/issues/{}", encode_path(project), iid), None)
+            .await?;
+
+        parse::<GitlabIssue>(data).map(Issue::from)
+    }
+
+    async fn search_issues(&self, query: &str) -> Result<Vec<Issue>, TrackerError> {
+        let projects: Vec<&str> = query
+            .split(',')
+            .map(|project| project.trim())
+            .filter(|project| !project.is_empty())
+            .collect();
+
+        let mut issues: Vec<GitlabIssue> = Vec::new();
+        if projects.is_empty() {
+            issues.extend(self.get_all("/issues?scope=assigned_to_me&state=opened").await?);
+        }
+        for project in projects {
+            let path = format!(
+                "/projects/{}/issues?assignee_username={}&state=opened",
+                encode_path(project),
+                self.user
+            );
+            issues.extend(self.get_all(&path).await?);
+        }
+
+        Ok(issues.into_iter().map(Issue::from).collect())
+    }
+
+    // gitlab has no worklogs, only timelogs adding to the time spent on the issue. The
+    // remote id is the summary every timelog of the worklog carries, and updates and deletes
+    // spend the difference to what those timelogs add up to, so a retried write is not
+    // counted twice
+    async fn create_worklog(&self, issue_key: &str, worklog: &Worklog) -> Result<WorklogReceipt, TrackerError> {
+        let spent = self
+            .add_spent_time(issue_key, worklog.total_time as i64, worklog)
+            .await?;
+
+        Ok(WorklogReceipt {
+            remote_id: worklog_summary(worklog),
+            issue_time_spent: Some(spent),
+        })
+    }
+
+    async fn update_worklog(
+        &self,
+        issue_key: &str,
+        _remote_id: &str,
+        worklog: &Worklog,
+    ) -> Result<WorklogReceipt, TrackerError> {
+        self.log_seconds(issue_key, worklog.total_time as i64, worklog).await
+    }
+
+    async fn delete_worklog(
+        &self,
+        issue_key: &str,
+        _remote_id: &str,
+        worklog: &Worklog,
+    ) -> Result<WorklogReceipt, TrackerError> {
+        self.log_seconds(issue_key, 0, worklog).await
+    }
+
+    async fn find_worklog(
+        &self,
+        issue_key: &str,
+        worklog: &Worklog,
+    ) -> Result<Option<String>, TrackerError> {
+        let logged = self.logged_seconds(issue_key, worklog).await?;
+
+        Ok((logged != 0).then(|| worklog_summary(worklog)))
+    }
+}
+
+impl From<GitlabIssue> for Issue {
+    fn from(value: GitlabIssue) -> Self {
+        let project = split_key(&value.references.full)
+            .map(|(project, _)| project.to_string())
+            .unwrap_or_default();
+
+        Issue {
+            id: Uuid::new_v4().to_string(),
+            name: value.references.full,
+            url: value.web_url,
+            summary: value.title,
+            assignee_email: value.assignee.map(|user| user.username).unwrap_or_default(),
+            source: GITLAB_SOURCE.to_string(),
+            status: value.state,
+            project,
+            issue_type: value.issue_type.unwrap_or("issue".to_string()),
+            time_estimate: value.time_stats.time_estimate,
+            remote_time_spent: value.time_stats.total_time_spent,
+            tags: value.labels,
+            ..Issue::empty()
+        }
+    }
+}
+
+/// Issue keys look like `group/project#12`.
+fn split_key(key: &str) -> Result<(&str, u64), TrackerError> {
+    key.rsplit_once('#')
+        .and_then(|(project, iid)| Some((project, iid.parse::<u64>().ok()?)))
+        .ok_or_else(|| TrackerError::Rejected(0, format!("{} is not a gitlab issue key", key)))
+}
+
+fn encode_path(project: &str) -> String {
+    project.replace('/', "%2F")
+}
+
+fn worklog_summary(worklog: &Worklog) -> String {
+    format!("time-track worklog {}", worklog.id)
+}
+
+/// Formats seconds the way gitlab parses durations, e.g. `-1h30m0s`.
+fn gitlab_duration(seconds: i64) -> String {
+    let sign = if seconds < 0 { "-" } else { "" };
+    let seconds = seconds.unsigned_abs();
+
+    format!("{}{}h{}m{}s", sign, seconds / 3600, (seconds % 3600) / 60, seconds % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::tracker::tests::{MockRequest, MockServer};
+
+    const ISSUE: &str = "group/project#12";
+    const PAGE: usize = 2;
+
+    fn seconds(duration: &str) -> i64 {
+        let (sign, duration) = match duration.strip_prefix('-') {
+            Some(rest) => (-1, rest),
+            None => (1, duration),
+        };
+        let (hours, rest) = duration.split_once('h').unwrap();
+        let (minutes, rest) = rest.split_once('m').unwrap();
+        let seconds = rest.trim_end_matches('s');
+
+        sign * (hours.parse::<i64>().unwrap() * 3600 + minutes.parse::<i64>().unwrap() * 60 + seconds.parse::<i64>().unwrap())
+    }
+
+    // timelogs are (seconds, summary), graphql pages hold two of them to go through the cursors
+    fn answer(timelogs: &Mutex<Vec<(i64, String)>>, request: &MockRequest) -> (u16, Value) {
+        let mut timelogs = timelogs.lock().unwrap();
+        match (request.method.as_str(), request.path.as_str()) {
+            ("POST", "/api/v4/projects/group%2Fproject/issues/12/add_spent_time") => {
+                let summary = request.body["summary"].as_str().unwrap().to_string();
+                timelogs.push((seconds(request.body["duration"].as_str().unwrap()), summary));
+                let total: i64 = timelogs.iter().map(|(seconds, _)| seconds).sum();
+                (201, json!({ "time_estimate": 0, "total_time_spent": total }))
+            }
+            ("POST", "/api/graphql") => {
+                let variables = &request.body["variables"];
+                assert_eq!(variables["project"], "group/project");
+                assert_eq!(variables["iid"], "12");
+                let start = variables["after"].as_str().map_or(0, |after| after.parse::<usize>().unwrap());
+                let nodes: Vec<Value> = timelogs
+                    .iter()
+                    .skip(start)
+                    .take(PAGE)
+                    .map(|(seconds, summary)| json!({ "timeSpent": seconds, "summary": summary }))
+                    .collect();
+                let next = start + PAGE;
+                let page_info = json!({ "hasNextPage": next < timelogs.len(), "endCursor": next.to_string() });
+                (200, json!({ "data": { "project": { "issue": { "timelogs": { "nodes": nodes, "pageInfo": page_info } } } } }))
+            }
+            _ => (404, json!({ "message": "404 Not Found" })),
+        }
+    }
+
+    async fn gitlab(timelogs: Vec<(i64, String)>) -> (GitlabTracker, MockServer, Arc<Mutex<Vec<(i64, String)>>>) {
+        let timelogs = Arc::new(Mutex::new(timelogs));
+        let served = timelogs.clone();
+        let server = MockServer::start(move |request| answer(&served, request)).await;
+        let tracker = GitlabTracker {
+            base_url: server.url.clone(),
+            user: "me".to_string(),
+            token: "token".to_string(),
+            client: ClientBuilder::new().build().unwrap(),
+        };
+
+        (tracker, server, timelogs)
+    }
+
+    fn worklog(total_time: u64) -> Worklog {
+        Worklog::new("w1".to_string(), "issue".to_string(), 1715000000, 1715000000 + total_time, total_time)
+    }
+
+    #[tokio::test]
+    async fn writes_spend_the_difference_to_the_logged_time() {
+        // time of others on the issue is not touched
+        let others = vec![(600, "review".to_string()), (300, "time-track worklog w2".to_string())];
+        let (tracker, server, timelogs) = gitlab(others).await;
+
+        let created = tracker.create_worklog(ISSUE, &worklog(3600)).await.unwrap();
+        assert_eq!(created.remote_id, "time-track worklog w1");
+        assert_eq!(created.issue_time_spent, Some(4500));
+
+        let updated = tracker.update_worklog(ISSUE, &created.remote_id, &worklog(5400)).await.unwrap();
+        assert_eq!(updated.issue_time_spent, Some(6300));
+        // a retried update finds the time already logged
+        let retried = tracker.update_worklog(ISSUE, &created.remote_id, &worklog(5400)).await.unwrap();
+        assert_eq!(retried.issue_time_spent, None);
+        assert_eq!(timelogs.lock().unwrap().len(), 4);
+
+        tracker.delete_worklog(ISSUE, &created.remote_id, &worklog(5400)).await.unwrap();
+        tracker.delete_worklog(ISSUE, &created.remote_id, &worklog(5400)).await.unwrap();
+        let logged: i64 = timelogs.lock().unwrap().iter().map(|(seconds, _)| seconds).sum();
+        assert_eq!(logged, 900);
+        let spends = server.requests().into_iter().filter(|request| request.path.ends_with("add_spent_time")).count();
+        assert_eq!(spends, 3);
+    }
+
+    #[tokio::test]
+    async fn finds_a_worklog_by_its_timelogs() {
+        let (tracker, _server, _) = gitlab(vec![(600, "review".to_string())]).await;
+        assert_eq!(tracker.find_worklog(ISSUE, &worklog(3600)).await.unwrap(), None);
+
+        tracker.create_worklog(ISSUE, &worklog(3600)).await.unwrap();
+
+        let found = tracker.find_worklog(ISSUE, &worklog(3600)).await.unwrap();
+        assert_eq!(found, Some("time-track worklog w1".to_string()));
+    }
+}
@@ -0,0 +1,455 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use shared::{IdleAction, IdlePeriod, Worklog};
+use tauri::{AppHandle, Manager, State};
+use uuid::Uuid;
+
+use crate::{commands::finish_worklog, events, sync, MangedState};
+
+const IDLE_POLL: Duration = Duration::from_secs(10);
+
+/// Tells for how long the user has not touched the keyboard or the mouse.
+pub trait IdleDetector: Send + Sync {
+    fn idle_seconds(&self) -> Result<u64, String>;
+}
+
+/// Picks the detector for the session, `IDLE_DETECTOR` overrides it with `x11`, `wayland`,
+/// `off` or `fake:<seconds>,<seconds>,...`.
+pub fn setup_idle_detector() -> Option<Box<dyn IdleDetector>> {
+    let chosen = std::env::var("IDLE_DETECTOR").unwrap_or_default();
+    if let Some(script) = chosen.strip_prefix("fake:") {
+        return Some(Box::new(FakeIdleDetector::new(script)));
+    }
+
+    match chosen.as_str() {
+        "off" => None,
+        "wayland" => Some(Box::new(WaylandIdleDetector)),
+        #[cfg(target_os = "linux")]
+        "x11" => x11::X11IdleDetector::open().map(|detector| Box::new(detector) as Box<dyn IdleDetector>),
+        _ if std::env::var("WAYLAND_DISPLAY").is_ok() => Some(Box::new(WaylandIdleDetector)),
+        #[cfg(target_os = "linux")]
+        _ if std::env::var("DISPLAY").is_ok() => {
+            x11::X11IdleDetector::open().map(|detector| Box::new(detector) as Box<dyn IdleDetector>)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod x11 {
+    use std::ptr;
+
+    use x11_dl::{xlib::Xlib, xss::Xss};
+
+    use super::IdleDetector;
+
+    /// Reads the idle time from the XScreenSaver extension, the libraries are loaded at runtime.
+    pub struct X11IdleDetector {
+        xlib: Xlib,
+        xss: Xss,
+    }
+
+    // the loaded libraries are only tables of function pointers
+    unsafe impl Send for X11IdleDetector {}
+    unsafe impl Sync for X11IdleDetector {}
+
+    impl X11IdleDetector {
+        pub fn open() -> Option<Self> {
+            match (Xlib::open(), Xss::open()) {
+                (Ok(xlib), Ok(xss)) => Some(Self { xlib, xss }),
+                (Err(e), _) | (_, Err(e)) => {
+                    log::warn!("no x11 idle detection: {}", e);
+                    None
+                }
+            }
+        }
+    }
+
+    impl IdleDetector for X11IdleDetector {
+        fn idle_seconds(&self) -> Result<u64, String> {
+            unsafe {
+                let display = (self.xlib.XOpenDisplay)(ptr::null());
+                if display.is_null() {
+                    return Err("can't open the X display".to_string());
+                }
+
+                let info = (self.xss.XScreenSaverAllocInfo)();
+                let status = match info.is_null() {
+                    true => 0,
+                    false => (self.xss.XScreenSaverQueryInfo)(display, (self.xlib.XDefaultRootWindow)(display), info),
+                };
+                let idle = match status {
+                    0 => Err("the X server has no XScreenSaver extension".to_string()),
+                    _ => Ok((*info).idle / 1000),
+                };
+
+                if !info.is_null() {
+                    (self.xlib.XFree)(info.cast());
+                }
+                (self.xlib.XCloseDisplay)(display);
+
+                idle
+            }
+        }
+    }
+}
+
+/// Asks the compositor over D-Bus, wayland itself does not tell other clients about input.
+pub struct WaylandIdleDetector;
+
+impl WaylandIdleDetector {
+    fn call(destination: &str, path: &str, method: &str) -> Result<u64, String> {
+        let output = std::process::Command::new("gdbus")
+            .args(["call", "--session", "--dest", destination, "--object-path", path, "--method", method])
+            .output()
+            .map_err(|e| e.to_string())?;
+        if !output.status.success() {
+            return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+        }
+
+        // answers look like `(uint64 1234,)`
+        String::from_utf8_lossy(&output.stdout)
+            .split(|c: char| !c.is_ascii_digit())
+            .rfind(|part| !part.is_empty())
+            .and_then(|millis| millis.parse::<u64>().ok())
+            .ok_or(format!("unexpected answer from {}", destination))
+    }
+}
+
+impl IdleDetector for WaylandIdleDetector {
+    fn idle_seconds(&self) -> Result<u64, String> {
+        // gnome, then the freedesktop screensaver kde implements
+        Self::call(
+            "org.gnome.Mutter.IdleMonitor",
+            "/org/gnome/Mutter/IdleMonitor/Core",
+            "org.gnome.Mutter.IdleMonitor.GetIdletime",
+        )
+        .or_else(|_| {
+            Self::call(
+                "org.freedesktop.ScreenSaver",
+                "/org/freedesktop/ScreenSaver",
+                "org.freedesktop.ScreenSaver.GetSessionIdleTime",
+            )
+        })
+        .map(|millis| millis / 1000)
+    }
+}
+
+/// Answers with the scripted idle times one poll after another and keeps repeating the last
+/// one, for trying the idle prompt without walking away.
+pub struct FakeIdleDetector {
+    script: Mutex<VecDeque<u64>>,
+}
+
+impl FakeIdleDetector {
+    pub fn new(script: &str) -> Self {
+        let script = script
+            .split(',')
+            .filter_map(|seconds| seconds.trim().parse::<u64>().ok())
+            .collect();
+
+        Self {
+            script: Mutex::new(script),
+        }
+    }
+}
+
+impl IdleDetector for FakeIdleDetector {
+    fn idle_seconds(&self) -> Result<u64, String> {
+        let mut script = self.script.lock().map_err(|e| e.to_string())?;
+        match script.len() {
+            0 => Ok(0),
+            1 => Ok(script[0]),
+            _ => Ok(script.pop_front().unwrap_or_default()),
+        }
+    }
+}
+
+/// Background task noticing when the user walks away from a tracked issue and comes back,
+/// the window is brought up to ask what to do with the time in between.
+pub async fn run_idle_watch(app_state: Arc<MangedState>, app_handle: AppHandle, detector: Box<dyn IdleDetector>) {
+    loop {
+        tokio::time::sleep(IDLE_POLL).await;
+
+        let now = match sync::now() {
+            Ok(now) => now as u64,
+            Err(_) => continue,
+        };
+        match poll_idle(&app_state, detector.as_ref(), now).await {
+            Ok(true) => {
+                if let Some(window) = app_handle.get_window("main") {
+                    let _ = window.show();
+                    let _ = window.set_focus();
+                }
+            }
+            Ok(false) => {}
+            Err(e) => log::warn!("idle detection: {}", e),
+        }
+    }
+}
+
+/// Asks the detector once, an idle period starts when the user has been away for the
+/// threshold and ends when they are back. True when they just came back.
+async fn poll_idle(app_state: &MangedState, detector: &dyn IdleDetector, now: u64) -> Result<bool, String> {
+    let (worklog_start, threshold) = {
+        let m = app_state.0.lock().await;
+        match &m.tracing_info {
+            Some(info) if m.config.idle.enabled && !info.paused => (info.current_worklog.start, m.config.idle.threshold_minutes * 60),
+            _ => return Ok(false),
+        }
+    };
+
+    // asking may wait on another process, the state is not locked meanwhile
+    let idle = detector.idle_seconds()?;
+
+    let mut m = app_state.0.lock().await;
+    if m.tracing_info.is_none() {
+        return Ok(false);
+    }
+    match &mut m.idle {
+        None if idle >= threshold => {
+            m.idle = Some(IdlePeriod {
+                start: now.saturating_sub(idle).max(worklog_start),
+                end: None,
+            });
+            Ok(false)
+        }
+        Some(period) if period.end.is_none() && idle < threshold => {
+            period.end = Some(now.saturating_sub(idle));
+            Ok(true)
+        }
+        _ => Ok(false),
+    }
+}
+
+/// Keeps the idle time in the tracked worklog, cuts it out, or cuts it out and logs it on
+/// another issue. Cutting ends the worklog where the idle time started and goes on with a
+/// new one from where it ended.
+#[tauri::command(async)]
+pub async fn resolve_idle(
+    action: IdleAction,
+    app_state: State<'_, Arc<MangedState>>,
+    app_handle: AppHandle,
+) -> Result<(), String> {
+    let res = cut_idle(action, &app_state).await;
+    events::issues_changed(&app_handle);
+    events::tracking_changed(&app_handle, &app_state, events::TRACKING_TICK).await;
+    res
+}
+
+async fn cut_idle(action: IdleAction, app_state: &MangedState) -> Result<(), String> {
+    let mut m = app_state.0.lock().await;
+    let (start, end) = match &m.idle {
+        Some(IdlePeriod { start, end: Some(end) }) => (*start, *end),
+        Some(_) => return Err("still idle".to_string()),
+        None => return Err("there is no idle time".to_string()),
+    };
+    let mut info = match (&action, m.tracing_info.clone()) {
+        (IdleAction::Keep, _) | (_, None) => {
+            m.idle = None;
+            return Ok(());
+        }
+        (_, Some(info)) => info,
+    };
+
+    if let IdleAction::Assign(name) = &action {
+        let issue = m.storage.get_issue(name.clone()).await?;
+        let worklog = m
+            .storage
+            .add_worklog(Worklog {
+                id: Uuid::new_v4().to_string(),
+                issue_id: issue.id,
+                start,
+                end: 0,
+                total_time: 0,
+                remote_id: None,
+                synced: false,
+            })
+            .await?;
+        finish_worklog(&m, worklog, end, m.config.rounding.apply(end - start)).await?;
+    }
+
+    let worked = start.saturating_sub(info.current_worklog.start);
+    if worked > 0 {
+        let total_time = info.worklog_total(&m.config.rounding, worked);
+        let worklog = finish_worklog(&m, info.current_worklog.clone(), start, total_time).await?;
+        info.add_to_session(&worklog, Duration::from_secs(worked));
+        info.current_worklog = m
+            .storage
+            .add_worklog(Worklog {
+                id: Uuid::new_v4().to_string(),
+                start: end,
+                end: 0,
+                total_time: 0,
+                remote_id: None,
+                synced: false,
+                ..info.current_worklog
+            })
+            .await?;
+    } else {
+        // idle from the start, the worklog begins when the user came back
+        info.current_worklog.start = end;
+        info.current_worklog = m.storage.edit_worklog(info.current_worklog).await?;
+    }
+
+    let since_back = Duration::from_secs((sync::now()? as u64).saturating_sub(end));
+    info.start_time = Instant::now().checked_sub(since_back).unwrap_or_else(Instant::now);
+
+    m.tracing_info = Some(info);
+    m.idle = None;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use shared::{Issue, RoundingConfig, RoundingMode};
+
+    use super::*;
+    use crate::{commands::stop_tracking, tests::test_state, TracingInfo};
+
+    const START: u64 = 1714554000; // of the tracked worklog, the threshold is 5 minutes
+
+    async fn track(app_state: &MangedState) {
+        let mut m = app_state.0.lock().await;
+        let mut tracked = None;
+        for (id, name) in [("i1", "ABC-1"), ("i2", "ABC-2")] {
+            let issue = Issue {
+                id: id.to_string(),
+                name: name.to_string(),
+                ..Issue::empty()
+            };
+            tracked = tracked.or(Some(m.storage.add_issue(issue).await.unwrap()));
+        }
+        let worklog = Worklog::new("w1".to_string(), "i1".to_string(), START, 0, 0);
+        m.tracing_info = Some(TracingInfo {
+            time_tracing_issue: tracked.unwrap(),
+            current_worklog: m.storage.add_worklog(worklog).await.unwrap(),
+            start_time: Instant::now(),
+            session_time: Duration::ZERO,
+            session_logged: 0,
+            paused: false,
+        });
+    }
+
+    /// The user is seen away for `away` seconds at `seen`, and back at `back`.
+    async fn go_idle(app_state: &MangedState, away: u64, seen: u64, back: u64) {
+        let detector = FakeIdleDetector::new(&format!("{},0", away));
+        assert!(!poll_idle(app_state, &detector, START + seen).await.unwrap());
+        assert!(poll_idle(app_state, &detector, START + back).await.unwrap());
+    }
+
+    /// Start, end and total of the worklogs of the issue.
+    async fn worklogs(app_state: &MangedState, issue_id: &str) -> Vec<(u64, u64, u64)> {
+        let m = app_state.0.lock().await;
+        let worklogs = m.storage.get_issue_worklogs(issue_id).await.unwrap();
+        worklogs.into_iter().map(|worklog| (worklog.start, worklog.end, worklog.total_time)).collect()
+    }
+
+    async fn session(app_state: &MangedState) -> (u64, Duration) {
+        let m = app_state.0.lock().await;
+        let info = m.tracing_info.as_ref().unwrap();
+        assert!(m.idle.is_none());
+        (info.current_worklog.start, info.session_time)
+    }
+
+    #[tokio::test]
+    async fn short_breaks_are_not_idle() {
+        let app_state = test_state().await;
+        track(&app_state).await;
+
+        let detector = FakeIdleDetector::new("299,0");
+        assert!(!poll_idle(&app_state, &detector, START + 1000).await.unwrap());
+        assert!(!poll_idle(&app_state, &detector, START + 1100).await.unwrap());
+
+        assert!(app_state.0.lock().await.idle.is_none());
+    }
+
+    #[tokio::test]
+    async fn idle_time_has_to_end_before_it_is_resolved() {
+        let app_state = test_state().await;
+        track(&app_state).await;
+
+        let detector = FakeIdleDetector::new("400");
+        poll_idle(&app_state, &detector, START + 1400).await.unwrap();
+
+        assert_eq!(cut_idle(IdleAction::Discard, &app_state).await, Err("still idle".to_string()));
+    }
+
+    #[tokio::test]
+    async fn keep_leaves_the_worklog_as_it_is() {
+        let app_state = test_state().await;
+        track(&app_state).await;
+        go_idle(&app_state, 400, 1400, 2000).await;
+
+        cut_idle(IdleAction::Keep, &app_state).await.unwrap();
+
+        assert_eq!(worklogs(&app_state, "i1").await, vec![(START, 0, 0)]);
+        assert_eq!(session(&app_state).await, (START, Duration::ZERO));
+    }
+
+    #[tokio::test]
+    async fn discard_splits_the_worklog_around_the_idle_time() {
+        let app_state = test_state().await;
+        app_state.0.lock().await.config.rounding = RoundingConfig {
+            minutes: 15,
+            mode: RoundingMode::Up,
+        };
+        track(&app_state).await;
+        go_idle(&app_state, 400, 1400, 2000).await;
+
+        cut_idle(IdleAction::Discard, &app_state).await.unwrap();
+
+        assert_eq!(worklogs(&app_state, "i1").await, vec![(START, START + 1000, 1800), (START + 2000, 0, 0)]);
+        assert_eq!(session(&app_state).await, (START + 2000, Duration::from_secs(1000)));
+
+        // the session is rounded once, 500 more seconds still fit in the 30 minutes logged
+        let worked = Instant::now().checked_sub(Duration::from_secs(500)).unwrap();
+        app_state.0.lock().await.tracing_info.as_mut().unwrap().start_time = worked;
+        stop_tracking(&app_state).await.unwrap();
+        let logged: u64 = worklogs(&app_state, "i1").await.iter().map(|(_, _, total)| total).sum();
+        assert_eq!(logged, 1800);
+    }
+
+    #[tokio::test]
+    async fn assign_logs_the_idle_time_on_the_other_issue() {
+        let app_state = test_state().await;
+        track(&app_state).await;
+        go_idle(&app_state, 400, 1400, 2000).await;
+
+        cut_idle(IdleAction::Assign("ABC-2".to_string()), &app_state).await.unwrap();
+
+        assert_eq!(worklogs(&app_state, "i1").await, vec![(START, START + 1000, 1000), (START + 2000, 0, 0)]);
+        assert_eq!(worklogs(&app_state, "i2").await, vec![(START + 1000, START + 2000, 1000)]);
+        assert_eq!(session(&app_state).await, (START + 2000, Duration::from_secs(1000)));
+    }
+
+    #[tokio::test]
+    async fn idle_from_the_start_trims_the_worklog() {
+        let app_state = test_state().await;
+        track(&app_state).await;
+        // away since before the worklog started, the idle time starts with it
+        go_idle(&app_state, 500, 400, 900).await;
+
+        cut_idle(IdleAction::Discard, &app_state).await.unwrap();
+
+        assert_eq!(worklogs(&app_state, "i1").await, vec![(START + 900, 0, 0)]);
+        assert_eq!(session(&app_state).await, (START + 900, Duration::ZERO));
+    }
+
+    #[tokio::test]
+    async fn idle_from_the_start_assigned_moves_it_all() {
+        let app_state = test_state().await;
+        track(&app_state).await;
+        go_idle(&app_state, 500, 400, 900).await;
+
+        cut_idle(IdleAction::Assign("ABC-2".to_string()), &app_state).await.unwrap();
+
+        assert_eq!(worklogs(&app_state, "i1").await, vec![(START + 900, 0, 0)]);
+        assert_eq!(worklogs(&app_state, "i2").await, vec![(START, START + 900, 900)]);
+        assert_eq!(session(&app_state).await, (START + 900, Duration::ZERO));
+    }
+}
@@ -0,0 +1,454 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime};
+use shared::{parse_duration, ImportBatch, ImportMapping, ImportPreview, ImportRow, ImportRowStatus, Issue, Worklog};
+use tauri::{AppHandle, State};
+use tokio::sync::MutexGuard;
+use uuid::Uuid;
+
+use crate::{events, export::{parse_time_zone, FileZone}, sync, worklogs, AppState, MangedState};
+
+const TIME_FORMATS: [&str; 4] = ["%H:%M:%S", "%H:%M", "%I:%M:%S %p", "%I:%M %p"];
+
+/// What importing the CSV file with the mapping would do, nothing is written.
+#[tauri::command(async)]
+pub async fn preview_import(
+    contents: String,
+    mapping: ImportMapping,
+    app_state: State<'_, Arc<MangedState>>,
+) -> Result<ImportPreview, String> {
+    let m = app_state.0.lock().await;
+    Ok(plan(&m, &contents, &mapping).await?.preview)
+}
+
+/// Creates the missing issues and adds the new worklogs of the file as one batch, rows that
+/// are invalid, already there or overlapping other worklogs are skipped.
+#[tauri::command(async)]
+pub async fn import_worklogs(
+    contents: String,
+    source: String,
+    mapping: ImportMapping,
+    app_state: State<'_, Arc<MangedState>>,
+    app_handle: AppHandle,
+) -> Result<ImportBatch, String> {
+    let m = app_state.0.lock().await;
+    let plan = plan(&m, &contents, &mapping).await?;
+    if let Some(error) = plan.preview.error {
+        return Err(error);
+    }
+    if plan.entries.is_empty() {
+        return Err("there is nothing new in the file".to_string());
+    }
+
+    let batch = ImportBatch {
+        id: Uuid::new_v4().to_string(),
+        created: sync::now()? as u64,
+        source,
+        worklogs: plan.entries.len() as u64,
+        issues: plan.new_issues.len() as u64,
+    };
+    let assignee_email = m.tracker.as_ref().map(|tracker| tracker.user().to_string()).unwrap_or_default();
+    let mut issue_ids = plan.issue_ids;
+    let mut issues = Vec::new();
+    for (key, summary) in plan.new_issues {
+        let id = Uuid::new_v4().to_string();
+        issue_ids.insert(key.clone(), id.clone());
+        issues.push(Issue {
+            id,
+            name: key,
+            summary,
+            assignee_email: assignee_email.clone(),
+            ..Issue::empty()
+        });
+    }
+
+    let worklogs: Vec<Worklog> = plan
+        .entries
+        .into_iter()
+        .map(|entry| Worklog {
+            // history from another tracker is never sent
+            synced: !mapping.push_to_tracker,
+            ..Worklog::new(
+                Uuid::new_v4().to_string(),
+                issue_ids.get(&entry.key).cloned().unwrap_or_default(),
+                entry.start,
+                entry.end,
+                entry.end - entry.start,
+            )
+        })
+        .collect();
+    m.storage.add_import_batch(&batch, &issues, &worklogs).await?;
+
+    // queued once the import is in, one that fails to queue is picked up by the next sync
+    for worklog in worklogs.iter().filter(|worklog| !worklog.synced) {
+        sync::enqueue(m.tracker.as_deref(), &m.storage, sync::OutboxKind::Create, worklog).await?;
+    }
+
+    events::issues_changed(&app_handle);
+    Ok(batch)
+}
+
+#[tauri::command(async)]
+pub async fn get_import_batches(app_state: State<'_, Arc<MangedState>>) -> Result<Vec<ImportBatch>, String> {
+    let m = app_state.0.lock().await;
+    m.storage.get_import_batches().await
+}
+
+/// Removes the worklogs of an import and the issues it created, the ones already sent are
+/// deleted in the tracker through the outbox.
+#[tauri::command(async)]
+pub async fn undo_import(
+    id: String,
+    app_state: State<'_, Arc<MangedState>>,
+    app_handle: AppHandle,
+) -> Result<(), String> {
+    let m = app_state.0.lock().await;
+    let mut outbox = Vec::new();
+    for worklog in m.storage.get_import_batch_worklogs(&id).await? {
+        let change = sync::outbox_change(m.tracker.as_deref(), &m.storage, sync::OutboxKind::Delete, &worklog).await?;
+        outbox.extend(change);
+    }
+    m.storage.delete_import_batch(&id, &outbox).await?;
+
+    events::issues_changed(&app_handle);
+    Ok(())
+}
+
+struct Entry {
+    key: String,
+    summary: String,
+    start: u64,
+    end: u64,
+}
+
+struct Plan {
+    preview: ImportPreview,
+    entries: Vec<Entry>,              // the new ones
+    new_issues: Vec<(String, String)>, // key and summary
+    issue_ids: HashMap<String, String>, // of the issues already there, by key
+}
+
+async fn plan(m: &MutexGuard<'_, AppState>, contents: &str, mapping: &ImportMapping) -> Result<Plan, String> {
+    let zone = parse_time_zone(&mapping.time_zone)?;
+    let mut records = parse_csv(contents, mapping.delimiter).into_iter();
+    let headers = records.next().map(|(_, headers)| headers).unwrap_or_default();
+
+    let mut plan = Plan {
+        preview: ImportPreview {
+            headers: headers.clone(),
+            ..ImportPreview::default()
+        },
+        entries: Vec::new(),
+        new_issues: Vec::new(),
+        issue_ids: HashMap::new(),
+    };
+    let columns = match Columns::find(&headers, mapping) {
+        Ok(columns) => columns,
+        Err(e) => {
+            plan.preview.error = Some(e);
+            return Ok(plan);
+        }
+    };
+
+    plan.issue_ids = m
+        .storage
+        .get_issues()
+        .await?
+        .into_iter()
+        .map(|issue| (issue.name, issue.id))
+        .collect();
+    let now = sync::now()? as u64;
+    let mut seen = HashSet::new();
+    let mut taken: Vec<(u64, u64, u64)> = Vec::new(); // start, end and line of the new ones
+
+    for (line, record) in records {
+        let entry = match columns.read(&record, mapping, zone) {
+            Ok(entry) => entry,
+            Err(e) => {
+                plan.preview.rows.push(ImportRow {
+                    line,
+                    issue_key: String::new(),
+                    start: 0,
+                    end: 0,
+                    status: ImportRowStatus::Invalid(e),
+                });
+                continue;
+            }
+        };
+
+        let status = if entry.end <= entry.start {
+            ImportRowStatus::Invalid("the worklog has to end after it started".to_string())
+        } else if entry.end > now {
+            ImportRowStatus::Invalid("the worklog can't end in the future".to_string())
+        } else if !seen.insert((entry.key.clone(), entry.start, entry.end)) {
+            ImportRowStatus::Duplicate
+        } else {
+            match plan.issue_ids.get(&entry.key) {
+                Some(issue_id) if m.storage.has_worklog(issue_id, entry.start, entry.end).await? => {
+                    ImportRowStatus::Duplicate
+                }
+                _ if mapping.import_overlapping => ImportRowStatus::New,
+                _ => match taken.iter().find(|(start, end, _)| entry.start < *end && entry.end > *start) {
+                    Some((_, _, line)) => ImportRowStatus::Overlap(format!("the time overlaps line {}", line)),
+                    None => match worklogs::check_overlap(m, "", entry.start, entry.end).await {
+                        Err(conflict) => ImportRowStatus::Overlap(conflict),
+                        Ok(()) => ImportRowStatus::New,
+                    },
+                },
+            }
+        };
+
+        plan.preview.rows.push(ImportRow {
+            line,
+            issue_key: entry.key.clone(),
+            start: entry.start,
+            end: entry.end,
+            status: status.clone(),
+        });
+        if status == ImportRowStatus::New {
+            taken.push((entry.start, entry.end, line));
+            if !plan.issue_ids.contains_key(&entry.key) && !plan.new_issues.iter().any(|(key, _)| *key == entry.key) {
+                plan.preview.new_issues.push(entry.key.clone());
+                plan.new_issues.push((entry.key.clone(), entry.summary.clone()));
+            }
+            plan.entries.push(entry);
+        }
+    }
+
+    Ok(plan)
+}
+
+/// Positions of the mapped columns in the records.
+struct Columns {
+    issue_key: usize,
+    summary: Option<usize>,
+    start_date: usize,
+    start_time: Option<usize>,
+    end_date: Option<usize>,
+    end_time: Option<usize>,
+    duration: Option<usize>,
+}
+
+impl Columns {
+    fn find(headers: &[String], mapping: &ImportMapping) -> Result<Self, String> {
+        let find = |name: &str| match name {
+            "" => Err("map the issue key and the start columns".to_string()),
+            name => headers
+                .iter()
+                .position(|header| header.trim() == name)
+                .ok_or(format!("the file has no column {}", name)),
+        };
+        let find_optional = |name: &Option<String>| match name.as_deref() {
+            None | Some("") => Ok(None),
+            Some(name) => find(name).map(Some),
+        };
+
+        let columns = Columns {
+            issue_key: find(&mapping.issue_key)?,
+            summary: find_optional(&mapping.summary)?,
+            start_date: find(&mapping.start_date)?,
+            start_time: find_optional(&mapping.start_time)?,
+            end_date: find_optional(&mapping.end_date)?,
+            end_time: find_optional(&mapping.end_time)?,
+            duration: find_optional(&mapping.duration)?,
+        };
+        if columns.end_date.is_none() && columns.end_time.is_none() && columns.duration.is_none() {
+            return Err("map an end or a duration column".to_string());
+        }
+        Ok(columns)
+    }
+
+    fn read(&self, record: &[String], mapping: &ImportMapping, zone: FileZone) -> Result<Entry, String> {
+        let field = |i: usize| record.get(i).map(|value| value.trim()).unwrap_or_default();
+        let optional = |i: Option<usize>| i.map(field).filter(|value| !value.is_empty());
+
+        let key = match mapping.extract_key {
+            true => extract_key(field(self.issue_key)).ok_or("there is no issue key like ABC-123".to_string())?,
+            false => field(self.issue_key),
+        };
+        if key.is_empty() {
+            return Err("there is no issue key".to_string());
+        }
+        let summary = optional(self.summary)
+            .map(|summary| summary.replacen(key, "", 1).trim_matches([' ', ':', '-']).to_string())
+            .filter(|summary| !summary.is_empty())
+            .unwrap_or_else(|| key.to_string());
+
+        let start_date = field(self.start_date);
+        let start = parse_time(start_date, optional(self.start_time), &mapping.date_format, zone)?;
+        let end = match (optional(self.end_date), optional(self.end_time), optional(self.duration)) {
+            (date, Some(time), _) => {
+                let end = parse_time(date.unwrap_or(start_date), Some(time), &mapping.date_format, zone)?;
+                // past midnight without an end date
+                match date.is_none() && end < start {
+                    true => end + 24 * 3600,
+                    false => end,
+                }
+            }
+            (Some(date), None, _) if self.end_time.is_none() => parse_time(date, None, &mapping.date_format, zone)?,
+            (_, _, Some(duration)) => {
+                start + parse_duration(duration).ok_or(format!("can't read the duration {}", duration))?
+            }
+            _ => return Err("there is no end or duration".to_string()),
+        };
+
+        Ok(Entry {
+            key: key.to_string(),
+            summary,
+            start,
+            end,
+        })
+    }
+}
+
+/// Unix seconds of a date and a time, or of a date with the time in it when there is no time.
+fn parse_time(date: &str, time: Option<&str>, date_format: &str, zone: FileZone) -> Result<u64, String> {
+    let naive = match time {
+        Some(time) => {
+            let day = NaiveDate::parse_from_str(date, date_format)
+                .map_err(|_| format!("can't read the date {} as {}", date, date_format))?;
+            let time = TIME_FORMATS
+                .iter()
+                .find_map(|format| NaiveTime::parse_from_str(time, format).ok())
+                .ok_or(format!("can't read the time {}", time))?;
+            day.and_time(time)
+        }
+        None => {
+            if let Ok(time) = DateTime::parse_from_rfc3339(date) {
+                return Ok(time.timestamp() as u64);
+            }
+            TIME_FORMATS
+                .iter()
+                .flat_map(|format| [format!("{} {}", date_format, format), format!("{}T{}", date_format, format)])
+                .find_map(|format| NaiveDateTime::parse_from_str(date, &format).ok())
+                .ok_or(format!("can't read the time {} as {} and a time", date, date_format))?
+        }
+    };
+
+    zone.seconds(&naive)
+        .map(|time| time as u64)
+        .ok_or(format!("{} does not exist in the time zone", naive))
+}
+
+/// First word looking like a Jira key, `ABC-123`.
+fn extract_key(text: &str) -> Option<&str> {
+    text.split(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == '_'))
+        .find(|word| match word.split_once('-') {
+            Some((project, number)) => {
+                project.starts_with(|c: char| c.is_ascii_uppercase())
+                    && project.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+                    && !number.is_empty()
+                    && number.chars().all(|c| c.is_ascii_digit())
+            }
+            None => false,
+        })
+}
+
+/// Records of the file with the line each starts on, quoted fields may hold the delimiter,
+/// line breaks and doubled quotes. Blank lines are skipped.
+fn parse_csv(contents: &str, delimiter: char) -> Vec<(u64, Vec<String>)> {
+    let contents = contents.trim_start_matches('\u{feff}');
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let (mut line, mut record_line) = (1, 1);
+    let mut quoted = false;
+    let mut chars = contents.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' if quoted => quoted = false,
+            '"' if field.is_empty() => quoted = true,
+            '\n' if !quoted => {
+                record.push(std::mem::take(&mut field));
+                if record.len() > 1 || !record[0].trim().is_empty() {
+                    records.push((record_line, std::mem::take(&mut record)));
+                }
+                record.clear();
+                line += 1;
+                record_line = line;
+            }
+            '\r' if !quoted && chars.peek() == Some(&'\n') => {}
+            c if c == delimiter && !quoted => record.push(std::mem::take(&mut field)),
+            c => {
+                if c == '\n' {
+                    line += 1;
+                }
+                field.push(c);
+            }
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push((record_line, record));
+    }
+
+    records
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::test_state;
+
+    const NINE: u64 = 1714554000; // 2024-05-01 09:00 UTC
+
+    fn statuses(plan: &Plan) -> Vec<ImportRowStatus> {
+        plan.preview.rows.iter().map(|row| row.status.clone()).collect()
+    }
+
+    #[tokio::test]
+    async fn overlapping_rows_are_skipped_unless_wanted() {
+        let app_state = test_state().await;
+        let m = app_state.0.lock().await;
+        let issue = Issue {
+            id: "i1".to_string(),
+            name: "ABC-1".to_string(),
+            ..Issue::empty()
+        };
+        m.storage.add_issue(issue).await.unwrap();
+        let worklog = Worklog::new("w1".to_string(), "i1".to_string(), NINE, NINE + 3600, 3600);
+        m.storage.add_worklog(worklog).await.unwrap();
+
+        let contents = "key,start,end
+ABC-1,2024-05-01T09:30:00Z,2024-05-01T10:30:00Z
+ABC-2,2024-05-01T11:00:00Z,2024-05-01T12:00:00Z
+ABC-2,2024-05-01T11:30:00Z,2024-05-01T12:30:00Z
+ABC-2,2024-05-01T11:00:00Z,2024-05-01T12:00:00Z
+ABC-1,2024-05-01T10:00:00Z,2024-05-01T11:00:00Z
+";
+        let mut mapping = ImportMapping {
+            issue_key: "key".to_string(),
+            start_date: "start".to_string(),
+            end_date: Some("end".to_string()),
+            ..ImportMapping::default()
+        };
+
+        let skipped = plan(&m, contents, &mapping).await.unwrap();
+        assert_eq!(statuses(&skipped), vec![
+            ImportRowStatus::Overlap("the time overlaps a worklog of ABC-1".to_string()),
+            ImportRowStatus::New,
+            ImportRowStatus::Overlap("the time overlaps line 3".to_string()),
+            ImportRowStatus::Duplicate,
+            ImportRowStatus::New,
+        ]);
+        assert_eq!(skipped.entries.len(), 2);
+
+        mapping.import_overlapping = true;
+        let kept = plan(&m, contents, &mapping).await.unwrap();
+        assert_eq!(statuses(&kept), vec![
+            ImportRowStatus::New,
+            ImportRowStatus::New,
+            ImportRowStatus::New,
+            ImportRowStatus::Duplicate,
+            ImportRowStatus::New,
+        ]);
+    }
+}
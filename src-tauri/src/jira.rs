@@ -1,73 +1,339 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use chrono::{TimeZone, Utc};
 use http_auth_basic::Credentials;
-use shared::Issue;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use shared::{Issue, JiraConfig, Worklog};
 use tauri::{
-    api::http::{Client, ClientBuilder, HttpRequestBuilder},
+    api::http::{Body, Client, ClientBuilder, HttpRequestBuilder},
     http::header::AUTHORIZATION,
-    Error, State,
+    Error,
 };
+use uuid::Uuid;
+
+use crate::tracker::{execute, parse, Tracker, TrackerError, WorklogReceipt};
 
-use crate::AppState;
+pub const JIRA_SOURCE: &str = "jira";
+pub const DEFAULT_JQL: &str = "assignee = currentUser() AND statusCategory != Done";
+const SEARCH_PAGE_SIZE: usize = 50;
+// jira refuses worklogs shorter than a minute
+const MIN_WORKLOG_SECONDS: u64 = 60;
+const ISSUE_FIELDS: &str = "summary,assignee,status,issuetype,project,timespent,timeestimate,labels";
 
-pub struct JiraConfiguration {
+pub struct JiraTracker {
     pub base_path: String,
     pub user: String,
-    pub api_key: String,
+    pub jql: String,
     pub client: Client,
     pub credentials: Credentials,
 }
 
-pub fn setup_jira() -> Result<JiraConfiguration, Error> {
+pub fn setup_jira(config: &JiraConfig) -> Result<JiraTracker, Error> {
     let client_builder = ClientBuilder::new();
     let client = client_builder.build()?;
-    let base_path =
-        std::env::var("JIRA_URL").map_err(|err| Error::AssetNotFound(err.to_string()))?;
-    let user = std::env::var("JIRA_USER").map_err(|err| Error::AssetNotFound(err.to_string()))?;
-    let api_key = std::env::var("JIRA_KEY").map_err(|err| Error::AssetNotFound(err.to_string()))?;
-    let credentials = Credentials::new(user.as_str(), api_key.as_str());
-
-    let config = JiraConfiguration {
-        base_path,
+    let credentials = Credentials::new(config.user.as_str(), config.api_key.as_str());
+
+    let tracker = JiraTracker {
+        base_path: config.url.trim_end_matches('/').to_string(),
         client,
-        api_key,
-        user,
+        user: config.user.clone(),
+        jql: config.jql.clone().unwrap_or(DEFAULT_JQL.to_string()),
         credentials,
     };
 
-    Ok(config)
+    Ok(tracker)
+}
+
+impl JiraTracker {
+    fn request(&self, method: &str, path: String) -> Result<HttpRequestBuilder, TrackerError> {
+        let link = format!("{}{}", self.base_path, path);
+        let request = HttpRequestBuilder::new(method, link)
+            .map_err(|err| TrackerError::Rejected(0, err.to_string()))?
+            .header(AUTHORIZATION, self.credentials.as_http_header())
+            .map_err(|err| TrackerError::Rejected(0, err.to_string()))?;
+
+        Ok(request)
+    }
+
+    async fn send(&self, method: &str, path: String, body: Option<Value>) -> Result<Value, TrackerError> {
+        let mut request = self.request(method, path)?;
+        if let Some(body) = body {
+            request = request.body(Body::Json(body));
+        }
+
+        execute(&self.client, request).await
+    }
 }
 
-#[tauri::command(async)]
-pub async fn get_issue_jira(name: String, app_state: State<'_, AppState>) -> Result<Issue, String> {
-    let link = format!(
-        "{}/rest/api/3/issue/{}",
-        app_state.jira_config.base_path, name
-    );
-    let request = HttpRequestBuilder::new("GET", link).map_err(|err| err.to_string())?;
-
-    let request = request
-        .header(
-            AUTHORIZATION,
-            app_state.jira_config.credentials.as_http_header(),
+#[async_trait]
+impl Tracker for JiraTracker {
+    fn source(&self) -> &'static str {
+        JIRA_SOURCE
+    }
+
+    fn user(&self) -> &str {
+        &self.user
+    }
+
+    fn default_query(&self) -> &str {
+        &self.jql
+    }
+
+    fn min_worklog_seconds(&self) -> u64 {
+        MIN_WORKLOG_SECONDS
+    }
+
+    async fn check_connection(&self) -> Result<String, TrackerError> {
+        let user: JiraUser = parse(self.send("GET", "/rest/api/3/myself".to_string(), None).await?)?;
+
+        Ok(user.display_name.or(user.email_address).unwrap_or_default())
+    }
+
+    async fn get_issue(&self, key: &str) -> Result<Issue, TrackerError> {
+        let data = self
+            .send("GET", format!("/rest/api/3/issue/{}?fields={}", key, ISSUE_FIELDS), None)
+            .await?;
+
+        parse::<JiraIssue>(data).map(|issue| to_issue(&self.base_path, issue))
+    }
+
+    async fn search_issues(&self, query: &str) -> Result<Vec<Issue>, TrackerError> {
+        let mut issues = Vec::new();
+
+        loop {
+            let params = HashMap::from([
+                ("jql".to_string(), query.to_string()),
+                ("startAt".to_string(), issues.len().to_string()),
+                ("maxResults".to_string(), SEARCH_PAGE_SIZE.to_string()),
+                ("fields".to_string(), ISSUE_FIELDS.to_string()),
+            ]);
+            let request = self.request("GET", "/rest/api/3/search".to_string())?.query(params);
+            let page: JiraSearchPage = parse(execute(&self.client, request).await?)?;
+
+            let page_len = page.issues.len();
+            issues.extend(page.issues.into_iter().map(|issue| to_issue(&self.base_path, issue)));
+
+            if page_len == 0 || issues.len() >= page.total {
+                break;
+            }
+        }
+
+        Ok(issues)
+    }
+
+    async fn create_worklog(&self, issue_key: &str, worklog: &Worklog) -> Result<WorklogReceipt, TrackerError> {
+        let data = self
+            .send(
+                "POST",
+                format!("/rest/api/3/issue/{}/worklog", issue_key),
+                Some(worklog_body(worklog)?),
+            )
+            .await?;
+
+        match data["id"].as_str() {
+            Some(id) => Ok(WorklogReceipt::new(id.to_string())),
+            None => Err(TrackerError::Rejected(0, format!("worklog response has no id: {}", data))),
+        }
+    }
+
+    async fn update_worklog(
+        &self,
+        issue_key: &str,
+        remote_id: &str,
+        worklog: &Worklog,
+    ) -> Result<WorklogReceipt, TrackerError> {
+        self.send(
+            "PUT",
+            format!("/rest/api/3/issue/{}/worklog/{}", issue_key, remote_id),
+            Some(worklog_body(worklog)?),
+        )
+        .await?;
+
+        Ok(WorklogReceipt::new(remote_id.to_string()))
+    }
+
+    async fn delete_worklog(
+        &self,
+        issue_key: &str,
+        remote_id: &str,
+        _worklog: &Worklog,
+    ) -> Result<WorklogReceipt, TrackerError> {
+        self.send(
+            "DELETE",
+            format!("/rest/api/3/issue/{}/worklog/{}", issue_key, remote_id),
+            None,
         )
-        .map_err(|err| err.to_string())?;
-    let response = app_state
-        .jira_config
-        .client
-        .send(request)
-        .await
-        .map_err(|err| err.to_string())?
-        .read()
-        .await
-        .map_err(|err| err.to_string())?;
-
-    Ok(Issue {
-        id: response.data["id"].to_string(),
-        name: name,
-        url: response.data["self"].to_string(),
-        summary: response.data["fields"]["summary"].to_string(),
-        assignee_email: response.data["fields"]["assignee"]["emailAddress"].to_string(),
-        time_tracked_all: response.data["fields"]["timespent"]
-            .as_u64()
-            .map_or(0, |v| v),
-    })
+        .await?;
+
+        Ok(WorklogReceipt::new(remote_id.to_string()))
+    }
+
+    // jira has no idempotency keys, a worklog with the same start and duration is ours
+    async fn find_worklog(
+        &self,
+        issue_key: &str,
+        worklog: &Worklog,
+    ) -> Result<Option<String>, TrackerError> {
+        let data = self
+            .send("GET", format!("/rest/api/3/issue/{}/worklog", issue_key), None)
+            .await?;
+        let started = worklog_body(worklog)?["started"].clone();
+
+        let found = data["worklogs"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .find(|w| {
+                w["started"] == started
+                    && w["timeSpentSeconds"].as_u64() == Some(worklog.total_time)
+            })
+            .and_then(|w| w["id"].as_str())
+            .map(|id| id.to_string());
+
+        Ok(found)
+    }
+}
+
+fn worklog_body(worklog: &Worklog) -> Result<Value, TrackerError> {
+    let started = Utc
+        .timestamp_opt(worklog.start as i64, 0)
+        .single()
+        .ok_or_else(|| TrackerError::Rejected(0, format!("invalid worklog start {}", worklog.start)))?;
+
+    Ok(json!({
+        "started": started.format("%Y-%m-%dT%H:%M:%S%.3f%z").to_string(),
+        "timeSpentSeconds": worklog.total_time,
+    }))
+}
+
+#[derive(Deserialize, Debug)]
+pub struct JiraIssue {
+    pub key: String,
+    pub fields: JiraIssueFields,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct JiraIssueFields {
+    pub summary: String,
+    pub assignee: Option<JiraUser>,
+    pub status: Option<JiraNamed>,
+    #[serde(rename = "issuetype")]
+    pub issue_type: Option<JiraNamed>,
+    pub project: Option<JiraProject>,
+    pub timespent: Option<u64>,
+    pub timeestimate: Option<u64>,
+    #[serde(default)]
+    pub labels: Vec<String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct JiraUser {
+    pub email_address: Option<String>,
+    pub display_name: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct JiraNamed {
+    pub name: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct JiraProject {
+    pub key: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct JiraSearchPage {
+    total: usize,
+    issues: Vec<JiraIssue>,
+}
+
+// the issue is matched by its key, the id is the local one and the url the page people open
+fn to_issue(base_path: &str, issue: JiraIssue) -> Issue {
+    let fields = issue.fields;
+
+    Issue {
+        id: Uuid::new_v4().to_string(),
+        url: format!("{}/browse/{}", base_path, issue.key),
+        name: issue.key,
+        summary: fields.summary,
+        assignee_email: fields
+            .assignee
+            .and_then(|assignee| assignee.email_address)
+            .unwrap_or_default(),
+        time_tracked_all: 0,
+        source: JIRA_SOURCE.to_string(),
+        stale: false,
+        status: fields.status.map(|status| status.name).unwrap_or_default(),
+        project: fields.project.map(|project| project.key).unwrap_or_default(),
+        issue_type: fields.issue_type.map(|issue_type| issue_type.name).unwrap_or_default(),
+        time_estimate: fields.timeestimate.unwrap_or_default(),
+        remote_time_spent: fields.timespent.unwrap_or_default(),
+        tags: fields.labels,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+    use tauri::api::http::ClientBuilder;
+
+    use super::*;
+    use crate::tracker::tests::{MockRequest, MockServer};
+
+    fn issue(number: usize) -> Value {
+        json!({
+            "id": format!("{}", 10000 + number),
+            "key": format!("ABC-{}", number),
+            "self": format!("https://example.atlassian.net/rest/api/3/issue/{}", 10000 + number),
+            "fields": { "summary": "work", "project": { "key": "ABC" }, "labels": ["backend"] },
+        })
+    }
+
+    fn answer(request: &MockRequest) -> (u16, Value) {
+        let start = request
+            .path
+            .split(['?', '&'])
+            .find_map(|param| param.strip_prefix("startAt="))
+            .and_then(|start| start.parse::<usize>().ok())
+            .unwrap_or_default();
+        let issues: Vec<Value> = (start + 1..=(start + SEARCH_PAGE_SIZE).min(60)).map(issue).collect();
+
+        match request.path.starts_with("/rest/api/3/search") {
+            true => (200, json!({ "total": 60, "issues": issues })),
+            false => (200, issue(7)),
+        }
+    }
+
+    fn jira(server: &MockServer) -> JiraTracker {
+        JiraTracker {
+            base_path: server.url.clone(),
+            user: "me@example.com".to_string(),
+            jql: DEFAULT_JQL.to_string(),
+            client: ClientBuilder::new().build().unwrap(),
+            credentials: Credentials::new("me@example.com", "key"),
+        }
+    }
+
+    #[tokio::test]
+    async fn issues_link_to_their_page_in_jira() {
+        let server = MockServer::start(answer).await;
+        let jira = jira(&server);
+
+        let issues = jira.search_issues(DEFAULT_JQL).await.unwrap();
+        assert_eq!(issues.len(), 60);
+        assert_eq!(server.requests().len(), 2);
+        assert_eq!(issues[59].name, "ABC-60");
+        assert_eq!(issues[59].url, format!("{}/browse/ABC-60", server.url));
+        assert_eq!(issues[59].tags, vec!["backend"]);
+
+        let single = jira.get_issue("ABC-7").await.unwrap();
+        assert_eq!(single.url, format!("{}/browse/ABC-7", server.url));
+        // the local id is ours, not the one jira numbers its issues with
+        assert_ne!(single.id, "10007");
+    }
 }
@@ -2,26 +2,43 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 use crate::commands::exit_save_process;
-use crate::jira::{get_issue_jira, setup_jira, JiraConfiguration};
+use crate::config::{database_url, read_config, with_env};
 use crate::storage::Storage;
-use shared::{Issue, Worklog};
-use sqlx::sqlite::SqlitePoolOptions;
+use crate::tracker::{setup_tracker, Tracker};
+use crate::tray::{handle_tray_event, set_up_tray};
+use shared::{AppConfig, IdlePeriod, Issue, RoundingConfig, Worklog};
 use std::collections::VecDeque;
 use std::sync::Arc;
-use std::{
-    env::{self},
-    time::Instant,
-};
-use tauri::{AppHandle, CustomMenuItem, Manager, State, SystemTray, SystemTrayEvent, SystemTrayMenu, SystemTrayMenuItem};
+use std::time::{Duration, Instant};
+use tauri::{Manager, RunEvent, State, SystemTray};
 use tokio::sync::Mutex;
 
+mod calendar;
 mod commands;
+mod config;
+mod events;
+mod export;
+mod github;
+mod idle;
+mod import;
+mod gitlab;
 mod jira;
+mod recovery;
+mod report;
 mod storage;
+mod sync;
+mod timesheet;
+mod tracker;
+mod tray;
+mod worklogs;
 
 struct AppState {
-    jira_config: JiraConfiguration,
+    config: AppConfig,              // in use, with the environment overrides
+    file_config: Option<AppConfig>, // as saved in config.toml, none when the file does not parse
+    config_error: Option<String>,   // why the config is not in use
+    tracker: Option<Arc<dyn Tracker>>,
     tracing_info: Option<TracingInfo>,
+    idle: Option<IdlePeriod>,
     storage: Storage,
     recent_issues: VecDeque<String>,
 }
@@ -30,101 +47,55 @@ struct AppState {
 struct TracingInfo {
     time_tracing_issue: Issue,
     current_worklog: Worklog,
-    start_time: Instant,
+    start_time: Instant, // of the current worklog
+    session_time: Duration, // tracked in the worklogs pauses already ended
+    session_logged: u64,    // total time of those worklogs, rounded
+    paused: bool,           // the current worklog is finished while paused
 }
 
-struct MangedState(Mutex<AppState>);
-
-
-fn set_up_tray(recent_issues: &VecDeque<String>) -> SystemTrayMenu {
-  let mut tray_menu = SystemTrayMenu::new()
-  .add_item(CustomMenuItem::new("quit".to_string(), "Quit"))
-  .add_native_item(SystemTrayMenuItem::Separator)
-  .add_item(CustomMenuItem::new("hide".to_string(), "Hide"))
-  .add_item(CustomMenuItem::new("show".to_string(), "Show"))
-  .add_native_item(SystemTrayMenuItem::Separator);
-
-  let fill_in_count = 5 - recent_issues.len();
-
-  for (i, issue) in recent_issues.iter().enumerate() {
-    tray_menu = tray_menu.add_item(CustomMenuItem::new(i.to_string(), issue.clone()));
-  }
-
-  for i in 0..fill_in_count {
-    tray_menu = tray_menu.add_item(CustomMenuItem::new(i.to_string(), format!("Recent Issue: {} ", i)));
-    
-  }
-
-  tray_menu
-}
-
-
-
-fn handle_tray_event<'a>(app: &'a AppHandle, event: SystemTrayEvent) {
-  match event {
-    SystemTrayEvent::MenuItemClick { id, .. } => match id.as_str() {
-        "quit" => {
-            let s:State<Arc<MangedState>> = app.state();
-            exit_save_process(s.inner().clone()).expect("Could not save the progress");
-            print!("closing app from tray click");
-            app.exit(0);
-        }
-        "hide" => {
-            let window = app.get_window("main").unwrap();
-
-            match window.hide() {
-                Ok(_) => print!("window hidden"),
-                Err(e) => print!("window cannot be hidden:  {}", e),
-            }
-        }
-        "show" => {
-          let window = app.get_window("main").unwrap();
-
-          match window.show() {
-              Ok(_) => print!("window show"),
-              Err(e) => print!("window cannot be shown:  {}", e),
-          }
+impl TracingInfo {
+    /// Tracked time of the whole session, without the pauses.
+    fn elapsed(&self) -> Duration {
+        match self.paused {
+            true => self.session_time,
+            false => self.session_time + self.start_time.elapsed(),
         }
-        "0" => {
-          
-        }
-        "1" => {
-          
-        }
-        "2" => {
-          
-        }
-        "3" => {
-          
-        }
-        "4" => {
-          
-        }
-        _ => {}
-    },
-    _ => {}
-  }
+    }
+
+    /// Total time of the current worklog with `seconds` tracked in it. The session is rounded as
+    /// a whole, so the worklogs pauses and idle time split off don't add up their rounding.
+    fn worklog_total(&self, rounding: &RoundingConfig, seconds: u64) -> u64 {
+        rounding.apply(self.session_time.as_secs() + seconds).saturating_sub(self.session_logged)
+    }
+
+    /// Counts the finished current worklog, with `tracked` time in it, into the session.
+    fn add_to_session(&mut self, worklog: &Worklog, tracked: Duration) {
+        self.session_time += tracked;
+        self.session_logged += worklog.total_time;
+    }
 }
 
+struct MangedState(Mutex<AppState>);
+
 
 #[tokio::main]
 async fn main() {
-    let setup_jira = setup_jira().expect("Error while setting up jira");
-    let jira_cfg = setup_jira;
-
-    let database_url: String = match env::var("DATABASE_URL") {
-        Ok(v) => v,
-        Err(_) => "storage".to_string(),
+    // the log goes to stderr, RUST_LOG=debug or RUST_LOG=warn changes how much of it
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+
+    // a broken config should not keep the app from starting, it runs without a tracker then
+    // and the window shows why
+    let (file_config, config, config_error) = match read_config() {
+        Ok(file_config) => match with_env(file_config.clone()) {
+            Ok(config) => (Some(file_config), config, None),
+            Err(e) => (Some(file_config), AppConfig::default(), Some(e)),
+        },
+        Err(e) => (None, AppConfig::default(), Some(e)),
     };
+    let tracker = setup_tracker(config.tracker.as_ref()).expect("Error while setting up the issue tracker");
 
-    let conn = SqlitePoolOptions::new()
-        .max_connections(3)
-        .connect(&database_url)
-        .await
-        .expect("failed to connect to storage");
-    sqlx::migrate!().run(&conn).await.expect("Couldn't init db");
-
-    let storage = Storage { conn };
+    let database_url = database_url(&config).expect("Error while locating the storage");
+    let storage = Storage::open(&database_url).await.expect("failed to open the storage");
     let recent_issues = storage.get_recent_issues().await.expect("Error getting recent issues");
       
     let tray_menu = set_up_tray(&recent_issues);
@@ -132,33 +103,111 @@ async fn main() {
 
 
 
+    let app_state = Arc::new(MangedState(Mutex::new(AppState {
+        config,
+        file_config,
+        config_error,
+        tracker,
+        tracing_info: None,
+        idle: None,
+        storage,
+        recent_issues,
+    })));
+
+    tokio::spawn(sync::run_outbox(app_state.clone()));
+    tokio::spawn(recovery::run_heartbeat(app_state.clone()));
+    tokio::spawn(calendar::run_calendar_feed(app_state.clone()));
+
+    let signal_state = app_state.clone();
     tauri::Builder::default()
-        .manage(Arc::new(MangedState {
-            0: Mutex::new(AppState {
-                jira_config: jira_cfg,
-                tracing_info: None,
-                storage: storage,
-                recent_issues: recent_issues,
-            }),
-        }))
+        .manage(app_state)
+        .setup(move |app| {
+            if let Some(detector) = idle::setup_idle_detector() {
+                tokio::spawn(idle::run_idle_watch(signal_state.clone(), app.handle(), detector));
+            }
+            tokio::spawn(tray::run_tray_clock(signal_state.clone(), app.handle()));
+            tokio::spawn(events::run_tracking_ticks(signal_state.clone(), app.handle()));
+            tokio::spawn(recovery::exit_on_signal(signal_state, app.handle()));
+            Ok(())
+        })
         .system_tray(system_tray)
         .on_system_tray_event(handle_tray_event)
-        .on_window_event(|event| match event.event() {
-          tauri::WindowEvent::CloseRequested { api, .. } => {
+        .on_window_event(|event| {
+          if let tauri::WindowEvent::CloseRequested { api, .. } = event.event() {
             event.window().hide().unwrap();
             api.prevent_close();
           }
-          _ => {}
         })
         .invoke_handler(tauri::generate_handler![
-            get_issue_jira,
+            config::get_config,
+            config::get_config_error,
+            config::update_config,
+            config::test_tracker_connection,
+            tracker::get_tracker_issue,
+            tracker::import_issues,
             commands::get_issues,
             commands::add_issue,
             commands::start_tracking_cmd,
             commands::stop_tracking_cmd,
+            commands::pause_tracking_cmd,
+            commands::resume_tracking_cmd,
+            events::get_tracking_state,
             commands::get_tracing_issue_name,
-            commands::get_elapsed_time
+            commands::get_elapsed_time,
+            commands::sync_worklogs,
+            commands::get_outbox_status,
+            commands::retry_outbox_item,
+            commands::discard_outbox_item,
+            recovery::get_open_worklogs,
+            recovery::close_open_worklog,
+            recovery::discard_open_worklog,
+            idle::resolve_idle,
+            worklogs::get_issue_worklogs,
+            worklogs::get_worklogs_between,
+            worklogs::edit_worklog,
+            worklogs::delete_worklog,
+            worklogs::add_manual_worklog,
+            worklogs::reassign_worklog,
+            worklogs::check_time_tracked,
+            timesheet::get_timesheet,
+            timesheet::set_timesheet_cell,
+            report::get_report,
+            export::export_worklogs,
+            calendar::suggest_worklogs,
+            import::preview_import,
+            import::import_worklogs,
+            import::get_import_batches,
+            import::undo_import
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(|app, event| {
+          // the event loop ending without going through the tray, e.g. quitting from the dock
+          if let RunEvent::Exit = event {
+            let s: State<Arc<MangedState>> = app.state();
+            if let Err(e) = exit_save_process(s.inner().clone()) {
+              log::error!("could not save the progress: {}", e);
+            }
+          }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::tests::test_storage;
+
+    /// App state without a tracker on a database of its own.
+    pub async fn test_state() -> Arc<MangedState> {
+        Arc::new(MangedState(Mutex::new(AppState {
+            config: AppConfig::default(),
+            file_config: Some(AppConfig::default()),
+            config_error: None,
+            tracker: None,
+            tracing_info: None,
+            idle: None,
+            storage: test_storage().await,
+            recent_issues: VecDeque::new(),
+        })))
+    }
 }
@@ -0,0 +1,120 @@
+use std::{sync::Arc, time::Duration};
+
+use shared::{OpenWorklog, Worklog};
+use tauri::{AppHandle, State};
+use tokio::sync::MutexGuard;
+
+use crate::{
+    commands::{finish_worklog, save_on_exit},
+    events, sync, AppState, MangedState,
+};
+
+const HEARTBEAT: Duration = Duration::from_secs(30);
+
+/// Background task noting that the tracked worklog is still running, a worklog left open
+/// by a crash can then be closed at its last heartbeat.
+pub async fn run_heartbeat(app_state: Arc<MangedState>) {
+    loop {
+        tokio::time::sleep(HEARTBEAT).await;
+
+        let m = app_state.0.lock().await;
+        let info = match &m.tracing_info {
+            Some(info) if !info.paused => info,
+            _ => continue,
+        };
+        let res = match sync::now() {
+            Ok(now) => m.storage.set_worklog_heartbeat(&info.current_worklog.id, now as u64).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = res {
+            log::error!("heartbeat: {}", e);
+        }
+    }
+}
+
+/// Saves the tracked worklog and exits when the process is asked to stop, e.g. on logout.
+pub async fn exit_on_signal(app_state: Arc<MangedState>, app_handle: AppHandle) {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut terminate = match signal(SignalKind::terminate()) {
+            Ok(terminate) => terminate,
+            Err(e) => {
+                log::warn!("can't listen for SIGTERM: {}", e);
+                return;
+            }
+        };
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {},
+            _ = terminate.recv() => {},
+        }
+    }
+    #[cfg(not(unix))]
+    if tokio::signal::ctrl_c().await.is_err() {
+        return;
+    }
+
+    if let Err(e) = save_on_exit(&app_state).await {
+        log::error!("could not save the progress: {}", e);
+    }
+    app_handle.exit(0);
+}
+
+/// Worklogs left open by an earlier run, the one tracked right now is not one of them.
+#[tauri::command(async)]
+pub async fn get_open_worklogs(app_state: State<'_, Arc<MangedState>>) -> Result<Vec<OpenWorklog>, String> {
+    let m = app_state.0.lock().await;
+    left_open(&m).await
+}
+
+/// Finishes a worklog left open at `end`, at its last heartbeat when not given.
+#[tauri::command(async)]
+pub async fn close_open_worklog(
+    id: String,
+    end: Option<u64>,
+    app_state: State<'_, Arc<MangedState>>,
+    app_handle: AppHandle,
+) -> Result<Worklog, String> {
+    let m = app_state.0.lock().await;
+    let open = find_left_open(&m, &id).await?;
+
+    let end = end.unwrap_or(open.last_heartbeat);
+    if end < open.worklog.start {
+        return Err("the worklog can't end before it started".to_string());
+    }
+    if end > sync::now()? as u64 {
+        return Err("the worklog can't end in the future".to_string());
+    }
+
+    let total_time = m.config.rounding.apply(end - open.worklog.start);
+    let worklog = finish_worklog(&m, open.worklog, end, total_time).await?;
+    events::issues_changed(&app_handle);
+    Ok(worklog)
+}
+
+#[tauri::command(async)]
+pub async fn discard_open_worklog(id: String, app_state: State<'_, Arc<MangedState>>) -> Result<(), String> {
+    let m = app_state.0.lock().await;
+    find_left_open(&m, &id).await?;
+    m.storage.delete_worklog(&id).await
+}
+
+async fn left_open(m: &MutexGuard<'_, AppState>) -> Result<Vec<OpenWorklog>, String> {
+    let tracked = m.tracing_info.as_ref().map(|info| info.current_worklog.id.as_str());
+
+    Ok(m.storage
+        .get_open_worklogs()
+        .await?
+        .into_iter()
+        .filter(|open| Some(open.worklog.id.as_str()) != tracked)
+        .collect())
+}
+
+async fn find_left_open(m: &MutexGuard<'_, AppState>, id: &str) -> Result<OpenWorklog, String> {
+    left_open(m)
+        .await?
+        .into_iter()
+        .find(|open| open.worklog.id == id)
+        .ok_or(format!("worklog {} is not left open", id))
+}
@@ -0,0 +1,172 @@
+use std::{collections::HashMap, sync::Arc};
+
+use chrono::{Datelike, Local, TimeZone};
+use shared::{Report, ReportGrouping, ReportRow};
+use tauri::State;
+
+use crate::{storage::IssueWorklog, MangedState};
+
+const NONE: &str = "(none)";
+
+/// Adds up the finished worklogs started in `[from, to)`, days, weeks and months in local time.
+#[tauri::command(async)]
+pub async fn get_report(
+    from: u64,
+    to: u64,
+    grouping: ReportGrouping,
+    app_state: State<'_, Arc<MangedState>>,
+) -> Result<Report, String> {
+    let worklogs = {
+        let m = app_state.0.lock().await;
+        m.storage.get_issue_worklogs_between(from, to).await?
+    };
+
+    Ok(build_report(from, to, grouping, &worklogs))
+}
+
+fn build_report(from: u64, to: u64, grouping: ReportGrouping, worklogs: &[IssueWorklog]) -> Report {
+    let mut seconds: HashMap<String, u64> = HashMap::new();
+    for entry in worklogs {
+        for key in keys(grouping, entry) {
+            *seconds.entry(key).or_default() += entry.worklog.total_time;
+        }
+    }
+
+    let total = worklogs.iter().map(|entry| entry.worklog.total_time).sum();
+    let mut rows: Vec<ReportRow> = seconds
+        .into_iter()
+        .map(|(key, seconds)| ReportRow {
+            key,
+            seconds,
+            percent: match total {
+                0 => 0.0,
+                total => seconds as f64 * 100.0 / total as f64,
+            },
+        })
+        .collect();
+
+    // periods in time order, the rest biggest first
+    match grouping {
+        ReportGrouping::Day | ReportGrouping::Week | ReportGrouping::Month => rows.sort_by(|a, b| a.key.cmp(&b.key)),
+        _ => rows.sort_by(|a, b| b.seconds.cmp(&a.seconds).then(a.key.cmp(&b.key))),
+    }
+
+    Report {
+        from,
+        to,
+        grouping,
+        total,
+        rows,
+    }
+}
+
+// a worklog of an issue with several tags counts for each of them
+fn keys(grouping: ReportGrouping, entry: &IssueWorklog) -> Vec<String> {
+    let or_none = |value: &str| match value.is_empty() {
+        true => NONE.to_string(),
+        false => value.to_string(),
+    };
+    let start = match Local.timestamp_opt(entry.worklog.start as i64, 0).earliest() {
+        Some(start) => start,
+        None => return vec![NONE.to_string()],
+    };
+
+    match grouping {
+        ReportGrouping::Day => vec![start.format("%Y-%m-%d").to_string()],
+        ReportGrouping::Week => {
+            let week = start.iso_week();
+            vec![format!("{}-W{:02}", week.year(), week.week())]
+        }
+        ReportGrouping::Month => vec![start.format("%Y-%m").to_string()],
+        ReportGrouping::Issue => vec![format!("{}: {}", entry.issue.name, entry.issue.summary)],
+        ReportGrouping::Project => vec![or_none(&entry.issue.project)],
+        ReportGrouping::Tag if entry.issue.tags.is_empty() => vec![NONE.to_string()],
+        ReportGrouping::Tag => entry.issue.tags.clone(),
+        ReportGrouping::Assignee => vec![or_none(&entry.issue.assignee_email)],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use shared::{Issue, Worklog};
+
+    use super::*;
+
+    fn entry(issue: &str, project: &str, tags: &[&str], start: u64, seconds: u64) -> IssueWorklog {
+        IssueWorklog {
+            worklog: Worklog::new(format!("{}-{}", issue, start), issue.to_string(), start, start + seconds, seconds),
+            issue: Issue {
+                name: issue.to_string(),
+                summary: "work".to_string(),
+                project: project.to_string(),
+                tags: tags.iter().map(|tag| tag.to_string()).collect(),
+                ..Issue::empty()
+            },
+        }
+    }
+
+    fn rows(report: &Report) -> Vec<(&str, u64, f64)> {
+        report.rows.iter().map(|row| (row.key.as_str(), row.seconds, row.percent)).collect()
+    }
+
+    /// Local noon of 2024-05-0`day`.
+    fn may(day: u32) -> u64 {
+        Local.with_ymd_and_hms(2024, 5, day, 12, 0, 0).unwrap().timestamp() as u64
+    }
+
+    fn worklogs() -> Vec<IssueWorklog> {
+        vec![
+            entry("ABC-1", "ABC", &["backend", "bug"], may(2), 3600),
+            entry("ABC-2", "ABC", &[], may(2), 1800),
+            entry("OPS-1", "", &["bug"], may(3), 5400),
+            entry("ABC-1", "ABC", &["backend", "bug"], may(6), 1800),
+        ]
+    }
+
+    #[test]
+    fn days_weeks_and_months_are_in_time_order() {
+        let report = build_report(may(1), may(8), ReportGrouping::Day, &worklogs());
+        assert_eq!(report.total, 12600);
+        let percent = |seconds: u64| seconds as f64 * 100.0 / 12600.0;
+        assert_eq!(
+            rows(&report),
+            vec![("2024-05-02", 5400, percent(5400)), ("2024-05-03", 5400, percent(5400)), ("2024-05-06", 1800, percent(1800))]
+        );
+
+        let report = build_report(may(1), may(8), ReportGrouping::Week, &worklogs());
+        assert_eq!(rows(&report).iter().map(|row| (row.0, row.1)).collect::<Vec<_>>(), vec![("2024-W18", 10800), ("2024-W19", 1800)]);
+
+        let report = build_report(may(1), may(8), ReportGrouping::Month, &worklogs());
+        assert_eq!(rows(&report), vec![("2024-05", 12600, 100.0)]);
+    }
+
+    #[test]
+    fn issues_and_projects_are_biggest_first() {
+        let report = build_report(may(1), may(8), ReportGrouping::Issue, &worklogs());
+        assert_eq!(
+            rows(&report).iter().map(|row| (row.0, row.1)).collect::<Vec<_>>(),
+            vec![("ABC-1: work", 5400), ("OPS-1: work", 5400), ("ABC-2: work", 1800)]
+        );
+
+        let report = build_report(may(1), may(8), ReportGrouping::Project, &worklogs());
+        assert_eq!(rows(&report).iter().map(|row| (row.0, row.1)).collect::<Vec<_>>(), vec![("ABC", 7200), (NONE, 5400)]);
+    }
+
+    #[test]
+    fn a_worklog_counts_for_each_tag_of_its_issue() {
+        let report = build_report(may(1), may(8), ReportGrouping::Tag, &worklogs());
+        assert_eq!(report.total, 12600);
+        assert_eq!(
+            rows(&report).iter().map(|row| (row.0, row.1)).collect::<Vec<_>>(),
+            vec![("bug", 10800), ("backend", 5400), (NONE, 1800)]
+        );
+        assert!(report.rows.iter().map(|row| row.percent).sum::<f64>() > 100.0);
+    }
+
+    #[test]
+    fn nothing_tracked_gives_no_rows() {
+        let report = build_report(may(1), may(8), ReportGrouping::Assignee, &[]);
+        assert_eq!(report.total, 0);
+        assert!(report.rows.is_empty());
+    }
+}
@@ -1,54 +1,48 @@
-use std::{borrow::Borrow, collections::VecDeque, num::ParseIntError};
+use std::{collections::VecDeque, str::FromStr};
 
 use serde::{Deserialize, Serialize};
-use shared::{Issue, Worklog};
-use sqlx::{Pool, Sqlite};
+use shared::{DailyTotal, ImportBatch, Issue, OpenWorklog, OutboxItem, OutboxStatus, TotalDrift, Worklog};
+use sqlx::{
+    sqlite::{SqliteConnectOptions, SqliteConnection, SqlitePoolOptions},
+    Pool, Sqlite,
+};
 
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct WorklogDB {
     pub id: String,
     pub issue_id: String,
-    pub start: String,
-    pub end: String,
-    pub total_time: String,
+    pub start: i64,
+    pub end: i64,
+    pub total_time: i64,
+    pub remote_id: Option<String>,
+    pub synced: i64,
 }
 
-impl TryFrom<WorklogDB> for Worklog {
-    type Error = ParseIntError;
-
-    fn try_from(value: WorklogDB) -> Result<Self, Self::Error> {
-        let start = match value.start.parse::<u64>() {
-            Ok(v) => v,
-            Err(e) => return Err(e),
-        };
-        let end = match value.end.parse::<u64>() {
-            Ok(v) => v,
-            Err(e) => return Err(e),
-        };
-        let total_time = match value.total_time.parse::<u64>() {
-            Ok(v) => v,
-            Err(e) => return Err(e),
-        };
-
-        Ok(Worklog {
+impl From<WorklogDB> for Worklog {
+    fn from(value: WorklogDB) -> Self {
+        Worklog {
             id: value.id,
             issue_id: value.issue_id,
-            start: start,
-            end: end,
-            total_time: total_time,
-        })
+            start: value.start as u64,
+            end: value.end as u64,
+            total_time: value.total_time as u64,
+            remote_id: value.remote_id,
+            synced: value.synced != 0,
+        }
     }
 }
 
-impl Into<WorklogDB> for Worklog {
-    fn into(self) -> WorklogDB {
+impl From<Worklog> for WorklogDB {
+    fn from(value: Worklog) -> Self {
         WorklogDB {
-            id: self.id,
-            issue_id: self.issue_id,
-            start: self.start.to_string(),
-            end: self.end.to_string(),
-            total_time: self.total_time.to_string(),
+            id: value.id,
+            issue_id: value.issue_id,
+            start: value.start as i64,
+            end: value.end as i64,
+            total_time: value.total_time as i64,
+            remote_id: value.remote_id,
+            synced: value.synced as i64,
         }
     }
 }
@@ -60,53 +54,179 @@ pub struct IssueDb {
     pub url: String,
     pub summary: String,
     pub assignee_email: String,
-    pub time_tracked_all: String,
+    pub time_tracked_all: i64,
+    pub source: String,
+    pub stale: i64,
+    pub status: String,
+    pub project: String,
+    pub issue_type: String,
+    pub time_estimate: i64,
+    pub remote_time_spent: i64,
+    pub tags: String,
 }
 
-impl TryFrom<IssueDb> for Issue {
-    type Error = ParseIntError;
-
-    fn try_from(value: IssueDb) -> Result<Self, Self::Error> {
-        let time = match value.time_tracked_all.parse::<u64>() {
-            Ok(v) => v,
-            Err(e) => return Err(e),
-        };
-
-        Ok(Issue {
+impl From<IssueDb> for Issue {
+    fn from(value: IssueDb) -> Self {
+        Issue {
             id: value.id,
             name: value.name,
             url: value.url,
             summary: value.summary,
             assignee_email: value.assignee_email,
-            time_tracked_all: time,
-        })
+            time_tracked_all: value.time_tracked_all as u64,
+            source: value.source,
+            stale: value.stale != 0,
+            status: value.status,
+            project: value.project,
+            issue_type: value.issue_type,
+            time_estimate: value.time_estimate as u64,
+            remote_time_spent: value.remote_time_spent as u64,
+            tags: value
+                .tags
+                .split(',')
+                .filter(|tag| !tag.is_empty())
+                .map(|tag| tag.to_string())
+                .collect(),
+        }
     }
 }
 
-impl Into<IssueDb> for Issue {
-    fn into(self) -> IssueDb {
+impl From<Issue> for IssueDb {
+    fn from(value: Issue) -> Self {
         IssueDb {
-            id: self.id,
-            name: self.name,
-            url: self.url,
-            summary: self.summary,
-            assignee_email: self.assignee_email,
-            time_tracked_all: self.time_tracked_all.to_string(),
+            id: value.id,
+            name: value.name,
+            url: value.url,
+            summary: value.summary,
+            assignee_email: value.assignee_email,
+            time_tracked_all: value.time_tracked_all as i64,
+            source: value.source,
+            stale: value.stale as i64,
+            status: value.status,
+            project: value.project,
+            issue_type: value.issue_type,
+            time_estimate: value.time_estimate as i64,
+            remote_time_spent: value.remote_time_spent as i64,
+            tags: value.tags.join(","),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct OutboxDb {
+    pub id: i64,
+    pub kind: String,
+    pub worklog_id: String,
+    pub issue_key: String,
+    pub remote_id: Option<String>,
+    pub payload: String,
+    pub attempts: i64,
+    pub next_attempt: i64,
+    pub failed: i64,
+    pub last_error: Option<String>,
+}
+
+impl From<OutboxDb> for OutboxItem {
+    fn from(value: OutboxDb) -> Self {
+        OutboxItem {
+            id: value.id,
+            kind: value.kind,
+            worklog_id: value.worklog_id,
+            issue_key: value.issue_key,
+            attempts: value.attempts as u64,
+            last_error: value.last_error,
         }
     }
 }
 
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct OpenWorklogDb {
+    pub id: String,
+    pub issue_id: String,
+    pub start: i64,
+    pub end: i64,
+    pub total_time: i64,
+    pub remote_id: Option<String>,
+    pub synced: i64,
+    pub heartbeat: i64,
+    pub issue_name: String,
+    pub issue_summary: String,
+}
+
+impl From<OpenWorklogDb> for OpenWorklog {
+    fn from(value: OpenWorklogDb) -> Self {
+        let worklog = Worklog::from(WorklogDB {
+            id: value.id,
+            issue_id: value.issue_id,
+            start: value.start,
+            end: value.end,
+            total_time: value.total_time,
+            remote_id: value.remote_id,
+            synced: value.synced,
+        });
+
+        OpenWorklog {
+            last_heartbeat: (value.heartbeat as u64).max(worklog.start),
+            worklog,
+            issue_name: value.issue_name,
+            issue_summary: value.issue_summary,
+        }
+    }
+}
+
+/// A worklog with the fields of its issue reports and exports need.
+#[derive(Debug, PartialEq, Clone)]
+pub struct IssueWorklog {
+    pub worklog: Worklog,
+    pub issue: Issue,
+}
+
+/// A write to the outbox, worked out ahead so it can be written together with the worklog change
+/// it belongs to.
+#[derive(Debug, PartialEq)]
+pub enum OutboxChange {
+    /// the tracker never saw the worklog, its queued writes are dropped
+    Drop(Vec<i64>),
+    Add {
+        kind: &'static str,
+        worklog_id: String,
+        issue_key: String,
+        remote_id: Option<String>,
+        payload: String,
+        /// the tracker's copy is forgotten with it, a later push creates the worklog again
+        unsync: bool,
+    },
+}
+
 #[derive(Clone)]
 pub struct Storage {
     pub conn: Pool<Sqlite>,
 }
 
 impl Storage {
+    /// Connects to the sqlite database, creating it when missing, and runs the migrations.
+    pub async fn open(database_url: &str) -> Result<Storage, String> {
+        let options = SqliteConnectOptions::from_str(database_url)
+            .map_err(|e| e.to_string())?
+            .create_if_missing(true)
+            .foreign_keys(true);
+
+        let conn = SqlitePoolOptions::new()
+            .max_connections(3)
+            .connect_with(options)
+            .await
+            .map_err(|e| e.to_string())?;
+        sqlx::migrate!().run(&conn).await.map_err(|e| e.to_string())?;
+
+        Ok(Storage { conn })
+    }
+
     pub async fn get_issue(&self, name: String) -> Result<Issue, String> {
         let val = sqlx::query_as!(
             IssueDb,
             r#"
-            select id, name, url, summary, assignee_email, time_tracked_all
+            select id, name, url, summary, assignee_email, time_tracked_all, source, stale,
+                   status, project, issue_type, time_estimate, remote_time_spent, tags
             from issue
             where name = $1
             "#,
@@ -119,11 +239,30 @@ impl Storage {
         convert_to_issue(val)
     }
 
+    pub async fn get_issue_by_id(&self, id: &str) -> Result<Issue, String> {
+        let val = sqlx::query_as!(
+            IssueDb,
+            r#"
+            select id, name, url, summary, assignee_email, time_tracked_all, source, stale,
+                   status, project, issue_type, time_estimate, remote_time_spent, tags
+            from issue
+            where id = $1
+            "#,
+            id
+        )
+        .fetch_one(&self.conn)
+        .await
+        .map_err(|e| e.to_string());
+
+        convert_to_issue(val)
+    }
+
     pub async fn get_issues(&self) -> Result<Vec<Issue>, String> {
         let values = sqlx::query_as!(
             IssueDb,
             r#"
-            select id, name, url, summary, assignee_email, time_tracked_all
+            select id, name, url, summary, assignee_email, time_tracked_all, source, stale,
+                   status, project, issue_type, time_estimate, remote_time_spent, tags
             from issue
             "#
         )
@@ -131,19 +270,7 @@ impl Storage {
         .await
         .map_err(|e| e.to_string());
 
-        match values {
-            Ok(values) => {
-                let mut exit_vales = Vec::new();
-                for val in values {
-                    match Issue::try_from(val) {
-                        Ok(val) => exit_vales.push(val),
-                        Err(e) => return Err(e.to_string()),
-                    }
-                }
-                Ok(exit_vales)
-            }
-            Err(err) => Err(err),
-        }
+        values.map(|values| values.into_iter().map(Issue::from).collect())
     }
 
     pub async fn add_issue(&self, given_issue: Issue) -> Result<Issue, String> {
@@ -151,46 +278,98 @@ impl Storage {
         let val = sqlx::query_as!(
             IssueDb,
             r#"
-            insert into issue(id, name, url, summary, assignee_email, time_tracked_all)
-            values($1,$2,$3,$4,$5,$6)
-            returning id, name, url, summary, assignee_email, time_tracked_all
+            insert into issue(id, name, url, summary, assignee_email, time_tracked_all, source, stale,
+                              status, project, issue_type, time_estimate, remote_time_spent, tags)
+            values($1,$2,$3,$4,$5,0,$6,$7,$8,$9,$10,$11,$12,$13)
+            returning id, name, url, summary, assignee_email, time_tracked_all, source, stale,
+                   status, project, issue_type, time_estimate, remote_time_spent, tags
             "#,
             issue.id,
             issue.name,
             issue.url,
             issue.summary,
             issue.assignee_email,
-            issue.time_tracked_all
+            issue.source,
+            issue.stale,
+            issue.status,
+            issue.project,
+            issue.issue_type,
+            issue.time_estimate,
+            issue.remote_time_spent,
+            issue.tags
         )
-        .fetch_one(&self.conn)
-        .await
-        .map_err(|e| e.to_string());
+        .fetch_all(&self.conn)
+        .await;
 
-        convert_to_issue(val)
+        convert_to_issue(written_row(val))
     }
 
-    pub async fn edit_issue(&self, given_issue: Issue) -> Result<Issue, String> {
+    /// Inserts an issue coming from a tracker or refreshes the one with the same key,
+    /// locally tracked time is kept.
+    pub async fn upsert_issue(&self, given_issue: Issue) -> Result<Issue, String> {
         let issue: IssueDb = given_issue.into();
         let val = sqlx::query_as!(
             IssueDb,
             r#"
-            update issue
-            set name = $2, url = $3, summary = $4, assignee_email = $5, time_tracked_all = $6
-            where id = $1
-            returning id, name, url, summary, assignee_email, time_tracked_all
+            insert into issue(id, name, url, summary, assignee_email, time_tracked_all, source, stale,
+                              status, project, issue_type, time_estimate, remote_time_spent, tags)
+            values($1,$2,$3,$4,$5,0,$6,0,$7,$8,$9,$10,$11,$12)
+            on conflict(name) do update
+            set url = excluded.url, summary = excluded.summary, assignee_email = excluded.assignee_email,
+                source = excluded.source, stale = 0, status = excluded.status, project = excluded.project,
+                issue_type = excluded.issue_type, time_estimate = excluded.time_estimate,
+                remote_time_spent = excluded.remote_time_spent, tags = excluded.tags
+            returning id, name, url, summary, assignee_email, time_tracked_all, source, stale,
+                   status, project, issue_type, time_estimate, remote_time_spent, tags
             "#,
             issue.id,
             issue.name,
             issue.url,
             issue.summary,
             issue.assignee_email,
-            issue.time_tracked_all
+            issue.source,
+            issue.status,
+            issue.project,
+            issue.issue_type,
+            issue.time_estimate,
+            issue.remote_time_spent,
+            issue.tags
         )
-        .fetch_one(&self.conn)
+        .fetch_all(&self.conn)
+        .await;
+
+        convert_to_issue(written_row(val))
+    }
+
+    pub async fn set_issue_stale(&self, id: &str, stale: bool) -> Result<(), String> {
+        sqlx::query!(
+            r#"
+            update issue set stale = $2 where id = $1
+            "#,
+            id,
+            stale
+        )
+        .execute(&self.conn)
         .await
-        .map_err(|e| e.to_string());
+        .map_err(|e| e.to_string())?;
 
-        convert_to_issue(val)
+        Ok(())
+    }
+
+    pub async fn set_issue_remote_time_spent(&self, name: &str, time_spent: u64) -> Result<(), String> {
+        let time_spent = time_spent as i64;
+        sqlx::query!(
+            r#"
+            update issue set remote_time_spent = $2 where name = $1
+            "#,
+            name,
+            time_spent
+        )
+        .execute(&self.conn)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(())
     }
 
     pub async fn add_worklog(&self, given_worklog: Worklog) -> Result<Worklog, String> {
@@ -198,21 +377,22 @@ impl Storage {
         let val = sqlx::query_as!(
             WorklogDB,
             r#"
-            insert into worklog(id, issue_id, start, end, total_time)
-            values($1,$2,$3,$4,$5)
-            returning id, issue_id, start, end, total_time
+            insert into worklog(id, issue_id, start, end, total_time, remote_id, synced)
+            values($1,$2,$3,$4,$5,$6,$7)
+            returning id, issue_id, start, end, total_time, remote_id, synced
             "#,
             worklog.id,
             worklog.issue_id,
             worklog.start,
             worklog.end,
             worklog.total_time,
+            worklog.remote_id,
+            worklog.synced,
         )
-        .fetch_one(&self.conn)
-        .await
-        .map_err(|e| e.to_string());
+        .fetch_all(&self.conn)
+        .await;
 
-        convert_to_worklog(val)
+        convert_to_worklog(written_row(val))
     }
 
     pub async fn edit_worklog(&self, given_worklog: Worklog) -> Result<Worklog, String> {
@@ -221,14 +401,33 @@ impl Storage {
             WorklogDB,
             r#"
             update worklog
-            set start = $2, end = $3, total_time = $4
+            set start = $2, end = $3, total_time = $4, remote_id = $5, synced = $6, issue_id = $7
             where id = $1
-            returning id, issue_id, start, end, total_time
+            returning id, issue_id, start, end, total_time, remote_id, synced
             "#,
             worklog.id,
             worklog.start,
             worklog.end,
             worklog.total_time,
+            worklog.remote_id,
+            worklog.synced,
+            worklog.issue_id,
+        )
+        .fetch_all(&self.conn)
+        .await;
+
+        convert_to_worklog(written_row(val))
+    }
+
+    pub async fn get_worklog(&self, id: &str) -> Result<Worklog, String> {
+        let val = sqlx::query_as!(
+            WorklogDB,
+            r#"
+            select id, issue_id, start, end, total_time, remote_id, synced
+            from worklog
+            where id = $1
+            "#,
+            id
         )
         .fetch_one(&self.conn)
         .await
@@ -237,67 +436,937 @@ impl Storage {
         convert_to_worklog(val)
     }
 
-    pub async fn save_recent_issues(&self, recent_issues: VecDeque<String>) -> Result<(), String> {
+    pub async fn get_issue_worklogs(&self, issue_id: &str) -> Result<Vec<Worklog>, String> {
+        let values = sqlx::query_as!(
+            WorklogDB,
+            r#"
+            select id, issue_id, start, end, total_time, remote_id, synced
+            from worklog
+            where issue_id = $1
+            order by start
+            "#,
+            issue_id
+        )
+        .fetch_all(&self.conn)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(values.into_iter().map(Worklog::from).collect())
+    }
+
+    /// Worklogs started in `[from, to)`, of every issue.
+    pub async fn get_worklogs_between(&self, from: u64, to: u64) -> Result<Vec<Worklog>, String> {
+        let (from, to) = (from as i64, to as i64);
+        let values = sqlx::query_as!(
+            WorklogDB,
+            r#"
+            select id, issue_id, start, end, total_time, remote_id, synced
+            from worklog
+            where start >= $1 and start < $2
+            order by start
+            "#,
+            from,
+            to
+        )
+        .fetch_all(&self.conn)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(values.into_iter().map(Worklog::from).collect())
+    }
+
+    /// Worklogs other than `id` sharing time with `[start, end)`, open ones count as still running.
+    pub async fn get_overlapping_worklogs(&self, id: &str, start: u64, end: u64) -> Result<Vec<Worklog>, String> {
+        let (start, end) = (start as i64, end as i64);
+        let values = sqlx::query_as!(
+            WorklogDB,
+            r#"
+            select id, issue_id, start, end, total_time, remote_id, synced
+            from worklog
+            where id != $1 and start < $3 and (end > $2 or end = 0)
+            order by start
+            "#,
+            id,
+            start,
+            end
+        )
+        .fetch_all(&self.conn)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(values.into_iter().map(Worklog::from).collect())
+    }
+
+    /// Finished worklogs started in `[from, to)` with their issue, oldest first.
+    pub async fn get_issue_worklogs_between(&self, from: u64, to: u64) -> Result<Vec<IssueWorklog>, String> {
+        let (from, to) = (from as i64, to as i64);
+        let values = sqlx::query!(
+            r#"
+            select worklog.id, worklog.issue_id, worklog.start, worklog.end, worklog.total_time,
+                worklog.remote_id, worklog.synced,
+                issue.name, issue.url, issue.summary, issue.assignee_email, issue.time_tracked_all,
+                issue.source, issue.stale, issue.status, issue.project, issue.issue_type,
+                issue.time_estimate, issue.remote_time_spent, issue.tags
+            from worklog
+            join issue on issue.id = worklog.issue_id
+            where worklog.end != 0 and worklog.start >= $1 and worklog.start < $2
+            order by worklog.start
+            "#,
+            from,
+            to
+        )
+        .fetch_all(&self.conn)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(values
+            .into_iter()
+            .map(|val| IssueWorklog {
+                worklog: Worklog::from(WorklogDB {
+                    id: val.id,
+                    issue_id: val.issue_id.clone(),
+                    start: val.start,
+                    end: val.end,
+                    total_time: val.total_time,
+                    remote_id: val.remote_id,
+                    synced: val.synced,
+                }),
+                issue: Issue::from(IssueDb {
+                    id: val.issue_id,
+                    name: val.name,
+                    url: val.url,
+                    summary: val.summary,
+                    assignee_email: val.assignee_email,
+                    time_tracked_all: val.time_tracked_all,
+                    source: val.source,
+                    stale: val.stale,
+                    status: val.status,
+                    project: val.project,
+                    issue_type: val.issue_type,
+                    time_estimate: val.time_estimate,
+                    remote_time_spent: val.remote_time_spent,
+                    tags: val.tags,
+                }),
+            })
+            .collect())
+    }
+
+    /// Time per issue and local day in the finished worklogs started in `[from, to)`.
+    pub async fn get_daily_totals(&self, from: u64, to: u64) -> Result<Vec<DailyTotal>, String> {
+        let (from, to) = (from as i64, to as i64);
+        let values = sqlx::query!(
+            r#"
+            select issue.name, issue.summary,
+                date(worklog.start, 'unixepoch', 'localtime') as "day!: String",
+                sum(worklog.total_time) as "seconds!: i64"
+            from worklog
+            join issue on issue.id = worklog.issue_id
+            where worklog.end != 0 and worklog.start >= $1 and worklog.start < $2
+            group by issue.id, 3
+            order by issue.name, 3
+            "#,
+            from,
+            to
+        )
+        .fetch_all(&self.conn)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(values
+            .into_iter()
+            .map(|val| DailyTotal {
+                issue_name: val.name,
+                issue_summary: val.summary,
+                day: val.day,
+                seconds: val.seconds as u64,
+            })
+            .collect())
+    }
+
+    /// Issues whose cached total differs from the time in their finished worklogs.
+    pub async fn get_time_tracked_drift(&self) -> Result<Vec<TotalDrift>, String> {
+        let values = sqlx::query!(
+            r#"
+            select issue.name, issue.time_tracked_all,
+                coalesce(sum(worklog.total_time), 0) as "worklogs!: i64"
+            from issue
+            left join worklog on worklog.issue_id = issue.id and worklog.end != 0
+            group by issue.id
+            having issue.time_tracked_all != coalesce(sum(worklog.total_time), 0)
+            order by issue.name
+            "#
+        )
+        .fetch_all(&self.conn)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        values
+            .into_iter()
+            .map(|val| {
+                Ok(TotalDrift {
+                    issue_name: val.name,
+                    recorded: val.time_tracked_all as u64,
+                    worklogs: val.worklogs as u64,
+                })
+            })
+            .collect()
+    }
+
+    /// Sets every cached issue total to the time in its finished worklogs.
+    pub async fn repair_time_tracked(&self) -> Result<(), String> {
         sqlx::query!(
             r#"
-            delete from recent_issue
+            update issue
+            set time_tracked_all = (
+                select coalesce(sum(total_time), 0)
+                from worklog
+                where worklog.issue_id = issue.id and worklog.end != 0
+            )
+            "#
+        )
+        .execute(&self.conn)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(())
+    }
+
+    pub async fn get_unsynced_worklogs(&self) -> Result<Vec<Worklog>, String> {
+        let values = sqlx::query_as!(
+            WorklogDB,
+            r#"
+            select id, issue_id, start, end, total_time, remote_id, synced
+            from worklog
+            where synced = 0 and end != 0
+            order by start
+            "#
+        )
+        .fetch_all(&self.conn)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(values.into_iter().map(Worklog::from).collect())
+    }
+
+    pub async fn delete_worklog(&self, id: &str) -> Result<(), String> {
+        sqlx::query!(
+            r#"
+            delete from worklog
+            where id = $1
+            "#,
+            id
+        )
+        .execute(&self.conn)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(())
+    }
+
+    pub async fn set_worklog_heartbeat(&self, id: &str, heartbeat: u64) -> Result<(), String> {
+        let heartbeat = heartbeat as i64;
+        sqlx::query!(
+            r#"
+            update worklog
+            set heartbeat = $2
+            where id = $1
+            "#,
+            id,
+            heartbeat
+        )
+        .execute(&self.conn)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(())
+    }
+
+    /// Worklogs that were never finished, the one tracked right now included.
+    pub async fn get_open_worklogs(&self) -> Result<Vec<OpenWorklog>, String> {
+        let values = sqlx::query_as!(
+            OpenWorklogDb,
+            r#"
+            select worklog.id, worklog.issue_id, worklog.start, worklog.end, worklog.total_time,
+                worklog.remote_id, worklog.synced, worklog.heartbeat,
+                issue.name as issue_name, issue.summary as issue_summary
+            from worklog
+            join issue on issue.id = worklog.issue_id
+            where worklog.end = 0
+            order by worklog.start
+            "#
+        )
+        .fetch_all(&self.conn)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(values.into_iter().map(OpenWorklog::from).collect())
+    }
+
+    /// Time in the finished worklogs started at `since` or later.
+    pub async fn get_tracked_since(&self, since: u64) -> Result<u64, String> {
+        let since = since as i64;
+        let total = sqlx::query_scalar!(
+            r#"
+            select coalesce(sum(total_time), 0) as "total!: i64"
+            from worklog
+            where end != 0 and start >= $1
+            "#,
+            since
+        )
+        .fetch_one(&self.conn)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(total as u64)
+    }
+
+    pub async fn mark_worklog_synced(&self, id: &str, remote_id: &str) -> Result<(), String> {
+        sqlx::query!(
+            r#"
+            update worklog
+            set remote_id = $2, synced = 1
+            where id = $1
+            "#,
+            id,
+            remote_id
+        )
+        .execute(&self.conn)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(())
+    }
+
+    /// Writes a change of the outbox worked out by `sync`.
+    pub async fn change_outbox(&self, change: &OutboxChange) -> Result<(), String> {
+        let mut tx = self.conn.begin().await.map_err(|e| e.to_string())?;
+        write_outbox_change(&mut tx, change).await?;
+        tx.commit().await.map_err(|e| e.to_string())
+    }
+
+    /// Oldest item that did not fail permanently, items have to be replayed in order.
+    pub async fn get_next_outbox_item(&self) -> Result<Option<OutboxDb>, String> {
+        sqlx::query_as!(
+            OutboxDb,
+            r#"
+            select id, kind, worklog_id, issue_key, remote_id, payload, attempts, next_attempt, failed, last_error
+            from outbox
+            where failed = 0
+            order by id
+            limit 1
             "#
         )
+        .fetch_optional(&self.conn)
+        .await
+        .map_err(|e| e.to_string())
+    }
+
+    pub async fn get_outbox_items_for_worklog(&self, worklog_id: &str) -> Result<Vec<OutboxDb>, String> {
+        sqlx::query_as!(
+            OutboxDb,
+            r#"
+            select id, kind, worklog_id, issue_key, remote_id, payload, attempts, next_attempt, failed, last_error
+            from outbox
+            where worklog_id = $1
+            order by id
+            "#,
+            worklog_id
+        )
+        .fetch_all(&self.conn)
+        .await
+        .map_err(|e| e.to_string())
+    }
+
+    /// Whether the outbox holds writes of the issue other than the item `except`, failed ones included.
+    pub async fn has_other_outbox_items(&self, issue_key: &str, except: i64) -> Result<bool, String> {
+        let count = sqlx::query_scalar!(
+            r#"
+            select count(*) as "count!: i64" from outbox where issue_key = $1 and id != $2
+            "#,
+            issue_key,
+            except
+        )
+        .fetch_one(&self.conn)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(count > 0)
+    }
+
+    pub async fn delete_outbox_item(&self, id: i64) -> Result<(), String> {
+        sqlx::query!(
+            r#"
+            delete from outbox where id = $1
+            "#,
+            id
+        )
+        .execute(&self.conn)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(())
+    }
+
+    pub async fn reschedule_outbox_item(&self, id: i64, next_attempt: i64, error: String) -> Result<(), String> {
+        sqlx::query!(
+            r#"
+            update outbox
+            set attempts = attempts + 1, next_attempt = $2, last_error = $3
+            where id = $1
+            "#,
+            id,
+            next_attempt,
+            error
+        )
+        .execute(&self.conn)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(())
+    }
+
+    pub async fn fail_outbox_item(&self, id: i64, error: String) -> Result<(), String> {
+        sqlx::query!(
+            r#"
+            update outbox
+            set attempts = attempts + 1, failed = 1, last_error = $2
+            where id = $1
+            "#,
+            id,
+            error
+        )
+        .execute(&self.conn)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(())
+    }
+
+    pub async fn retry_outbox_item(&self, id: i64) -> Result<(), String> {
+        sqlx::query!(
+            r#"
+            update outbox
+            set failed = 0, next_attempt = 0
+            where id = $1
+            "#,
+            id
+        )
+        .execute(&self.conn)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(())
+    }
+
+    /// Fills in the remote id for queued updates and deletes once the create went through.
+    pub async fn set_outbox_remote_id(&self, worklog_id: &str, remote_id: &str) -> Result<(), String> {
+        sqlx::query!(
+            r#"
+            update outbox
+            set remote_id = $2
+            where worklog_id = $1 and remote_id is null
+            "#,
+            worklog_id,
+            remote_id
+        )
         .execute(&self.conn)
         .await
         .map_err(|e| e.to_string())?;
 
+        Ok(())
+    }
+
+    pub async fn get_outbox_status(&self) -> Result<OutboxStatus, String> {
+        let pending = sqlx::query!(
+            r#"
+            select count(*) as "count: i64" from outbox where failed = 0
+            "#
+        )
+        .fetch_one(&self.conn)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        let failed = sqlx::query_as!(
+            OutboxDb,
+            r#"
+            select id, kind, worklog_id, issue_key, remote_id, payload, attempts, next_attempt, failed, last_error
+            from outbox
+            where failed = 1
+            order by id
+            "#
+        )
+        .fetch_all(&self.conn)
+        .await
+        .map_err(|e| e.to_string())?;
 
-        for issue in recent_issues.iter() {
+        Ok(OutboxStatus {
+            pending: pending.count as u64,
+            failed: failed.into_iter().map(OutboxItem::from).collect(),
+        })
+    }
+
+    /// Whether the issue has a worklog with exactly this start and end.
+    pub async fn has_worklog(&self, issue_id: &str, start: u64, end: u64) -> Result<bool, String> {
+        let (start, end) = (start as i64, end as i64);
+        let found = sqlx::query_scalar!(
+            r#"
+            select exists(select 1 from worklog where issue_id = $1 and start = $2 and end = $3) as "found!: bool"
+            "#,
+            issue_id,
+            start,
+            end
+        )
+        .fetch_one(&self.conn)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(found)
+    }
+
+    /// Adds an import with the issues it creates and its worklogs, all of it or nothing.
+    pub async fn add_import_batch(&self, batch: &ImportBatch, issues: &[Issue], worklogs: &[Worklog]) -> Result<(), String> {
+        let mut tx = self.conn.begin().await.map_err(|e| e.to_string())?;
+        let created = batch.created as i64;
+        sqlx::query!(
+            r#"
+            insert into import_batch(id, created, source) values($1,$2,$3)
+            "#,
+            batch.id,
+            created,
+            batch.source
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        for issue in issues {
+            let issue: IssueDb = issue.clone().into();
             sqlx::query!(
                 r#"
-                insert into recent_issue(name) values ($1) 
+                insert into issue(id, name, url, summary, assignee_email, time_tracked_all, source, stale,
+                                  status, project, issue_type, time_estimate, remote_time_spent, tags, import_batch)
+                values($1,$2,$3,$4,$5,0,$6,$7,$8,$9,$10,$11,$12,$13,$14)
                 "#,
-                issue
+                issue.id,
+                issue.name,
+                issue.url,
+                issue.summary,
+                issue.assignee_email,
+                issue.source,
+                issue.stale,
+                issue.status,
+                issue.project,
+                issue.issue_type,
+                issue.time_estimate,
+                issue.remote_time_spent,
+                issue.tags,
+                batch.id
             )
-            .execute(&self.conn)
+            .execute(&mut *tx)
             .await
             .map_err(|e| e.to_string())?;
         }
 
-        Ok(())
+        for worklog in worklogs {
+            let worklog: WorklogDB = worklog.clone().into();
+            sqlx::query!(
+                r#"
+                insert into worklog(id, issue_id, start, end, total_time, remote_id, synced, import_batch)
+                values($1,$2,$3,$4,$5,$6,$7,$8)
+                "#,
+                worklog.id,
+                worklog.issue_id,
+                worklog.start,
+                worklog.end,
+                worklog.total_time,
+                worklog.remote_id,
+                worklog.synced,
+                batch.id
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+        }
+
+        tx.commit().await.map_err(|e| e.to_string())
+    }
+
+    /// Imports not undone yet, the latest first.
+    pub async fn get_import_batches(&self) -> Result<Vec<ImportBatch>, String> {
+        let values = sqlx::query!(
+            r#"
+            select id, created, source,
+                (select count(*) from worklog where worklog.import_batch = import_batch.id) as "worklogs!: i64",
+                (select count(*) from issue where issue.import_batch = import_batch.id) as "issues!: i64"
+            from import_batch
+            order by created desc
+            "#
+        )
+        .fetch_all(&self.conn)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(values
+            .into_iter()
+            .map(|row| ImportBatch {
+                id: row.id,
+                created: row.created as u64,
+                source: row.source,
+                worklogs: row.worklogs as u64,
+                issues: row.issues as u64,
+            })
+            .collect())
+    }
+
+    pub async fn get_import_batch_worklogs(&self, batch: &str) -> Result<Vec<Worklog>, String> {
+        let values = sqlx::query_as!(
+            WorklogDB,
+            r#"
+            select id, issue_id, start, end, total_time, remote_id, synced
+            from worklog
+            where import_batch = $1
+            "#,
+            batch
+        )
+        .fetch_all(&self.conn)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(values.into_iter().map(Worklog::from).collect())
+    }
+
+    /// Removes the worklogs of an import together with the outbox changes deleting them in the
+    /// tracker, then forgets the import. The issues it created go too unless time was logged on
+    /// them since. All of it or nothing.
+    pub async fn delete_import_batch(&self, batch: &str, outbox: &[OutboxChange]) -> Result<(), String> {
+        let mut tx = self.conn.begin().await.map_err(|e| e.to_string())?;
+        for change in outbox {
+            write_outbox_change(&mut tx, change).await?;
+        }
+
+        sqlx::query!(
+            r#"
+            delete from worklog where import_batch = $1
+            "#,
+            batch
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        sqlx::query!(
+            r#"
+            delete from issue
+            where import_batch = $1 and not exists(select 1 from worklog where worklog.issue_id = issue.id)
+            "#,
+            batch
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        sqlx::query!(
+            r#"
+            update issue set import_batch = null where import_batch = $1
+            "#,
+            batch
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        sqlx::query!(
+            r#"
+            delete from import_batch where id = $1
+            "#,
+            batch
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        tx.commit().await.map_err(|e| e.to_string())
+    }
+
+    pub async fn save_recent_issues(&self, recent_issues: VecDeque<String>) -> Result<(), String> {
+        let mut tx = self.conn.begin().await.map_err(|e| e.to_string())?;
+        sqlx::query!(
+            r#"
+            delete from recent_issue
+            "#
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        for (position, issue) in recent_issues.iter().enumerate() {
+            let position = position as i64;
+            sqlx::query!(
+                r#"
+                insert into recent_issue(name, position) values ($1, $2)
+                "#,
+                issue,
+                position
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+        }
+
+        tx.commit().await.map_err(|e| e.to_string())
     }
 
+    /// The recent issues, oldest first.
     pub async fn get_recent_issues(&self) -> Result<VecDeque<String>, String> {
         let values = sqlx::query!(
             r#"
             select name from recent_issue
+            order by position
             "#,
         )
         .fetch_all(&self.conn)
         .await
         .map_err(|e| e.to_string())?;
-        
-        let mut recent_issues = VecDeque::new();
-        for record in values.iter() {
-            recent_issues.push_back(record.name.clone())
+
+        Ok(values.into_iter().map(|record| record.name).collect())
+    }
+}
+
+/// The row of an `insert` or `update ... returning`, the statement is run to its end so the write
+/// is committed before the connection goes back to the pool, `fetch_one` stops at the first row.
+fn written_row<T>(rows: Result<Vec<T>, sqlx::Error>) -> Result<T, String> {
+    rows.map_err(|e| e.to_string())?
+        .into_iter()
+        .next()
+        .ok_or_else(|| sqlx::Error::RowNotFound.to_string())
+}
+
+async fn write_outbox_change(conn: &mut SqliteConnection, change: &OutboxChange) -> Result<(), String> {
+    match change {
+        OutboxChange::Drop(ids) => {
+            for id in ids {
+                sqlx::query!(
+                    r#"
+                    delete from outbox where id = $1
+                    "#,
+                    id
+                )
+                .execute(&mut *conn)
+                .await
+                .map_err(|e| e.to_string())?;
+            }
         }
+        OutboxChange::Add {
+            kind,
+            worklog_id,
+            issue_key,
+            remote_id,
+            payload,
+            unsync,
+        } => {
+            sqlx::query!(
+                r#"
+                insert into outbox(kind, worklog_id, issue_key, remote_id, payload)
+                values($1,$2,$3,$4,$5)
+                "#,
+                kind,
+                worklog_id,
+                issue_key,
+                remote_id,
+                payload
+            )
+            .execute(&mut *conn)
+            .await
+            .map_err(|e| e.to_string())?;
 
-        Ok(recent_issues)
+            if *unsync {
+                sqlx::query!(
+                    r#"
+                    update worklog
+                    set remote_id = null, synced = 0
+                    where id = $1
+                    "#,
+                    worklog_id
+                )
+                .execute(&mut *conn)
+                .await
+                .map_err(|e| e.to_string())?;
+            }
+        }
     }
+
+    Ok(())
 }
 
 fn convert_to_issue(val: Result<IssueDb, String>) -> Result<Issue, String> {
-    match val {
-        Ok(val) => match Issue::try_from(val) {
-            Ok(issue) => Ok(issue),
-            Err(e) => Err(e.to_string()),
-        },
-        Err(e) => Err(e),
-    }
+    val.map(Issue::from)
 }
 
 fn convert_to_worklog(val: Result<WorklogDB, String>) -> Result<Worklog, String> {
-    match val {
-        Ok(val) => match Worklog::try_from(val) {
-            Ok(issue) => Ok(issue),
-            Err(e) => Err(e.to_string()),
-        },
-        Err(e) => Err(e),
+    val.map(Worklog::from)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::borrow::Cow;
+
+    use uuid::Uuid;
+
+    use super::*;
+
+    const INTEGER_TIMES: i64 = 20240512174521;
+
+    fn temp_database_url() -> String {
+        let path = std::env::temp_dir().join(format!("time_track_{}.db", Uuid::new_v4()));
+        format!("sqlite:{}", path.display())
+    }
+
+    /// A migrated database of its own for each test.
+    pub async fn test_storage() -> Storage {
+        Storage::open(&temp_database_url()).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn recent_issues_keep_their_order() {
+        let storage = test_storage().await;
+        let recent: VecDeque<String> = ["ABC-3", "ABC-1", "ABC-2"].into_iter().map(String::from).collect();
+
+        storage.save_recent_issues(recent.clone()).await.unwrap();
+        assert_eq!(storage.get_recent_issues().await.unwrap(), recent);
+
+        let moved: VecDeque<String> = ["ABC-1", "ABC-2", "ABC-3"].into_iter().map(String::from).collect();
+        storage.save_recent_issues(moved.clone()).await.unwrap();
+        assert_eq!(storage.get_recent_issues().await.unwrap(), moved);
+    }
+
+    #[tokio::test]
+    async fn issue_total_follows_its_finished_worklogs() {
+        let storage = test_storage().await;
+        let issue = Issue {
+            id: "i1".to_string(),
+            name: "ABC-1".to_string(),
+            ..Issue::empty()
+        };
+        storage.add_issue(issue).await.unwrap();
+        let total = || async { storage.get_issue_by_id("i1").await.unwrap().time_tracked_all };
+
+        // rounded up to 15 minutes, the total is the rounded time
+        storage.add_worklog(Worklog::new("w1".to_string(), "i1".to_string(), 100, 700, 900)).await.unwrap();
+        let open = storage.add_worklog(Worklog::new("w2".to_string(), "i1".to_string(), 1000, 0, 0)).await.unwrap();
+        assert_eq!(total().await, 900);
+
+        let finished = Worklog {
+            end: 1300,
+            total_time: 300,
+            ..open
+        };
+        storage.edit_worklog(finished).await.unwrap();
+        assert_eq!(total().await, 1200);
+
+        storage.delete_worklog("w1").await.unwrap();
+        assert_eq!(total().await, 300);
+    }
+
+    fn import_batch(id: &str) -> ImportBatch {
+        ImportBatch {
+            id: id.to_string(),
+            created: 100,
+            source: "hours.csv".to_string(),
+            worklogs: 0,
+            issues: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn import_with_a_failing_row_adds_nothing() {
+        let storage = test_storage().await;
+        let issue = Issue {
+            id: "i1".to_string(),
+            name: "ABC-1".to_string(),
+            ..Issue::empty()
+        };
+        let worklog = Worklog::new("w1".to_string(), "i1".to_string(), 100, 160, 60);
+        // the second row has the id of the first
+        let worklogs = [worklog.clone(), Worklog { start: 200, end: 260, ..worklog }];
+
+        assert!(storage.add_import_batch(&import_batch("b1"), &[issue], &worklogs).await.is_err());
+        assert!(storage.get_import_batches().await.unwrap().is_empty());
+        assert!(storage.get_issues().await.unwrap().is_empty());
+        assert!(storage.get_worklog("w1").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn undone_import_takes_its_worklogs_issues_and_outbox_changes_along() {
+        let storage = test_storage().await;
+        let kept = Issue {
+            id: "i1".to_string(),
+            name: "ABC-1".to_string(),
+            ..Issue::empty()
+        };
+        storage.add_issue(kept).await.unwrap();
+        let created = Issue {
+            id: "i2".to_string(),
+            name: "ABC-2".to_string(),
+            ..Issue::empty()
+        };
+        let worklogs = [
+            Worklog::new("w1".to_string(), "i1".to_string(), 100, 160, 60),
+            Worklog::new("w2".to_string(), "i2".to_string(), 200, 260, 60),
+        ];
+        storage.add_import_batch(&import_batch("b1"), &[created], &worklogs).await.unwrap();
+        let batches = storage.get_import_batches().await.unwrap();
+        assert_eq!(batches.iter().map(|b| (b.worklogs, b.issues)).collect::<Vec<_>>(), vec![(2, 1)]);
+        assert_eq!(storage.get_issue_by_id("i1").await.unwrap().time_tracked_all, 60);
+
+        let delete = OutboxChange::Add {
+            kind: "delete",
+            worklog_id: "w1".to_string(),
+            issue_key: "ABC-1".to_string(),
+            remote_id: Some("r-w1".to_string()),
+            payload: String::new(),
+            unsync: false,
+        };
+        storage.delete_import_batch("b1", &[delete]).await.unwrap();
+        assert!(storage.get_import_batches().await.unwrap().is_empty());
+        assert_eq!(storage.get_issues().await.unwrap().iter().map(|i| i.id.as_str()).collect::<Vec<_>>(), vec!["i1"]);
+        assert_eq!(storage.get_issue_by_id("i1").await.unwrap().time_tracked_all, 0);
+        assert!(storage.get_worklog("w1").await.is_err());
+        let queued = storage.get_outbox_items_for_worklog("w1").await.unwrap();
+        assert_eq!(queued.iter().map(|item| item.kind.as_str()).collect::<Vec<_>>(), vec!["delete"]);
+    }
+
+    #[tokio::test]
+    async fn integer_times_keeps_worklogs_of_missing_issues() {
+        let url = temp_database_url();
+        // foreign keys were off before the migration, so worklogs could lose their issue
+        let options = SqliteConnectOptions::from_str(&url).unwrap().create_if_missing(true).foreign_keys(false);
+        let conn = SqlitePoolOptions::new().connect_with(options).await.unwrap();
+        let mut before = sqlx::migrate!();
+        before.migrations = Cow::Owned(before.migrations.iter().filter(|m| m.version < INTEGER_TIMES).cloned().collect());
+        before.run(&conn).await.unwrap();
+
+        sqlx::query("insert into issue(id, name, url, summary, assignee_email, time_tracked_all) values ('i1', 'ABC-1', '', 'kept', '', '60')")
+            .execute(&conn)
+            .await
+            .unwrap();
+        sqlx::query(
+            "insert into worklog(id, issue_id, start, end, total_time)
+             values ('w1', 'i1', '100', '160', '60'), ('w2', 'gone', '200', '290', '90'), ('w3', 'gone', '300', '0', '0')",
+        )
+        .execute(&conn)
+        .await
+        .unwrap();
+        conn.close().await;
+
+        let storage = Storage::open(&url).await.unwrap();
+        let kept = storage.get_issue("ABC-1".to_string()).await.unwrap();
+        assert_eq!(kept.time_tracked_all, 60);
+        assert_eq!(storage.get_issue_worklogs("i1").await.unwrap().len(), 1);
+
+        let placeholder = storage.get_issue("missing-gone".to_string()).await.unwrap();
+        assert_eq!(placeholder.id, "gone");
+        assert_eq!(placeholder.time_tracked_all, 90);
+        let worklogs = storage.get_issue_worklogs("gone").await.unwrap();
+        assert_eq!(worklogs.iter().map(|w| (w.start, w.end, w.total_time)).collect::<Vec<_>>(), vec![(200, 290, 90), (300, 0, 0)]);
     }
 }
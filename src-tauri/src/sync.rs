@@ -0,0 +1,616 @@
+use std::{
+    str::FromStr,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use shared::{format_duration, Worklog, LOCAL_SOURCE};
+
+use crate::{
+    storage::{OutboxChange, OutboxDb, Storage},
+    tracker::{Tracker, TrackerError, WorklogReceipt},
+    MangedState,
+};
+
+const OUTBOX_POLL: Duration = Duration::from_secs(5);
+const FIRST_BACKOFF_SECONDS: u64 = 5;
+const MAX_BACKOFF_SECONDS: u64 = 30 * 60;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum OutboxKind {
+    Create,
+    Update,
+    Delete,
+}
+
+impl OutboxKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            OutboxKind::Create => "create",
+            OutboxKind::Update => "update",
+            OutboxKind::Delete => "delete",
+        }
+    }
+}
+
+impl FromStr for OutboxKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "create" => Ok(OutboxKind::Create),
+            "update" => Ok(OutboxKind::Update),
+            "delete" => Ok(OutboxKind::Delete),
+            _ => Err(format!("unknown outbox kind {}", s)),
+        }
+    }
+}
+
+/// Queues a tracker write for the worklog, the outbox task replays it once the tracker is reachable.
+pub async fn enqueue(
+    tracker: Option<&dyn Tracker>,
+    storage: &Storage,
+    kind: OutboxKind,
+    worklog: &Worklog,
+) -> Result<(), String> {
+    match outbox_change(tracker, storage, kind, worklog).await? {
+        Some(change) => storage.change_outbox(&change).await,
+        None => Ok(()),
+    }
+}
+
+/// What `enqueue` writes to the outbox, for callers writing it in one transaction with the
+/// worklog change.
+pub async fn outbox_change(
+    tracker: Option<&dyn Tracker>,
+    storage: &Storage,
+    kind: OutboxKind,
+    worklog: &Worklog,
+) -> Result<Option<OutboxChange>, String> {
+    let tracker = match tracker {
+        Some(tracker) if tracker.pushes_worklogs() => tracker,
+        // without a tracker the worklog stays unsynced until sync_worklogs queues it
+        _ => return Ok(None),
+    };
+    if worklog.synced && worklog.remote_id.is_none() {
+        // history imported without sending it, it stays local whatever happens to it
+        return Ok(None);
+    }
+
+    let issue = storage.get_issue_by_id(&worklog.issue_id).await?;
+    if issue.source != tracker.source() && issue.source != LOCAL_SOURCE {
+        return Ok(None);
+    }
+
+    let queued = storage.get_outbox_items_for_worklog(&worklog.id).await?;
+    let create_queued = queued.iter().any(|item| item.kind == OutboxKind::Create.as_str());
+
+    // shrunk below what the tracker takes, its copy goes until an edit makes the worklog long enough
+    let shrunk = kind == OutboxKind::Update && worklog.end != 0 && worklog.total_time < tracker.min_worklog_seconds();
+    let kind = match kind {
+        OutboxKind::Update if shrunk => OutboxKind::Delete,
+        kind => kind,
+    };
+
+    let kind = match kind {
+        OutboxKind::Create | OutboxKind::Update
+            if worklog.end == 0 || worklog.total_time < tracker.min_worklog_seconds() =>
+        {
+            return Ok(None);
+        }
+        OutboxKind::Create if worklog.synced || create_queued => return Ok(None),
+        // never made it to the tracker, nothing to update yet
+        OutboxKind::Update if worklog.remote_id.is_none() && !create_queued => OutboxKind::Create,
+        OutboxKind::Delete if worklog.remote_id.is_none() => {
+            // the tracker never saw it, dropping the queued writes is enough
+            return Ok(Some(OutboxChange::Drop(queued.into_iter().map(|item| item.id).collect())));
+        }
+        kind => kind,
+    };
+
+    Ok(Some(OutboxChange::Add {
+        kind: kind.as_str(),
+        worklog_id: worklog.id.clone(),
+        issue_key: issue.name,
+        remote_id: worklog.remote_id.clone(),
+        payload: serde_json::to_string(worklog).map_err(|e| e.to_string())?,
+        unsync: shrunk,
+    }))
+}
+
+/// Queues every finished worklog that is not yet in the tracker.
+pub async fn enqueue_unsynced(tracker: &dyn Tracker, storage: &Storage) -> Result<u64, String> {
+    let mut queued = 0;
+    if !tracker.pushes_worklogs() {
+        return Ok(queued);
+    }
+
+    for worklog in storage.get_unsynced_worklogs().await? {
+        if worklog.total_time < tracker.min_worklog_seconds() {
+            continue;
+        }
+
+        enqueue(Some(tracker), storage, OutboxKind::Create, &worklog).await?;
+        queued += 1;
+    }
+
+    Ok(queued)
+}
+
+/// Background task replaying the outbox in order, backs off while the tracker is unreachable.
+pub async fn run_outbox(app_state: Arc<MangedState>) {
+    loop {
+        let wait = match replay_next(&app_state).await {
+            Ok(wait) => wait,
+            Err(e) => {
+                log::error!("outbox: {}", e);
+                OUTBOX_POLL
+            }
+        };
+
+        tokio::time::sleep(wait).await;
+    }
+}
+
+async fn replay_next(app_state: &MangedState) -> Result<Duration, String> {
+    let (tracker, storage) = {
+        let m = app_state.0.lock().await;
+        match m.tracker.clone() {
+            Some(tracker) => (tracker, m.storage.clone()),
+            // queued items wait for a tracker to be configured
+            None => return Ok(OUTBOX_POLL),
+        }
+    };
+
+    let item = match storage.get_next_outbox_item().await? {
+        Some(item) => item,
+        None => return Ok(OUTBOX_POLL),
+    };
+
+    let now = now()?;
+    if item.next_attempt > now {
+        return Ok(Duration::from_secs((item.next_attempt - now) as u64).min(OUTBOX_POLL));
+    }
+
+    match replay(tracker.as_ref(), &storage, &item).await {
+        Ok(_) => {
+            storage.delete_outbox_item(item.id).await?;
+            Ok(Duration::ZERO)
+        }
+        Err(TrackerError::Unreachable(e)) => {
+            let backoff = FIRST_BACKOFF_SECONDS
+                .saturating_mul(2u64.saturating_pow(item.attempts as u32))
+                .min(MAX_BACKOFF_SECONDS);
+            storage
+                .reschedule_outbox_item(item.id, now + backoff as i64, e)
+                .await?;
+            Ok(OUTBOX_POLL)
+        }
+        Err(TrackerError::Rejected(status, body)) => {
+            log::warn!("outbox item {} rejected by the tracker with {}", item.id, status);
+            storage.fail_outbox_item(item.id, body).await?;
+            Ok(Duration::ZERO)
+        }
+    }
+}
+
+async fn replay(
+    tracker: &dyn Tracker,
+    storage: &Storage,
+    item: &OutboxDb,
+) -> Result<(), TrackerError> {
+    let kind = OutboxKind::from_str(&item.kind).map_err(|e| TrackerError::Rejected(0, e))?;
+    let worklog: Worklog =
+        serde_json::from_str(&item.payload).map_err(|e| TrackerError::Rejected(0, e.to_string()))?;
+    let remote_id = item.remote_id.clone().ok_or_else(|| {
+        TrackerError::Rejected(0, "worklog was never created in the tracker".to_string())
+    });
+
+    let receipt = match kind {
+        OutboxKind::Create => {
+            // an earlier attempt may have reached the tracker without us getting the answer
+            let existing = match item.attempts > 0 {
+                true => tracker.find_worklog(&item.issue_key, &worklog).await?,
+                false => None,
+            };
+            let receipt = match existing {
+                Some(remote_id) => WorklogReceipt::new(remote_id),
+                None => tracker.create_worklog(&item.issue_key, &worklog).await?,
+            };
+
+            storage
+                .mark_worklog_synced(&worklog.id, &receipt.remote_id)
+                .await
+                .map_err(TrackerError::Unreachable)?;
+            storage
+                .set_outbox_remote_id(&worklog.id, &receipt.remote_id)
+                .await
+                .map_err(TrackerError::Unreachable)?;
+            receipt
+        }
+        OutboxKind::Update => {
+            let receipt = tracker
+                .update_worklog(&item.issue_key, &remote_id?, &worklog)
+                .await?;
+
+            storage
+                .mark_worklog_synced(&worklog.id, &receipt.remote_id)
+                .await
+                .map_err(TrackerError::Unreachable)?;
+            receipt
+        }
+        OutboxKind::Delete => {
+            tracker
+                .delete_worklog(&item.issue_key, &remote_id?, &worklog)
+                .await?
+        }
+    };
+
+    if let Some(time_spent) = receipt.issue_time_spent {
+        reconcile_time_spent(storage, item, time_spent)
+            .await
+            .map_err(TrackerError::Unreachable)?;
+    }
+
+    Ok(())
+}
+
+/// Keeps the time the tracker has spent on the issue after a write and compares it with the time
+/// tracked here once no other write of the issue is queued. A difference, e.g. time spent in the
+/// tracker directly, is logged and returned with the local time, the local worklogs stay as they are.
+async fn reconcile_time_spent(storage: &Storage, item: &OutboxDb, time_spent: u64) -> Result<Option<u64>, String> {
+    storage.set_issue_remote_time_spent(&item.issue_key, time_spent).await?;
+    if storage.has_other_outbox_items(&item.issue_key, item.id).await? {
+        return Ok(None);
+    }
+
+    let tracked = storage.get_issue(item.issue_key.clone()).await?.time_tracked_all;
+    if tracked == time_spent {
+        return Ok(None);
+    }
+    log::warn!(
+        "{} has {} spent in the tracker but {} tracked here",
+        item.issue_key,
+        format_duration(time_spent),
+        format_duration(tracked)
+    );
+    Ok(Some(tracked))
+}
+
+pub fn now() -> Result<i64, String> {
+    match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(d) => Ok(d.as_secs() as i64),
+        Err(_) => Err("time went backwards".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use async_trait::async_trait;
+    use shared::{ImportBatch, Issue};
+
+    use super::*;
+    use crate::{storage::tests::test_storage, tests::test_state, worklogs};
+
+    /// Records the writes, answers as unreachable while `offline` is set. Creates answer with
+    /// `time_spent` as the issue's total when it is set.
+    #[derive(Default)]
+    struct FakeTracker {
+        calls: Mutex<Vec<String>>,
+        offline: Mutex<bool>,
+        min_seconds: u64,
+        time_spent: Option<u64>,
+    }
+
+    impl FakeTracker {
+        fn record(&self, call: String) -> Result<(), TrackerError> {
+            if *self.offline.lock().unwrap() {
+                return Err(TrackerError::Unreachable("offline".to_string()));
+            }
+            self.calls.lock().unwrap().push(call);
+            Ok(())
+        }
+
+        fn calls(&self) -> Vec<String> {
+            self.calls.lock().unwrap().clone()
+        }
+    }
+
+    #[async_trait]
+    impl Tracker for FakeTracker {
+        fn source(&self) -> &'static str {
+            "fake"
+        }
+
+        fn user(&self) -> &str {
+            "me"
+        }
+
+        fn default_query(&self) -> &str {
+            ""
+        }
+
+        fn min_worklog_seconds(&self) -> u64 {
+            self.min_seconds
+        }
+
+        async fn check_connection(&self) -> Result<String, TrackerError> {
+            Ok("me".to_string())
+        }
+
+        async fn get_issue(&self, key: &str) -> Result<Issue, TrackerError> {
+            Err(TrackerError::Rejected(404, key.to_string()))
+        }
+
+        async fn search_issues(&self, _query: &str) -> Result<Vec<Issue>, TrackerError> {
+            Ok(Vec::new())
+        }
+
+        async fn create_worklog(&self, issue_key: &str, worklog: &Worklog) -> Result<WorklogReceipt, TrackerError> {
+            self.record(format!("create {} {}", issue_key, worklog.id))?;
+            Ok(WorklogReceipt {
+                issue_time_spent: self.time_spent,
+                ..WorklogReceipt::new(format!("r-{}", worklog.id))
+            })
+        }
+
+        async fn update_worklog(
+            &self,
+            issue_key: &str,
+            remote_id: &str,
+            worklog: &Worklog,
+        ) -> Result<WorklogReceipt, TrackerError> {
+            self.record(format!("update {} {} {}", issue_key, remote_id, worklog.total_time))?;
+            Ok(WorklogReceipt::new(remote_id.to_string()))
+        }
+
+        async fn delete_worklog(
+            &self,
+            issue_key: &str,
+            remote_id: &str,
+            _worklog: &Worklog,
+        ) -> Result<WorklogReceipt, TrackerError> {
+            self.record(format!("delete {} {}", issue_key, remote_id))?;
+            Ok(WorklogReceipt::new(remote_id.to_string()))
+        }
+
+        async fn find_worklog(&self, issue_key: &str, worklog: &Worklog) -> Result<Option<String>, TrackerError> {
+            self.record(format!("find {} {}", issue_key, worklog.id))?;
+            Ok(None)
+        }
+    }
+
+    async fn add_issue(storage: &Storage) {
+        let issue = Issue {
+            id: "i1".to_string(),
+            name: "ABC-1".to_string(),
+            ..Issue::empty()
+        };
+        storage.add_issue(issue).await.unwrap();
+    }
+
+    fn finished(id: &str, total_time: u64) -> Worklog {
+        Worklog::new(id.to_string(), "i1".to_string(), 1000, 1000 + total_time, total_time)
+    }
+
+    async fn queued(storage: &Storage, worklog_id: &str) -> Vec<(String, Option<String>)> {
+        let items = storage.get_outbox_items_for_worklog(worklog_id).await.unwrap();
+        items.into_iter().map(|item| (item.kind, item.remote_id)).collect()
+    }
+
+    /// Replays until the outbox is empty or has to wait.
+    async fn replay_all(app_state: &MangedState) {
+        while replay_next(app_state).await.unwrap() == Duration::ZERO {}
+    }
+
+    #[tokio::test]
+    async fn writes_are_replayed_in_the_order_they_were_queued() {
+        let app_state = test_state().await;
+        let tracker = Arc::new(FakeTracker::default());
+        let storage = {
+            let mut m = app_state.0.lock().await;
+            m.tracker = Some(tracker.clone());
+            m.storage.clone()
+        };
+        add_issue(&storage).await;
+
+        let second = storage.add_worklog(finished("w2", 60)).await.unwrap();
+        let first = storage.add_worklog(finished("w1", 60)).await.unwrap();
+        enqueue(Some(tracker.as_ref()), &storage, OutboxKind::Create, &second).await.unwrap();
+        enqueue(Some(tracker.as_ref()), &storage, OutboxKind::Create, &first).await.unwrap();
+        let edited = Worklog {
+            total_time: 90,
+            ..second
+        };
+        enqueue(Some(tracker.as_ref()), &storage, OutboxKind::Update, &edited).await.unwrap();
+        assert_eq!(queued(&storage, "w2").await, vec![("create".to_string(), None), ("update".to_string(), None)]);
+
+        replay_all(&app_state).await;
+        assert_eq!(tracker.calls(), vec!["create ABC-1 w2", "create ABC-1 w1", "update ABC-1 r-w2 90"]);
+        assert!(storage.get_next_outbox_item().await.unwrap().is_none());
+        let synced = storage.get_worklog("w2").await.unwrap();
+        assert!(synced.synced);
+        assert_eq!(synced.remote_id.as_deref(), Some("r-w2"));
+    }
+
+    #[tokio::test]
+    async fn unreachable_tracker_backs_off_and_checks_before_creating_again() {
+        let app_state = test_state().await;
+        let tracker = Arc::new(FakeTracker::default());
+        let storage = {
+            let mut m = app_state.0.lock().await;
+            m.tracker = Some(tracker.clone());
+            m.storage.clone()
+        };
+        add_issue(&storage).await;
+        let worklog = storage.add_worklog(finished("w1", 60)).await.unwrap();
+        enqueue(Some(tracker.as_ref()), &storage, OutboxKind::Create, &worklog).await.unwrap();
+
+        *tracker.offline.lock().unwrap() = true;
+        let mut backoffs = Vec::new();
+        for _ in 0..3 {
+            let before = now().unwrap();
+            assert_eq!(replay_next(&app_state).await.unwrap(), OUTBOX_POLL);
+            let item = storage.get_next_outbox_item().await.unwrap().unwrap();
+            backoffs.push(item.next_attempt - before);
+            // waiting for the next attempt is not part of the test
+            storage.retry_outbox_item(item.id).await.unwrap();
+        }
+        assert!(backoffs[0] >= 5 && backoffs[0] <= 6, "{:?}", backoffs);
+        assert!(backoffs[1] >= 10 && backoffs[1] <= 11, "{:?}", backoffs);
+        assert!(backoffs[2] >= 20 && backoffs[2] <= 21, "{:?}", backoffs);
+        assert!(tracker.calls().is_empty());
+
+        *tracker.offline.lock().unwrap() = false;
+        replay_all(&app_state).await;
+        assert_eq!(tracker.calls(), vec!["find ABC-1 w1", "create ABC-1 w1"]);
+        assert!(storage.get_next_outbox_item().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn deleting_a_worklog_the_tracker_never_saw_drops_its_writes() {
+        let storage = test_storage().await;
+        let tracker = FakeTracker::default();
+        add_issue(&storage).await;
+
+        let worklog = storage.add_worklog(finished("w1", 60)).await.unwrap();
+        enqueue(Some(&tracker), &storage, OutboxKind::Create, &worklog).await.unwrap();
+        let edited = Worklog {
+            total_time: 90,
+            ..worklog
+        };
+        enqueue(Some(&tracker), &storage, OutboxKind::Update, &edited).await.unwrap();
+        assert_eq!(queued(&storage, "w1").await.len(), 2);
+
+        enqueue(Some(&tracker), &storage, OutboxKind::Delete, &edited).await.unwrap();
+        assert!(queued(&storage, "w1").await.is_empty());
+        assert!(tracker.calls().is_empty());
+    }
+
+    #[tokio::test]
+    async fn worklog_shrunk_below_the_minimum_is_deleted_in_the_tracker() {
+        let storage = test_storage().await;
+        let tracker = FakeTracker {
+            min_seconds: 60,
+            ..FakeTracker::default()
+        };
+        add_issue(&storage).await;
+        let worklog = storage.add_worklog(finished("w1", 120)).await.unwrap();
+        storage.mark_worklog_synced("w1", "r-w1").await.unwrap();
+
+        let shrunk = Worklog {
+            end: worklog.start + 30,
+            total_time: 30,
+            remote_id: Some("r-w1".to_string()),
+            synced: true,
+            ..worklog
+        };
+        enqueue(Some(&tracker), &storage, OutboxKind::Update, &shrunk).await.unwrap();
+        assert_eq!(queued(&storage, "w1").await, vec![("delete".to_string(), Some("r-w1".to_string()))]);
+        let stored = storage.get_worklog("w1").await.unwrap();
+        assert!(!stored.synced);
+        assert_eq!(stored.remote_id, None);
+
+        // long enough again, the tracker gets a new worklog
+        let grown = Worklog {
+            end: stored.start + 90,
+            total_time: 90,
+            ..stored
+        };
+        enqueue(Some(&tracker), &storage, OutboxKind::Update, &grown).await.unwrap();
+        assert_eq!(
+            queued(&storage, "w1").await,
+            vec![("delete".to_string(), Some("r-w1".to_string())), ("create".to_string(), None)]
+        );
+    }
+
+    #[tokio::test]
+    async fn imported_history_is_not_sent_when_edited() {
+        let app_state = test_state().await;
+        let tracker = Arc::new(FakeTracker {
+            min_seconds: 60,
+            ..FakeTracker::default()
+        });
+        let mut m = app_state.0.lock().await;
+        m.tracker = Some(tracker.clone());
+        add_issue(&m.storage).await;
+        let batch = ImportBatch {
+            id: "b1".to_string(),
+            created: 100,
+            source: "hours.csv".to_string(),
+            worklogs: 1,
+            issues: 0,
+        };
+        // imported without sending it to the tracker
+        let imported = Worklog {
+            synced: true,
+            ..finished("w1", 120)
+        };
+        m.storage.add_import_batch(&batch, &[], std::slice::from_ref(&imported)).await.unwrap();
+
+        let edited = Worklog {
+            end: imported.start + 90,
+            total_time: 90,
+            ..imported
+        };
+        worklogs::save_edit(&m, edited).await.unwrap();
+        assert!(queued(&m.storage, "w1").await.is_empty());
+
+        // below the minimum, there is no copy in the tracker to delete either
+        let shrunk = Worklog {
+            end: imported.start + 30,
+            total_time: 30,
+            ..m.storage.get_worklog("w1").await.unwrap()
+        };
+        worklogs::save_edit(&m, shrunk).await.unwrap();
+        assert!(queued(&m.storage, "w1").await.is_empty());
+        assert!(m.storage.get_worklog("w1").await.unwrap().synced);
+    }
+
+    #[tokio::test]
+    async fn time_spent_in_the_tracker_is_compared_once_nothing_of_the_issue_is_queued() {
+        let storage = test_storage().await;
+        let tracker = FakeTracker::default();
+        add_issue(&storage).await;
+        for (id, total_time) in [("w1", 600), ("w2", 1200)] {
+            let worklog = storage.add_worklog(finished(id, total_time)).await.unwrap();
+            enqueue(Some(&tracker), &storage, OutboxKind::Create, &worklog).await.unwrap();
+        }
+        let first = storage.get_outbox_items_for_worklog("w1").await.unwrap().remove(0);
+        let second = storage.get_outbox_items_for_worklog("w2").await.unwrap().remove(0);
+
+        // w2 is still on its way
+        assert_eq!(reconcile_time_spent(&storage, &first, 600).await, Ok(None));
+        storage.delete_outbox_item(first.id).await.unwrap();
+
+        assert_eq!(reconcile_time_spent(&storage, &second, 1800).await, Ok(None));
+        // someone spent a quarter of an hour on the issue in the tracker
+        assert_eq!(reconcile_time_spent(&storage, &second, 2700).await, Ok(Some(1800)));
+        let issue = storage.get_issue("ABC-1".to_string()).await.unwrap();
+        assert_eq!((issue.remote_time_spent, issue.time_tracked_all), (2700, 1800));
+    }
+
+    #[tokio::test]
+    async fn replayed_writes_keep_the_time_spent_in_the_tracker() {
+        let app_state = test_state().await;
+        let tracker = Arc::new(FakeTracker {
+            time_spent: Some(2700),
+            ..FakeTracker::default()
+        });
+        let storage = {
+            let mut m = app_state.0.lock().await;
+            m.tracker = Some(tracker.clone());
+            m.storage.clone()
+        };
+        add_issue(&storage).await;
+        let worklog = storage.add_worklog(finished("w1", 1800)).await.unwrap();
+        enqueue(Some(tracker.as_ref()), &storage, OutboxKind::Create, &worklog).await.unwrap();
+
+        replay_all(&app_state).await;
+
+        assert!(queued(&storage, "w1").await.is_empty());
+        assert_eq!(storage.get_issue("ABC-1".to_string()).await.unwrap().remote_time_spent, 2700);
+    }
+}
@@ -0,0 +1,236 @@
+use std::sync::Arc;
+
+use shared::{format_duration, DailyTotal, Worklog};
+use tauri::{AppHandle, State};
+use tokio::sync::MutexGuard;
+
+use crate::{events, sync, worklogs, AppState, MangedState};
+
+// time added to an empty day starts here
+const WORKDAY_START: u64 = 9 * 3600;
+
+/// Time per issue and local day for the grid, `from` and `to` are unix seconds.
+#[tauri::command(async)]
+pub async fn get_timesheet(
+    from: u64,
+    to: u64,
+    app_state: State<'_, Arc<MangedState>>,
+) -> Result<Vec<DailyTotal>, String> {
+    let m = app_state.0.lock().await;
+    m.storage.get_daily_totals(from, to).await
+}
+
+/// Makes the issue's finished time in the day `[day_start, day_end)` add up to `seconds`.
+/// More time is a new worklog in a free part of the day, less time is cut from the end of the
+/// issue's latest worklogs. The value is taken as typed, without rounding.
+#[tauri::command(async)]
+pub async fn set_timesheet_cell(
+    name: String,
+    day_start: u64,
+    day_end: u64,
+    seconds: u64,
+    app_state: State<'_, Arc<MangedState>>,
+    app_handle: AppHandle,
+) -> Result<(), String> {
+    let m = app_state.0.lock().await;
+    let res = set_cell(&m, name, day_start, day_end, seconds).await;
+    events::issues_changed(&app_handle);
+    res
+}
+
+async fn set_cell(m: &MutexGuard<'_, AppState>, name: String, day_start: u64, day_end: u64, seconds: u64) -> Result<(), String> {
+    let issue = m.storage.get_issue(name).await?;
+    let day = m.storage.get_worklogs_between(day_start, day_end).await?;
+    let mut logged: Vec<_> = day
+        .iter()
+        .filter(|worklog| worklog.issue_id == issue.id && worklog.end != 0)
+        .cloned()
+        .collect();
+    let current: u64 = logged.iter().map(|worklog| worklog.total_time).sum();
+
+    if seconds > current {
+        let missing = seconds - current;
+        check_tracker_minimum(m, missing)?;
+
+        // time can't be added to the future, nor to the worklog being tracked
+        let limit = day_end.min(sync::now()? as u64);
+        let tracked = m.tracing_info.as_ref().map(|info| info.current_worklog.id.as_str());
+        let busy: Vec<(u64, u64)> = day
+            .iter()
+            .filter_map(|worklog| match worklog.end {
+                0 if Some(worklog.id.as_str()) == tracked => Some((worklog.start, limit)),
+                0 => None,
+                end => Some((worklog.start, end)),
+            })
+            .collect();
+        let (start, end) = free_span(&busy, day_start, limit, missing)
+            .ok_or_else(|| "the day has no room for that much time".to_string())?;
+
+        worklogs::check_span(start, end)?;
+        worklogs::check_overlap(m, "", start, end).await?;
+        worklogs::insert(m, issue.id, start, end, missing).await?;
+        return Ok(());
+    }
+
+    // worked out before anything changes, so a refused edit leaves the day as it was
+    let mut extra = current - seconds;
+    let mut removed = Vec::new();
+    let mut shortened = None;
+    logged.sort_by_key(|worklog| worklog.start);
+    while extra > 0 {
+        let worklog = match logged.pop() {
+            Some(worklog) => worklog,
+            None => break,
+        };
+        if worklog.total_time <= extra {
+            extra -= worklog.total_time;
+            removed.push(worklog);
+        } else {
+            // the span follows the total, a rounded total could have left them apart
+            let total_time = worklog.total_time - extra;
+            let end = worklog.start + total_time;
+            worklogs::check_span(worklog.start, end)?;
+            worklogs::check_overlap(m, &worklog.id, worklog.start, end).await?;
+            check_tracker_minimum(m, total_time)?;
+            shortened = Some(Worklog { end, total_time, ..worklog });
+            extra = 0;
+        }
+    }
+
+    for worklog in removed {
+        worklogs::remove(m, &worklog).await?;
+    }
+    if let Some(worklog) = shortened {
+        worklogs::save_edit(m, worklog).await?;
+    }
+    Ok(())
+}
+
+/// Refuses a worklog too short for the tracker, it would never get there.
+fn check_tracker_minimum(m: &MutexGuard<'_, AppState>, seconds: u64) -> Result<(), String> {
+    let min = match &m.tracker {
+        Some(tracker) if tracker.pushes_worklogs() => tracker.min_worklog_seconds(),
+        _ => 0,
+    };
+    match seconds < min {
+        true => Err(format!("the tracker takes worklogs of at least {}", format_duration(min))),
+        false => Ok(()),
+    }
+}
+
+/// Where `seconds` fit in `[day_start, limit)` next to the `busy` spans: right after the last
+/// one, or at the start of the workday when there is none, and otherwise at the end of the
+/// latest gap long enough.
+fn free_span(busy: &[(u64, u64)], day_start: u64, limit: u64, seconds: u64) -> Option<(u64, u64)> {
+    let mut busy = busy.to_vec();
+    busy.sort();
+
+    let after = busy.iter().map(|(_, end)| *end).max().unwrap_or(day_start + WORKDAY_START);
+    if after + seconds <= limit {
+        return Some((after, after + seconds));
+    }
+
+    let mut gaps = Vec::new();
+    let mut free_from = day_start;
+    for (start, end) in busy {
+        if start > free_from {
+            gaps.push((free_from, start.min(limit)));
+        }
+        free_from = free_from.max(end);
+    }
+    gaps.push((free_from, limit));
+
+    gaps.into_iter()
+        .rev()
+        .find(|(start, end)| *end >= *start + seconds)
+        .map(|(_, end)| (end - seconds, end))
+}
+
+#[cfg(test)]
+mod tests {
+    use shared::Issue;
+
+    use super::*;
+    use crate::tests::test_state;
+
+    const DAY: u64 = 1714521600; // 2024-05-01, midnight
+
+    async fn add_issue(m: &MutexGuard<'_, AppState>) {
+        let issue = Issue {
+            id: "i1".to_string(),
+            name: "ABC-1".to_string(),
+            ..Issue::empty()
+        };
+        m.storage.add_issue(issue).await.unwrap();
+    }
+
+    /// Start, end and total of the worklogs of the issue.
+    async fn spans(m: &MutexGuard<'_, AppState>) -> Vec<(u64, u64, u64)> {
+        let worklogs = m.storage.get_issue_worklogs("i1").await.unwrap();
+        worklogs.into_iter().map(|worklog| (worklog.start, worklog.end, worklog.total_time)).collect()
+    }
+
+    #[tokio::test]
+    async fn time_added_to_a_past_day_follows_its_last_worklog() {
+        let app_state = test_state().await;
+        let m = app_state.0.lock().await;
+        add_issue(&m).await;
+
+        set_cell(&m, "ABC-1".to_string(), DAY, DAY + 86400, 3600).await.unwrap();
+        assert_eq!(spans(&m).await, vec![(DAY + 9 * 3600, DAY + 10 * 3600, 3600)]);
+
+        set_cell(&m, "ABC-1".to_string(), DAY, DAY + 86400, 5400).await.unwrap();
+        assert_eq!(
+            spans(&m).await,
+            vec![(DAY + 9 * 3600, DAY + 10 * 3600, 3600), (DAY + 10 * 3600, DAY + 10 * 3600 + 1800, 1800)]
+        );
+    }
+
+    #[tokio::test]
+    async fn time_added_to_today_ends_by_now() {
+        let app_state = test_state().await;
+        let m = app_state.0.lock().await;
+        add_issue(&m).await;
+        let now = sync::now().unwrap() as u64;
+        let day_start = now - 3 * 3600;
+        // finished a minute ago, half an hour does not fit after it
+        m.storage.add_worklog(Worklog::new("w1".to_string(), "i1".to_string(), now - 1800, now - 60, 1740)).await.unwrap();
+
+        set_cell(&m, "ABC-1".to_string(), day_start, day_start + 86400, 1740 + 1800).await.unwrap();
+        assert_eq!(spans(&m).await, vec![(now - 3600, now - 1800, 1800), (now - 1800, now - 60, 1740)]);
+    }
+
+    #[tokio::test]
+    async fn lowered_time_keeps_the_span_and_total_of_a_rounded_worklog_together() {
+        let app_state = test_state().await;
+        let m = app_state.0.lock().await;
+        add_issue(&m).await;
+        // 10 minutes rounded up to 15
+        let start = DAY + 10 * 3600;
+        m.storage.add_worklog(Worklog::new("w1".to_string(), "i1".to_string(), start, start + 600, 900)).await.unwrap();
+
+        set_cell(&m, "ABC-1".to_string(), DAY, DAY + 86400, 720).await.unwrap();
+        assert_eq!(spans(&m).await, vec![(start, start + 720, 720)]);
+    }
+
+    #[tokio::test]
+    async fn lowered_time_is_refused_when_the_span_runs_into_another_worklog() {
+        let app_state = test_state().await;
+        let m = app_state.0.lock().await;
+        add_issue(&m).await;
+        let other = Issue {
+            id: "i2".to_string(),
+            name: "ABC-2".to_string(),
+            ..Issue::empty()
+        };
+        m.storage.add_issue(other).await.unwrap();
+        let start = DAY + 10 * 3600;
+        m.storage.add_worklog(Worklog::new("w1".to_string(), "i1".to_string(), start, start + 600, 900)).await.unwrap();
+        m.storage.add_worklog(Worklog::new("w2".to_string(), "i2".to_string(), start + 600, start + 1200, 600)).await.unwrap();
+
+        // 14 minutes from the start run into the other issue's worklog
+        let refused = set_cell(&m, "ABC-1".to_string(), DAY, DAY + 86400, 840).await;
+        assert_eq!(refused, Err("the time overlaps a worklog of ABC-2".to_string()));
+        assert_eq!(spans(&m).await, vec![(start, start + 600, 900)]);
+    }
+}
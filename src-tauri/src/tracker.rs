@@ -0,0 +1,291 @@
+use std::{collections::HashSet, fmt::Display, sync::Arc};
+
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use shared::{Issue, TrackerConfig, Worklog};
+use tauri::{
+    api::http::{Client, HttpRequestBuilder, ResponseType},
+    AppHandle, Error, State,
+};
+
+use crate::{events, github::setup_github, gitlab::setup_gitlab, jira::setup_jira, MangedState};
+
+pub const NO_TRACKER: &str = "no issue tracker is configured";
+
+#[derive(Debug)]
+pub enum TrackerError {
+    /// the tracker could not be reached or had a server side problem, worth retrying later
+    Unreachable(String),
+    /// the tracker refused the request, retrying the same request won't help
+    Rejected(u16, String),
+}
+
+impl Display for TrackerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TrackerError::Unreachable(e) => write!(f, "tracker is unreachable: {}", e),
+            TrackerError::Rejected(status, body) => write!(f, "tracker returned {}: {}", status, body),
+        }
+    }
+}
+
+/// What the tracker answered to a worklog write.
+pub struct WorklogReceipt {
+    pub remote_id: String,
+    /// Time logged on the whole issue in the tracker, when the tracker reports it back.
+    pub issue_time_spent: Option<u64>,
+}
+
+impl WorklogReceipt {
+    pub fn new(remote_id: String) -> Self {
+        Self {
+            remote_id,
+            issue_time_spent: None,
+        }
+    }
+}
+
+/// Issue tracker the issues are imported from and the worklogs are pushed to.
+#[async_trait]
+pub trait Tracker: Send + Sync {
+    /// Stored in `Issue.source` of the issues imported from this tracker.
+    fn source(&self) -> &'static str;
+
+    /// User the issues are assigned to.
+    fn user(&self) -> &str;
+
+    /// Query used when importing without an explicit one.
+    fn default_query(&self) -> &str;
+
+    /// Whether finished worklogs are pushed to the tracker at all.
+    fn pushes_worklogs(&self) -> bool {
+        true
+    }
+
+    /// Shorter worklogs are not pushed.
+    fn min_worklog_seconds(&self) -> u64 {
+        0
+    }
+
+    /// Checks the url and credentials work, answers with the name of the user they belong to.
+    async fn check_connection(&self) -> Result<String, TrackerError>;
+
+    async fn get_issue(&self, key: &str) -> Result<Issue, TrackerError>;
+
+    async fn search_issues(&self, query: &str) -> Result<Vec<Issue>, TrackerError>;
+
+    /// Creates the worklog, the receipt has the id the tracker assigned to it.
+    async fn create_worklog(&self, issue_key: &str, worklog: &Worklog) -> Result<WorklogReceipt, TrackerError>;
+
+    /// Updates the worklog, the receipt has the id to use from now on.
+    async fn update_worklog(
+        &self,
+        issue_key: &str,
+        remote_id: &str,
+        worklog: &Worklog,
+    ) -> Result<WorklogReceipt, TrackerError>;
+
+    async fn delete_worklog(
+        &self,
+        issue_key: &str,
+        remote_id: &str,
+        worklog: &Worklog,
+    ) -> Result<WorklogReceipt, TrackerError>;
+
+    /// Looks for an already created worklog matching this one, used to not create a
+    /// worklog twice when a create went through but its answer got lost.
+    async fn find_worklog(
+        &self,
+        issue_key: &str,
+        worklog: &Worklog,
+    ) -> Result<Option<String>, TrackerError>;
+}
+
+/// Sets up the configured tracker, `None` when the issues are only kept locally.
+pub fn setup_tracker(config: Option<&TrackerConfig>) -> Result<Option<Arc<dyn Tracker>>, Error> {
+    let tracker: Arc<dyn Tracker> = match config {
+        Some(TrackerConfig::Jira(jira)) => Arc::new(setup_jira(jira)?),
+        Some(TrackerConfig::Github(github)) => Arc::new(setup_github(github)?),
+        Some(TrackerConfig::Gitlab(gitlab)) => Arc::new(setup_gitlab(gitlab)?),
+        None => return Ok(None),
+    };
+
+    Ok(Some(tracker))
+}
+
+/// Sends the request and classifies the failures, answers without a body come back as `Null`.
+pub async fn execute(client: &Client, request: HttpRequestBuilder) -> Result<Value, TrackerError> {
+    // read as text, deletes answer with an empty body
+    let request = request.response_type(ResponseType::Text);
+    let response = client
+        .send(request)
+        .await
+        .map_err(|err| TrackerError::Unreachable(err.to_string()))?
+        .read()
+        .await
+        .map_err(|err| TrackerError::Unreachable(err.to_string()))?;
+
+    let text = response.data.as_str().unwrap_or_default();
+    let data = match text.is_empty() {
+        true => Value::Null,
+        false => serde_json::from_str(text).unwrap_or_else(|_| Value::String(text.to_string())),
+    };
+
+    match response.status {
+        429 | 500..=599 => Err(TrackerError::Unreachable(format!("{}: {}", response.status, data))),
+        400..=499 => Err(TrackerError::Rejected(response.status, data.to_string())),
+        _ => Ok(data),
+    }
+}
+
+pub fn parse<T: DeserializeOwned>(data: Value) -> Result<T, TrackerError> {
+    serde_json::from_value(data)
+        .map_err(|err| TrackerError::Rejected(0, format!("unexpected tracker response: {}", err)))
+}
+
+#[tauri::command(async)]
+pub async fn get_tracker_issue(
+    name: String,
+    app_state: State<'_, Arc<MangedState>>,
+) -> Result<Issue, String> {
+    let tracker = app_state.0.lock().await.tracker.clone().ok_or(NO_TRACKER)?;
+    tracker.get_issue(&name).await.map_err(|err| err.to_string())
+}
+
+/// Imports the issues matching the query, issues the query no longer returns are marked stale.
+#[tauri::command(async)]
+pub async fn import_issues(
+    query: Option<String>,
+    app_state: State<'_, Arc<MangedState>>,
+    app_handle: AppHandle,
+) -> Result<Vec<Issue>, String> {
+    let (tracker, storage) = {
+        let m = app_state.0.lock().await;
+        (m.tracker.clone().ok_or(NO_TRACKER)?, m.storage.clone())
+    };
+
+    let query = query
+        .filter(|query| !query.trim().is_empty())
+        .unwrap_or(tracker.default_query().to_string());
+    let found = tracker
+        .search_issues(&query)
+        .await
+        .map_err(|err| err.to_string())?;
+
+    let mut imported = Vec::new();
+    for issue in found {
+        imported.push(storage.upsert_issue(issue).await?);
+    }
+
+    let keys: HashSet<&str> = imported.iter().map(|issue| issue.name.as_str()).collect();
+    for issue in storage.get_issues().await? {
+        if issue.source == tracker.source() && !issue.stale && !keys.contains(issue.name.as_str()) {
+            storage.set_issue_stale(&issue.id, true).await?;
+        }
+    }
+
+    events::issues_changed(&app_handle);
+    Ok(imported)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use serde_json::Value;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+    };
+
+    #[derive(Clone, Debug)]
+    pub struct MockRequest {
+        pub method: String,
+        pub path: String, // with the query
+        pub body: Value,
+    }
+
+    type Handler = dyn Fn(&MockRequest) -> (u16, Value) + Send + Sync;
+
+    /// A tracker api on the loopback interface, the handler answers every request.
+    pub struct MockServer {
+        pub url: String,
+        requests: Arc<Mutex<Vec<MockRequest>>>,
+    }
+
+    impl MockServer {
+        pub async fn start(handler: impl Fn(&MockRequest) -> (u16, Value) + Send + Sync + 'static) -> MockServer {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let url = format!("http://{}", listener.local_addr().unwrap());
+            let requests = Arc::new(Mutex::new(Vec::new()));
+            let handler: Arc<Handler> = Arc::new(handler);
+
+            let received = requests.clone();
+            tokio::spawn(async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    tokio::spawn(serve(stream, handler.clone(), received.clone()));
+                }
+            });
+
+            MockServer { url, requests }
+        }
+
+        pub fn requests(&self) -> Vec<MockRequest> {
+            self.requests.lock().unwrap().clone()
+        }
+    }
+
+    async fn serve(mut stream: TcpStream, handler: Arc<Handler>, requests: Arc<Mutex<Vec<MockRequest>>>) {
+        let mut read = Vec::new();
+        let mut buffer = [0; 4096];
+        let (head, length) = loop {
+            let n = stream.read(&mut buffer).await.unwrap();
+            if n == 0 {
+                return;
+            }
+            read.extend_from_slice(&buffer[..n]);
+            let text = String::from_utf8_lossy(&read);
+            if let Some(end) = text.find("\r\n\r\n") {
+                let head = text[..end].to_string();
+                let length = head
+                    .lines()
+                    .find_map(|line| {
+                        let (name, value) = line.split_once(':')?;
+                        name.eq_ignore_ascii_case("content-length").then(|| value.trim().parse::<usize>().ok())?
+                    })
+                    .unwrap_or(0);
+                break (head, end + 4 + length);
+            }
+        };
+        while read.len() < length {
+            let n = stream.read(&mut buffer).await.unwrap();
+            if n == 0 {
+                break;
+            }
+            read.extend_from_slice(&buffer[..n]);
+        }
+
+        let mut request_line = head.lines().next().unwrap_or_default().split(' ');
+        let body = &read[head.len() + 4..];
+        let request = MockRequest {
+            method: request_line.next().unwrap_or_default().to_string(),
+            path: request_line.next().unwrap_or_default().to_string(),
+            body: serde_json::from_slice(body).unwrap_or(Value::Null),
+        };
+        let (status, answer) = handler(&request);
+        requests.lock().unwrap().push(request);
+
+        let answer = match answer {
+            Value::Null => String::new(),
+            answer => answer.to_string(),
+        };
+        let response = format!(
+            "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            answer.len(),
+            answer
+        );
+        let _ = stream.write_all(response.as_bytes()).await;
+    }
+}
@@ -0,0 +1,300 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use chrono::Local;
+use shared::TrayTick;
+use tauri::{AppHandle, CustomMenuItem, Manager, State, SystemTrayEvent, SystemTrayMenu, SystemTrayMenuItem};
+
+use crate::{
+    commands::{exit_save_process, start_tracking, stop_tracking, toggle_pause, MAX_RECENT},
+    events::{issues_changed, tracking_changed, TRACKING_STARTED, TRACKING_STOPPED, TRACKING_TICK},
+    storage::Storage,
+    AppState, MangedState,
+};
+
+const NOT_TRACKING: &str = "Not tracking";
+
+/// Today's total of the finished worklogs, so the clock doesn't ask the storage every tick. It is
+/// read again on another day or after `forget_today_total`.
+static TODAY_TOTAL: Mutex<Option<TodayTotal>> = Mutex::new(None);
+
+#[derive(Clone, Copy)]
+struct TodayTotal {
+    day_start: u64,
+    seconds: u64,
+}
+
+// the recent issues are the items "0" to "4", the free ones keep a placeholder
+fn placeholder(i: usize) -> String {
+    format!("Recent Issue: {}", i)
+}
+
+pub fn set_up_tray(recent_issues: &VecDeque<String>) -> SystemTrayMenu {
+    let mut tray_menu = SystemTrayMenu::new()
+        .add_item(CustomMenuItem::new("quit".to_string(), "Quit"))
+        .add_native_item(SystemTrayMenuItem::Separator)
+        .add_item(CustomMenuItem::new("hide".to_string(), "Hide"))
+        .add_item(CustomMenuItem::new("show".to_string(), "Show"))
+        .add_native_item(SystemTrayMenuItem::Separator)
+        .add_item(CustomMenuItem::new("current".to_string(), NOT_TRACKING).disabled())
+        .add_item(CustomMenuItem::new("pause".to_string(), "Pause").disabled())
+        .add_item(CustomMenuItem::new("stop".to_string(), "Stop tracking").disabled())
+        .add_native_item(SystemTrayMenuItem::Separator);
+
+    for i in 0..MAX_RECENT {
+        let item = match recent_issues.get(i) {
+            Some(issue) => CustomMenuItem::new(i.to_string(), issue.clone()),
+            None => CustomMenuItem::new(i.to_string(), placeholder(i)).disabled(),
+        };
+        tray_menu = tray_menu.add_item(item);
+    }
+
+    tray_menu
+}
+
+/// Brings every tray item in line with the state, after any change to what is tracked.
+fn refresh_tray(app: &AppHandle, m: &AppState) {
+    let tray = app.tray_handle();
+
+    let mut results = Vec::new();
+    for i in 0..MAX_RECENT {
+        let item = tray.get_item(&i.to_string());
+        match m.recent_issues.get(i) {
+            Some(issue) => {
+                results.push(item.set_title(issue));
+                results.push(item.set_enabled(true));
+            }
+            None => {
+                results.push(item.set_title(placeholder(i)));
+                results.push(item.set_enabled(false));
+            }
+        }
+    }
+
+    let (current, paused) = match &m.tracing_info {
+        Some(info) => {
+            let minutes = info.elapsed().as_secs() / 60;
+            let title = format!("Currently: {} {:02}:{:02}", info.time_tracing_issue.name, minutes / 60, minutes % 60);
+            (title, Some(info.paused))
+        }
+        None => (NOT_TRACKING.to_string(), None),
+    };
+    results.push(tray.get_item("current").set_title(current));
+    results.push(tray.get_item("stop").set_enabled(paused.is_some()));
+    results.push(tray.get_item("pause").set_enabled(paused.is_some()));
+    results.push(tray.get_item("pause").set_title(if paused == Some(true) { "Resume" } else { "Pause" }));
+
+    if let Some(Err(e)) = results.into_iter().find(|res| res.is_err()) {
+        log::warn!("tray can't be updated: {}", e);
+    }
+}
+
+/// `refresh_tray` plus the tray title and tooltip, they show today's total while nothing is tracked.
+pub async fn sync_tray(app: &AppHandle, app_state: &MangedState) {
+    let m = app_state.0.lock().await;
+    refresh_tray(app, &m);
+
+    let label = match &m.tracing_info {
+        Some(info) => format!(
+            "{} {}{}",
+            info.time_tracing_issue.name,
+            clock(info.elapsed().as_secs(), m.config.tray.tick),
+            if info.paused { " (paused)" } else { "" }
+        ),
+        None => match today_total(&m.storage, start_of_today()).await {
+            Ok(today) => format!("Today {}", clock(today, TrayTick::Minute)),
+            Err(e) => {
+                log::warn!("today's total is unknown: {}", e);
+                String::new()
+            }
+        },
+    };
+    set_tray_label(app, &label, m.tracing_info.is_some());
+}
+
+/// Background task moving the time in the tray on, the window is usually hidden.
+pub async fn run_tray_clock(app_state: Arc<MangedState>, app: AppHandle) {
+    loop {
+        sync_tray(&app, &app_state).await;
+
+        let wait = match app_state.0.lock().await.config.tray.tick {
+            TrayTick::Minute => Duration::from_secs(60),
+            TrayTick::Second => Duration::from_secs(1),
+        };
+        tokio::time::sleep(wait).await;
+    }
+}
+
+// title next to the icon on macOS, tooltip elsewhere. Linux has neither, the "Currently" item
+// has the time while tracking and shows today's total in place of "Not tracking"
+fn set_tray_label(app: &AppHandle, label: &str, tracking: bool) {
+    let tray = app.tray_handle();
+
+    #[cfg(target_os = "macos")]
+    if let Err(e) = tray.set_title(label) {
+        log::warn!("tray title can't be set: {}", e);
+    }
+    #[cfg(not(target_os = "linux"))]
+    if let Err(e) = tray.set_tooltip(label) {
+        log::warn!("tray tooltip can't be set: {}", e);
+    }
+    #[cfg(not(target_os = "linux"))]
+    let _ = tracking;
+    #[cfg(target_os = "linux")]
+    if !tracking && !label.is_empty() {
+        if let Err(e) = tray.get_item("current").set_title(label) {
+            log::warn!("tray can't be updated: {}", e);
+        }
+    }
+}
+
+fn clock(seconds: u64, tick: TrayTick) -> String {
+    match tick {
+        TrayTick::Minute => format!("{}:{:02}", seconds / 3600, (seconds % 3600) / 60),
+        TrayTick::Second => format!("{}:{:02}:{:02}", seconds / 3600, (seconds % 3600) / 60, seconds % 60),
+    }
+}
+
+/// Drops the cached total of today, the finished worklogs changed.
+pub fn forget_today_total() {
+    *TODAY_TOTAL.lock().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+async fn today_total(storage: &Storage, day_start: u64) -> Result<u64, String> {
+    let cached = *TODAY_TOTAL.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(total) = cached.filter(|total| total.day_start == day_start) {
+        return Ok(total.seconds);
+    }
+
+    let seconds = storage.get_tracked_since(day_start).await?;
+    *TODAY_TOTAL.lock().unwrap_or_else(|e| e.into_inner()) = Some(TodayTotal { day_start, seconds });
+    Ok(seconds)
+}
+
+fn start_of_today() -> u64 {
+    Local::now()
+        .date_naive()
+        .and_hms_opt(0, 0, 0)
+        .and_then(|midnight| midnight.and_local_timezone(Local).earliest())
+        .map(|midnight| midnight.timestamp() as u64)
+        .unwrap_or_default()
+}
+
+pub fn handle_tray_event(app: &AppHandle, event: SystemTrayEvent) {
+    if let SystemTrayEvent::MenuItemClick { id, .. } = event {
+        match id.as_str() {
+            "quit" => {
+                let s: State<Arc<MangedState>> = app.state();
+                exit_save_process(s.inner().clone()).expect("Could not save the progress");
+                log::info!("closing the app from the tray");
+                app.exit(0);
+            }
+            "hide" => {
+                let window = app.get_window("main").unwrap();
+
+                match window.hide() {
+                    Ok(_) => log::debug!("window hidden"),
+                    Err(e) => log::error!("window cannot be hidden: {}", e),
+                }
+            }
+            "show" => {
+                let window = app.get_window("main").unwrap();
+
+                match window.show() {
+                    Ok(_) => log::debug!("window shown"),
+                    Err(e) => log::error!("window cannot be shown: {}", e),
+                }
+            }
+            "pause" => {
+                let app = app.clone();
+                tokio::spawn(async move {
+                    let s: State<Arc<MangedState>> = app.state();
+                    if let Err(e) = toggle_pause(&s).await {
+                        log::error!("cannot pause: {}", e);
+                    }
+                    tracking_changed(&app, &s, TRACKING_TICK).await;
+                });
+            }
+            "stop" => {
+                let app = app.clone();
+                tokio::spawn(async move {
+                    let s: State<Arc<MangedState>> = app.state();
+                    if let Err(e) = stop_tracking(&s).await {
+                        log::error!("cannot stop tracking: {}", e);
+                    }
+                    tracking_changed(&app, &s, TRACKING_STOPPED).await;
+                });
+            }
+            recent => {
+                let i = match recent.parse::<usize>() {
+                    Ok(i) => i,
+                    Err(_) => return,
+                };
+                let app = app.clone();
+                tokio::spawn(async move {
+                    let s: State<Arc<MangedState>> = app.state();
+                    let name = match s.0.lock().await.recent_issues.get(i) {
+                        Some(name) => name.clone(),
+                        None => return,
+                    };
+
+                    // same as starting from the window, the tracked worklog is finished first
+                    if let Err(e) = stop_tracking(&s).await {
+                        log::error!("cannot start tracking: {}", e);
+                        return;
+                    }
+                    match start_tracking(name, &s).await {
+                        Ok(()) => {
+                            // the stop changed the time tracked on the previous issue
+                            issues_changed(&app);
+                            tracking_changed(&app, &s, TRACKING_STARTED).await;
+                        }
+                        Err(e) => {
+                            log::error!("cannot start tracking: {}", e);
+                            tracking_changed(&app, &s, TRACKING_STOPPED).await;
+                        }
+                    }
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use shared::{Issue, Worklog};
+
+    use super::*;
+    use crate::storage::tests::test_storage;
+
+    const DAY: u64 = 1714521600; // 2024-05-01, midnight
+
+    #[tokio::test]
+    async fn today_total_is_read_again_once_forgotten_or_on_another_day() {
+        let storage = test_storage().await;
+        let issue = Issue {
+            id: "i1".to_string(),
+            name: "ABC-1".to_string(),
+            ..Issue::empty()
+        };
+        storage.add_issue(issue).await.unwrap();
+        let add = |id: &str, start: u64, seconds: u64| {
+            storage.add_worklog(Worklog::new(id.to_string(), "i1".to_string(), start, start + seconds, seconds))
+        };
+        add("w1", DAY + 3600, 1800).await.unwrap();
+        forget_today_total();
+
+        assert_eq!(today_total(&storage, DAY).await, Ok(1800));
+        add("w2", DAY + 7200, 600).await.unwrap();
+        assert_eq!(today_total(&storage, DAY).await, Ok(1800));
+
+        forget_today_total();
+        assert_eq!(today_total(&storage, DAY).await, Ok(2400));
+
+        add("w3", DAY + 86400, 900).await.unwrap();
+        assert_eq!(today_total(&storage, DAY + 86400).await, Ok(900));
+    }
+}
@@ -0,0 +1,212 @@
+use std::sync::Arc;
+
+use shared::{TotalDrift, Worklog};
+use tauri::{AppHandle, State};
+use tokio::sync::MutexGuard;
+use uuid::Uuid;
+
+use crate::{events, sync, AppState, MangedState};
+
+/// Every worklog of the issue, the running one included, oldest first.
+#[tauri::command(async)]
+pub async fn get_issue_worklogs(name: String, app_state: State<'_, Arc<MangedState>>) -> Result<Vec<Worklog>, String> {
+    let m = app_state.0.lock().await;
+    let issue = m.storage.get_issue(name).await?;
+    m.storage.get_issue_worklogs(&issue.id).await
+}
+
+/// Worklogs of all issues started in `[from, to)`, in unix seconds.
+#[tauri::command(async)]
+pub async fn get_worklogs_between(
+    from: u64,
+    to: u64,
+    app_state: State<'_, Arc<MangedState>>,
+) -> Result<Vec<Worklog>, String> {
+    let m = app_state.0.lock().await;
+    m.storage.get_worklogs_between(from, to).await
+}
+
+/// Moves the start and end of a finished worklog, the tracker gets the change through the outbox.
+#[tauri::command(async)]
+pub async fn edit_worklog(
+    id: String,
+    start: u64,
+    end: u64,
+    app_state: State<'_, Arc<MangedState>>,
+    app_handle: AppHandle,
+) -> Result<Worklog, String> {
+    let m = app_state.0.lock().await;
+    let mut worklog = finished_worklog(&m, &id).await?;
+    check_span(start, end)?;
+    check_overlap(&m, &id, start, end).await?;
+
+    worklog.start = start;
+    worklog.end = end;
+    worklog.total_time = m.config.rounding.apply(end - start);
+    let worklog = save_edit(&m, worklog).await?;
+
+    events::issues_changed(&app_handle);
+    Ok(worklog)
+}
+
+#[tauri::command(async)]
+pub async fn delete_worklog(
+    id: String,
+    app_state: State<'_, Arc<MangedState>>,
+    app_handle: AppHandle,
+) -> Result<(), String> {
+    let m = app_state.0.lock().await;
+    let worklog = finished_worklog(&m, &id).await?;
+    remove(&m, &worklog).await?;
+
+    events::issues_changed(&app_handle);
+    Ok(())
+}
+
+/// Logs time that was not tracked, e.g. a meeting away from the computer.
+#[tauri::command(async)]
+pub async fn add_manual_worklog(
+    name: String,
+    start: u64,
+    end: u64,
+    app_state: State<'_, Arc<MangedState>>,
+    app_handle: AppHandle,
+) -> Result<Worklog, String> {
+    let m = app_state.0.lock().await;
+    let issue = m.storage.get_issue(name).await?;
+    check_span(start, end)?;
+    check_overlap(&m, "", start, end).await?;
+
+    let worklog = insert(&m, issue.id, start, end, m.config.rounding.apply(end - start)).await?;
+
+    events::issues_changed(&app_handle);
+    Ok(worklog)
+}
+
+/// Logs a finished worklog on another issue. The tracker can't move worklogs, the one it has is
+/// deleted and the worklog is created again on the new issue.
+#[tauri::command(async)]
+pub async fn reassign_worklog(
+    id: String,
+    name: String,
+    app_state: State<'_, Arc<MangedState>>,
+    app_handle: AppHandle,
+) -> Result<Worklog, String> {
+    let m = app_state.0.lock().await;
+    let worklog = finished_worklog(&m, &id).await?;
+    let issue = m.storage.get_issue(name).await?;
+    if issue.id == worklog.issue_id {
+        return Ok(worklog);
+    }
+
+    sync::enqueue(m.tracker.as_deref(), &m.storage, sync::OutboxKind::Delete, &worklog).await?;
+    let worklog = m
+        .storage
+        .edit_worklog(Worklog {
+            issue_id: issue.id,
+            remote_id: None,
+            // imported history that was never sent stays local on the new issue too
+            synced: worklog.synced && worklog.remote_id.is_none(),
+            ..worklog
+        })
+        .await?;
+    sync::enqueue(m.tracker.as_deref(), &m.storage, sync::OutboxKind::Create, &worklog).await?;
+
+    events::issues_changed(&app_handle);
+    Ok(worklog)
+}
+
+/// Issues whose total drifted from their worklogs, e.g. edited in the database by hand.
+/// With `repair` the totals are set from the worklogs, the drift found is still returned.
+#[tauri::command(async)]
+pub async fn check_time_tracked(
+    repair: bool,
+    app_state: State<'_, Arc<MangedState>>,
+    app_handle: AppHandle,
+) -> Result<Vec<TotalDrift>, String> {
+    let m = app_state.0.lock().await;
+    let drift = m.storage.get_time_tracked_drift().await?;
+
+    if repair && !drift.is_empty() {
+        m.storage.repair_time_tracked().await?;
+        events::issues_changed(&app_handle);
+    }
+    Ok(drift)
+}
+
+/// Saves a changed finished worklog and queues the change for the tracker.
+pub async fn save_edit(m: &MutexGuard<'_, AppState>, worklog: Worklog) -> Result<Worklog, String> {
+    let worklog = m.storage.edit_worklog(worklog).await?;
+    sync::enqueue(m.tracker.as_deref(), &m.storage, sync::OutboxKind::Update, &worklog).await?;
+    Ok(worklog)
+}
+
+pub async fn remove(m: &MutexGuard<'_, AppState>, worklog: &Worklog) -> Result<(), String> {
+    // queued first, the outbox needs the worklog to drop writes the tracker never saw
+    sync::enqueue(m.tracker.as_deref(), &m.storage, sync::OutboxKind::Delete, worklog).await?;
+    m.storage.delete_worklog(&worklog.id).await
+}
+
+/// Adds a finished worklog that was not tracked live.
+pub async fn insert(
+    m: &MutexGuard<'_, AppState>,
+    issue_id: String,
+    start: u64,
+    end: u64,
+    total_time: u64,
+) -> Result<Worklog, String> {
+    let worklog = m
+        .storage
+        .add_worklog(Worklog {
+            id: Uuid::new_v4().to_string(),
+            issue_id,
+            start,
+            end,
+            total_time,
+            remote_id: None,
+            synced: false,
+        })
+        .await?;
+
+    sync::enqueue(m.tracker.as_deref(), &m.storage, sync::OutboxKind::Create, &worklog).await?;
+    Ok(worklog)
+}
+
+pub fn check_span(start: u64, end: u64) -> Result<(), String> {
+    if end <= start {
+        return Err("the worklog has to end after it started".to_string());
+    }
+    if end > sync::now()? as u64 {
+        return Err("the worklog can't end in the future".to_string());
+    }
+    Ok(())
+}
+
+/// Refuses a span sharing time with another worklog, `id` is the one being changed. Worklogs
+/// left open by a crash are not in the way, they are closed or discarded on their own.
+pub async fn check_overlap(m: &MutexGuard<'_, AppState>, id: &str, start: u64, end: u64) -> Result<(), String> {
+    let tracked = m.tracing_info.as_ref().map(|info| info.current_worklog.id.as_str());
+    let overlapping = m
+        .storage
+        .get_overlapping_worklogs(id, start, end)
+        .await?
+        .into_iter()
+        .find(|worklog| worklog.end != 0 || Some(worklog.id.as_str()) == tracked);
+
+    match overlapping {
+        Some(worklog) => {
+            let issue = m.storage.get_issue_by_id(&worklog.issue_id).await?;
+            Err(format!("the time overlaps a worklog of {}", issue.name))
+        }
+        None => Ok(()),
+    }
+}
+
+// open worklogs belong to the tracking session or to the recovery of a crashed one
+async fn finished_worklog(m: &MutexGuard<'_, AppState>, id: &str) -> Result<Worklog, String> {
+    let worklog = m.storage.get_worklog(id).await?;
+    match worklog.end {
+        0 => Err("the worklog is still running".to_string()),
+        _ => Ok(worklog),
+    }
+}
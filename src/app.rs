@@ -1,37 +1,101 @@
 use std::time::Duration;
 
 use crate::add_issue_form::AddIssueForm;
+use crate::backend::{invoke, listen_to, try_invoke, EmptyArgs, UpdateConfigArgs, WorklogIdArgs};
+use crate::calendar_import::CalendarImport;
 use crate::card::Card;
+use crate::day_view::DayView;
+use crate::export_form::ExportForm;
+use crate::idle_card::IdleCard;
+use crate::import_issues_form::ImportIssuesForm;
+use crate::import_wizard::ImportWizard;
+use crate::issue_details::IssueDetails;
+use crate::outbox_card::OutboxCard;
+use crate::recovery_card::RecoveryCard;
+use crate::report_view::ReportView;
+use crate::settings_form::SettingsForm;
+use crate::timesheet::Timesheet;
 use crate::tracking_card::TracingCard;
 use serde::{Deserialize, Serialize};
 use serde_wasm_bindgen::to_value;
-use shared::Issue;
-use wasm_bindgen::prelude::*;
+use shared::{format_duration, AppConfig, IdleAction, Issue, OpenWorklog, OutboxStatus, TotalDrift, TrackerConfig, TrackingState};
 use wasm_bindgen_futures::spawn_local;
 use yew::prelude::*;
 use yew_hooks::{use_effect_once, use_interval};
 
-#[wasm_bindgen]
-extern "C" {
-    #[wasm_bindgen(js_namespace = ["window", "__TAURI__", "tauri"])]
-    async fn invoke(cmd: &str, args: JsValue) -> JsValue;
-}
-
 #[derive(Serialize, Deserialize)]
 struct AddIssueArgs {
     name: String,
     summary: String,
 }
 
-#[derive(Serialize, Deserialize)]
-struct EmptyArgs {}
-
 #[derive(Serialize, Deserialize)]
 pub struct StartTrackingProps {
     name: String,
 }
 
-fn get_issues(issues: UseStateHandle<Box<Vec<Issue>>>, error: UseStateHandle<String>) {
+#[derive(Serialize, Deserialize)]
+struct ImportIssuesArgs {
+    query: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct OutboxItemArgs {
+    id: i64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct TestConnectionArgs {
+    tracker: TrackerConfig,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CloseOpenWorklogArgs {
+    id: String,
+    end: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CheckTotalsArgs {
+    repair: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ResolveIdleArgs {
+    action: IdleAction,
+}
+
+fn get_open_worklogs(open_worklogs: UseStateHandle<Vec<OpenWorklog>>, error: UseStateHandle<String>) {
+    spawn_local(async move {
+        let get_value = invoke("get_open_worklogs", to_value(&EmptyArgs {}).unwrap()).await;
+        match serde_wasm_bindgen::from_value(get_value) {
+            Ok(v) => open_worklogs.set(v),
+            Err(err) => error.set(err.to_string()),
+        };
+    });
+}
+
+fn get_config(config: UseStateHandle<AppConfig>, error: UseStateHandle<String>) {
+    spawn_local(async move {
+        let get_value = invoke("get_config", to_value(&EmptyArgs {}).unwrap()).await;
+        match serde_wasm_bindgen::from_value(get_value) {
+            Ok(v) => config.set(v),
+            Err(err) => error.set(err.to_string()),
+        };
+    });
+}
+
+fn get_config_error(config_error: UseStateHandle<Option<String>>, error: UseStateHandle<String>) {
+    spawn_local(async move {
+        let get_value = invoke("get_config_error", to_value(&EmptyArgs {}).unwrap()).await;
+        match serde_wasm_bindgen::from_value(get_value) {
+            Ok(v) => config_error.set(v),
+            Err(err) => error.set(err.to_string()),
+        };
+    });
+}
+
+fn get_issues(issues: UseStateHandle<Vec<Issue>>, error: UseStateHandle<String>) {
     let issues = issues.clone();
     let error = error.clone();
     spawn_local(async move {
@@ -44,109 +108,147 @@ fn get_issues(issues: UseStateHandle<Box<Vec<Issue>>>, error: UseStateHandle<Str
                 Vec::new()
             }
         };
-        issues.set(Box::new(val));
+        issues.set(val);
     });
 }
 
-fn get_tracing_info(tracking_issue: UseStateHandle<Option<String>>, error: UseStateHandle<String>, elapsed_time: UseStateHandle<Duration>) {
+fn get_outbox_status(outbox_status: UseStateHandle<OutboxStatus>, error: UseStateHandle<String>) {
     spawn_local(async move {
-        let args = to_value(&EmptyArgs {}).unwrap();
-        let get_value = invoke("get_elapsed_time", args).await;
-
-        let val: Duration = match serde_wasm_bindgen::from_value(get_value) {
-            Ok(v) => v,
-            Err(err) => {
-                error.set(err.to_string());
-                Duration::from_micros(0)
-            }
+        let get_value = invoke("get_outbox_status", to_value(&EmptyArgs {}).unwrap()).await;
+        match serde_wasm_bindgen::from_value(get_value) {
+            Ok(v) => outbox_status.set(v),
+            Err(err) => error.set(err.to_string()),
         };
-        elapsed_time.set(val);
+    });
+}
 
-        let args = to_value(&EmptyArgs {}).unwrap();
-        let get_value = invoke("get_tracing_issue_name", args).await;
-        let val: Option<String> = match serde_wasm_bindgen::from_value(get_value) {
-            Ok(v) => v,
-            Err(err) => {
-                error.set(err.to_string());
-                Some(err.to_string())
-            }
+fn outbox_action(cmd: &'static str, outbox_status: UseStateHandle<OutboxStatus>, error: UseStateHandle<String>) -> Callback<i64> {
+    Callback::from(move |id: i64| {
+        let outbox_status = outbox_status.clone();
+        let error = error.clone();
+        spawn_local(async move {
+            invoke(cmd, to_value(&OutboxItemArgs { id }).unwrap()).await;
+            get_outbox_status(outbox_status, error);
+        });
+    })
+}
+
+fn get_tracking_state(tracking: UseStateHandle<TrackingState>, error: UseStateHandle<String>) {
+    spawn_local(async move {
+        let get_value = invoke("get_tracking_state", to_value(&EmptyArgs {}).unwrap()).await;
+        match serde_wasm_bindgen::from_value(get_value) {
+            Ok(v) => tracking.set(v),
+            Err(err) => error.set(err.to_string()),
         };
-        tracking_issue.set(val)
     });
 }
 
+/// What the right side shows.
+#[derive(Clone, Copy, PartialEq)]
+enum View {
+    Issues,
+    Timesheet,
+    Day,
+    Reports,
+    Import,
+}
+
 #[function_component(App)]
 pub fn app() -> Html {
     wasm_logger::init(wasm_logger::Config::default());
 
-    let name = use_state(|| String::new());
-    let summary = use_state(|| String::new());
-    let error = use_state(|| String::new());
+    let name = use_state(String::new);
+    let summary = use_state(String::new);
+    let error = use_state(String::new);
 
-    let issues = use_state(|| Box::new(Vec::new()));
-    let tracking_issue: UseStateHandle<Option<String>> = use_state(|| None);
-    let elapsed_time = use_state(|| Duration::from_micros(0));
+    let issues = use_state(Vec::new);
+    let tracking = use_state(TrackingState::default);
+    let outbox_status = use_state(OutboxStatus::default);
+    let config = use_state(AppConfig::default);
+    let config_error: UseStateHandle<Option<String>> = use_state(|| None);
+    let show_settings = use_state(|| false);
+    let settings_status = use_state(String::new);
+    let open_worklogs = use_state(Vec::new);
+    let recovery_status = use_state(String::new);
+    let selected_issue: UseStateHandle<Option<String>> = use_state(|| None);
+    let view = use_state(|| View::Issues);
 
     {
         let issues2 = issues.clone();
         let error2 = error.clone();
+        let config = config.clone();
+        let config_error = config_error.clone();
+        let open_worklogs = open_worklogs.clone();
+        let tracking = tracking.clone();
         use_effect_once(move || {
-            get_issues(issues2, error2);
+            get_issues(issues2.clone(), error2.clone());
+            get_config(config, error2.clone());
+            get_config_error(config_error, error2.clone());
+            get_open_worklogs(open_worklogs, error2.clone());
+            get_tracking_state(tracking.clone(), error2.clone());
+
+            // the backend tells every window about tracking started from anywhere, the tray too
+            for event in ["tracking-started", "tracking-stopped", "tracking-tick"] {
+                let tracking = tracking.clone();
+                let error = error2.clone();
+                listen_to(event, move |payload| match serde_wasm_bindgen::from_value(payload) {
+                    Ok(state) => tracking.set(state),
+                    Err(err) => error.set(err.to_string()),
+                });
+            }
+            listen_to("issues-changed", move |_| get_issues(issues2.clone(), error2.clone()));
             || () // cleaning function
         });
     }
 
     {
-        let tracking_issue = tracking_issue.clone();
+        let outbox_status = outbox_status.clone();
         let error = error.clone();
-        let elapsed_time = elapsed_time.clone();
         use_interval(
             move || {
-                get_tracing_info(tracking_issue.clone(), error.clone(), elapsed_time.clone());
+                get_outbox_status(outbox_status.clone(), error.clone());
             },
-            999,
+            5000,
         );
     }
 
-    let start_tracking = {
-        let tracking_issue = tracking_issue.clone();
-        Callback::from(move |name: Box<String>| {
-            let tracking_issue = tracking_issue.clone();
-            spawn_local(async move {
-                let args = to_value(&StartTrackingProps {
-                    name: *name.clone(),
-                })
-                .unwrap();
-
-                invoke("start_tracking_cmd", args).await;
-                tracking_issue.set(Some(*name.clone()));
-            });
-        })
+    let show_view = |shown: View| {
+        let view = view.clone();
+        move |_: MouseEvent| view.set(shown)
     };
 
-    let stop_tracking = {
-        let issues = issues.clone();
-        let error = error.clone();
-        let tracking_issue = tracking_issue.clone();
-        Callback::from(move |()| {
-            let tracking_issue = tracking_issue.clone();
-            let issues = issues.clone();
-            let error = error.clone();
-            spawn_local(async move {
-                let args = to_value(&EmptyArgs{}).unwrap();
+    let open_details = {
+        let selected_issue = selected_issue.clone();
+        Callback::from(move |name: String| selected_issue.set(Some(name)))
+    };
 
-                invoke("stop_tracking_cmd", args).await;
-                tracking_issue.set(None);
-                get_issues(issues, error);
-            });
-        })
+    let close_details = {
+        let selected_issue = selected_issue.clone();
+        Callback::from(move |()| selected_issue.set(None))
     };
 
+    let retry_outbox_item = outbox_action("retry_outbox_item", outbox_status.clone(), error.clone());
+    let discard_outbox_item = outbox_action("discard_outbox_item", outbox_status.clone(), error.clone());
+
+    let start_tracking = Callback::from(move |name: String| {
+        spawn_local(async move {
+            let args = to_value(&StartTrackingProps { name }).unwrap();
+
+            invoke("start_tracking_cmd", args).await;
+        });
+    });
+
+    let stop_tracking = Callback::from(move |()| {
+        spawn_local(async move {
+            let args = to_value(&EmptyArgs{}).unwrap();
+
+            invoke("stop_tracking_cmd", args).await;
+        });
+    });
+
     {
         let name = name.clone();
         let summary = summary.clone();
-        let issues = issues.clone();
-        let error = error.clone();
         let name_copy = name.clone(); // clone to solved moved error
         use_effect_with(name_copy, move |_| {
             spawn_local(async move {
@@ -161,7 +263,6 @@ pub fn app() -> Html {
                 .unwrap();
 
                 invoke("add_issue", args).await;
-                get_issues(issues, error);
             });
             || {}
         });
@@ -176,27 +277,220 @@ pub fn app() -> Html {
         })
     };
 
+    let import_issues = Callback::from(move |query: Option<String>| {
+        spawn_local(async move {
+            let args = to_value(&ImportIssuesArgs { query }).unwrap();
+            invoke("import_issues", args).await;
+        });
+    });
+
+    let save_settings = {
+        let config = config.clone();
+        let config_error = config_error.clone();
+        let settings_status = settings_status.clone();
+        Callback::from(move |new_config: AppConfig| {
+            let config = config.clone();
+            let config_error = config_error.clone();
+            let settings_status = settings_status.clone();
+            spawn_local(async move {
+                let args = to_value(&UpdateConfigArgs { config: new_config }).unwrap();
+                match try_invoke("update_config", args).await {
+                    Ok(saved) => {
+                        if let Ok(saved) = serde_wasm_bindgen::from_value(saved) {
+                            config.set(saved);
+                        }
+                        config_error.set(None);
+                        settings_status.set("Settings saved".to_string());
+                    }
+                    Err(err) => settings_status.set(err.as_string().unwrap_or_default()),
+                }
+            });
+        })
+    };
+
+    let on_config_saved = {
+        let config = config.clone();
+        let config_error = config_error.clone();
+        Callback::from(move |saved: AppConfig| {
+            config.set(saved);
+            config_error.set(None);
+        })
+    };
+
+    let test_connection = {
+        let settings_status = settings_status.clone();
+        Callback::from(move |tracker: TrackerConfig| {
+            let settings_status = settings_status.clone();
+            spawn_local(async move {
+                settings_status.set("Connecting...".to_string());
+                let args = to_value(&TestConnectionArgs { tracker }).unwrap();
+                match try_invoke("test_tracker_connection", args).await {
+                    Ok(user) => settings_status.set(format!("Connected as {}", user.as_string().unwrap_or_default())),
+                    Err(err) => settings_status.set(err.as_string().unwrap_or_default()),
+                }
+            });
+        })
+    };
+
+    let check_totals = {
+        let settings_status = settings_status.clone();
+        Callback::from(move |repair: bool| {
+            let settings_status = settings_status.clone();
+            spawn_local(async move {
+                let args = to_value(&CheckTotalsArgs { repair }).unwrap();
+                let drift: Result<Vec<TotalDrift>, String> = match try_invoke("check_time_tracked", args).await {
+                    Ok(drift) => serde_wasm_bindgen::from_value(drift).map_err(|err| err.to_string()),
+                    Err(err) => Err(err.as_string().unwrap_or_default()),
+                };
+                let status = match drift {
+                    Ok(drift) if drift.is_empty() => "Totals match the worklogs".to_string(),
+                    Ok(drift) => format!(
+                        "{} {}",
+                        if repair { "Repaired" } else { "Off:" },
+                        drift
+                            .iter()
+                            .map(|d| format!("{} {} instead of {}", d.issue_name, format_duration(d.recorded), format_duration(d.worklogs)))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ),
+                    Err(err) => err,
+                };
+                settings_status.set(status);
+            });
+        })
+    };
+
+    let close_open_worklog = {
+        let open_worklogs = open_worklogs.clone();
+        let recovery_status = recovery_status.clone();
+        let error = error.clone();
+        Callback::from(move |(id, end): (String, Option<u64>)| {
+            let open_worklogs = open_worklogs.clone();
+            let recovery_status = recovery_status.clone();
+            let error = error.clone();
+            spawn_local(async move {
+                let args = to_value(&CloseOpenWorklogArgs { id, end }).unwrap();
+                match try_invoke("close_open_worklog", args).await {
+                    Ok(_) => recovery_status.set(String::new()),
+                    Err(err) => recovery_status.set(err.as_string().unwrap_or_default()),
+                }
+                get_open_worklogs(open_worklogs, error);
+            });
+        })
+    };
+
+    let discard_open_worklog = {
+        let open_worklogs = open_worklogs.clone();
+        let recovery_status = recovery_status.clone();
+        let error = error.clone();
+        Callback::from(move |id: String| {
+            let open_worklogs = open_worklogs.clone();
+            let recovery_status = recovery_status.clone();
+            let error = error.clone();
+            spawn_local(async move {
+                let args = to_value(&WorklogIdArgs { id }).unwrap();
+                match try_invoke("discard_open_worklog", args).await {
+                    Ok(_) => recovery_status.set(String::new()),
+                    Err(err) => recovery_status.set(err.as_string().unwrap_or_default()),
+                }
+                get_open_worklogs(open_worklogs, error);
+            });
+        })
+    };
+
+    let toggle_pause = {
+        let paused = tracking.paused;
+        Callback::from(move |()| {
+            spawn_local(async move {
+                let cmd = if paused { "resume_tracking_cmd" } else { "pause_tracking_cmd" };
+                invoke(cmd, to_value(&EmptyArgs {}).unwrap()).await;
+            });
+        })
+    };
+
+    let resolve_idle = {
+        let error = error.clone();
+        Callback::from(move |action: IdleAction| {
+            let error = error.clone();
+            spawn_local(async move {
+                let args = to_value(&ResolveIdleArgs { action }).unwrap();
+                if let Err(err) = try_invoke("resolve_idle", args).await {
+                    error.set(err.as_string().unwrap_or_default());
+                }
+            });
+        })
+    };
+
+    let toggle_settings = {
+        let show_settings = show_settings.clone();
+        move |_: MouseEvent| show_settings.set(!*show_settings)
+    };
+
     html! {
         <main class="container">
             <div class = "column column-25 wrap-flex">
+                if let Some(config_error) = (*config_error).clone() {
+                    <p class="error">{ format!("The config is not in use, the app runs without a tracker: {}", config_error) }</p>
+                }
                 <div class = "row wrap-flex">
                     <AddIssueForm add_issue={add_issue}/>
                 </div>
-                if tracking_issue.is_some() {
-                    <TracingCard name={tracking_issue.as_ref().unwrap().clone()} duration={*elapsed_time} stop_tracking={stop_tracking.clone()} />
+                <div class = "row wrap-flex">
+                    <button onclick={show_view(View::Issues)}>{"Issues"}</button>
+                    <button onclick={show_view(View::Timesheet)}>{"Timesheet"}</button>
+                    <button onclick={show_view(View::Day)}>{"Day"}</button>
+                    <button onclick={show_view(View::Reports)}>{"Reports"}</button>
+                    <button onclick={show_view(View::Import)}>{"Import"}</button>
+                </div>
+                <div class = "row wrap-flex">
+                    <button onclick={toggle_settings}>{ if *show_settings { "Hide settings" } else { "Settings" } }</button>
+                </div>
+                if *show_settings {
+                    <div class = "row wrap-flex">
+                        <SettingsForm config={(*config).clone()} save={save_settings} test_connection={test_connection} check_totals={check_totals} status={(*settings_status).clone()} />
+                    </div>
+                }
+                <div class = "row wrap-flex">
+                    <ImportIssuesForm import_issues={import_issues}/>
+                </div>
+                if !open_worklogs.is_empty() {
+                    <RecoveryCard open_worklogs={(*open_worklogs).clone()} close={close_open_worklog} discard={discard_open_worklog} status={(*recovery_status).clone()} />
+                }
+                if let (Some(period), Some(name)) = (tracking.idle.clone().filter(|period| period.end.is_some()), tracking.issue_name.clone()) {
+                    <IdleCard period={period} issues={(*issues).clone()} tracking_issue={name} resolve={resolve_idle} />
+                }
+                if let Some(name) = tracking.issue_name.clone() {
+                    <TracingCard name={name} duration={Duration::from_secs(tracking.elapsed)} paused={tracking.paused} stop_tracking={stop_tracking.clone()} toggle_pause={toggle_pause} />
+                }
+                if outbox_status.pending > 0 || !outbox_status.failed.is_empty() {
+                    <OutboxCard status={(*outbox_status).clone()} retry={retry_outbox_item} discard={discard_outbox_item} />
                 }
 
 
             </div>
             <div class="divider-vertical"></div>
             <div class = "column column-75">
-                <div class = "row wrap-flex">
-                    {
-                        issues.iter().map(|issue| {
-                            html!{<Card issue={issue} start_tracking={start_tracking.clone()} />}
-                        }).collect::<Html>()
-                    }
-                </div>
+                if *view == View::Import {
+                    <ImportWizard issues={(*issues).clone()} />
+                    <CalendarImport issues={(*issues).clone()} config={(*config).clone()} saved={on_config_saved} />
+                } else if *view == View::Reports {
+                    <ReportView issues={(*issues).clone()} />
+                    <ExportForm />
+                } else if *view == View::Day {
+                    <DayView issues={(*issues).clone()} />
+                } else if *view == View::Timesheet {
+                    <Timesheet issues={(*issues).clone()} />
+                } else if let Some(issue) = selected_issue.as_ref().and_then(|name| issues.iter().find(|issue| &issue.name == name)) {
+                    <IssueDetails key={issue.name.clone()} issue={issue.clone()} close={close_details} />
+                } else {
+                    <div class = "row wrap-flex">
+                        {
+                            issues.iter().map(|issue| {
+                                html!{<Card issue={issue} start_tracking={start_tracking.clone()} open_details={open_details.clone()} />}
+                            }).collect::<Html>()
+                        }
+                    </div>
+                }
             </div>
 
 
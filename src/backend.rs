@@ -0,0 +1,72 @@
+use js_sys::Reflect;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_wasm_bindgen::to_value;
+use shared::AppConfig;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::spawn_local;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = ["window", "__TAURI__", "tauri"])]
+    pub async fn invoke(cmd: &str, args: JsValue) -> JsValue;
+
+    // same as invoke, but an error returned by the command comes back as Err
+    #[wasm_bindgen(js_namespace = ["window", "__TAURI__", "tauri"], js_name = invoke, catch)]
+    pub async fn try_invoke(cmd: &str, args: JsValue) -> Result<JsValue, JsValue>;
+
+    #[wasm_bindgen(js_namespace = ["window", "__TAURI__", "event"])]
+    async fn listen(event: &str, handler: &Closure<dyn FnMut(JsValue)>) -> JsValue;
+}
+
+/// Runs the command and reads its answer, the error of the command comes back as its text.
+pub async fn call<T: DeserializeOwned>(cmd: &str, args: &impl Serialize) -> Result<T, String> {
+    let value = try_invoke(cmd, to_value(args).unwrap())
+        .await
+        .map_err(|err| err.as_string().unwrap_or_default())?;
+    serde_wasm_bindgen::from_value(value).map_err(|err| err.to_string())
+}
+
+/// Calls the handler with the payload of every backend event with this name. The app lives as
+/// long as the page, so the listener is never removed.
+pub fn listen_to(event: &'static str, handler: impl Fn(JsValue) + 'static) {
+    let closure = Closure::<dyn FnMut(JsValue)>::new(move |event: JsValue| {
+        handler(Reflect::get(&event, &JsValue::from_str("payload")).unwrap_or(JsValue::NULL))
+    });
+    spawn_local(async move {
+        listen(event, &closure).await;
+        closure.forget();
+    });
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct EmptyArgs {}
+
+#[derive(Serialize, Deserialize)]
+pub struct WorklogIdArgs {
+    pub id: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct EditWorklogArgs {
+    pub id: String,
+    pub start: u64,
+    pub end: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ManualWorklogArgs {
+    pub name: String,
+    pub start: u64,
+    pub end: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct RangeArgs {
+    pub from: u64,
+    pub to: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct UpdateConfigArgs {
+    pub config: AppConfig,
+}
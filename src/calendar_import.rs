@@ -0,0 +1,291 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use serde_wasm_bindgen::to_value;
+use shared::{format_duration, AppConfig, CalendarRule, Issue, SuggestedWorklog};
+use wasm_bindgen_futures::{spawn_local, JsFuture};
+use web_sys::{HtmlInputElement, HtmlSelectElement};
+use yew::prelude::*;
+
+use crate::backend::{call, try_invoke, ManualWorklogArgs, UpdateConfigArgs};
+use crate::time::{add_days, date_input_time, day_key, local_time, now, week_start};
+
+#[derive(Properties, PartialEq)]
+pub struct CalendarImportProps {
+    pub issues: Vec<Issue>,
+    /// the rules are its calendar rules
+    pub config: AppConfig,
+    /// the config once the rules are saved
+    pub saved: Callback<AppConfig>,
+}
+
+/// The days are local midnights and `to` is not included.
+#[derive(Serialize, Deserialize)]
+struct SuggestWorklogsArgs {
+    contents: String,
+    from: u64,
+    to: u64,
+}
+
+#[function_component(CalendarImport)]
+pub fn calendar_import(props: &CalendarImportProps) -> Html {
+    let contents = use_state(String::new);
+    let from = use_state(|| week_start(now()));
+    let to = use_state(|| add_days(now(), 1));
+    let rules = use_state(|| props.config.calendar.rules.clone());
+    let suggestions: UseStateHandle<Vec<SuggestedWorklog>> = use_state(Vec::new);
+    let status = use_state(String::new);
+    // issues picked for suggestions, over the one from the rules
+    let picked: UseStateHandle<HashMap<String, String>> = use_state(HashMap::new);
+
+    {
+        let rules = rules.clone();
+        use_effect_with(props.config.calendar.rules.clone(), move |saved| {
+            rules.set(saved.clone());
+            || ()
+        });
+    }
+
+    {
+        // the rules decide the issues, they are applied again once saved
+        let suggestions = suggestions.clone();
+        let status = status.clone();
+        let saved_rules = props.config.calendar.rules.clone();
+        use_effect_with(((*contents).clone(), *from, *to, saved_rules), move |(contents, from, to, _)| {
+            if !contents.is_empty() {
+                let args = SuggestWorklogsArgs { contents: contents.clone(), from: *from, to: *to };
+                spawn_local(async move {
+                    match call("suggest_worklogs", &args).await {
+                        Ok(v) => {
+                            suggestions.set(v);
+                            status.set(String::new());
+                        }
+                        Err(err) => status.set(err),
+                    }
+                });
+            }
+            || ()
+        });
+    }
+
+    // one after another, the accepted ones leave the list
+    let accept = {
+        let suggestions = suggestions.clone();
+        let status = status.clone();
+        Callback::from(move |accepted: Vec<SuggestedWorklog>| {
+            let suggestions = suggestions.clone();
+            let status = status.clone();
+            spawn_local(async move {
+                let mut added = Vec::new();
+                let mut errors = Vec::new();
+                for suggestion in accepted {
+                    let name = suggestion.issue_name.clone().unwrap_or_default();
+                    let args = to_value(&ManualWorklogArgs { name, start: suggestion.start, end: suggestion.end }).unwrap();
+                    match try_invoke("add_manual_worklog", args).await {
+                        Ok(_) => added.push(suggestion.id),
+                        Err(err) => errors.push(format!("{}: {}", suggestion.title, err.as_string().unwrap_or_default())),
+                    }
+                }
+                suggestions.set(suggestions.iter().filter(|suggestion| !added.contains(&suggestion.id)).cloned().collect());
+                status.set(match errors.is_empty() {
+                    true => format!("Added {} worklogs", added.len()),
+                    false => errors.join(", "),
+                });
+            });
+        })
+    };
+
+    let dismiss = {
+        let suggestions = suggestions.clone();
+        Callback::from(move |id: String| {
+            suggestions.set(suggestions.iter().filter(|suggestion| suggestion.id != id).cloned().collect());
+        })
+    };
+
+    let on_file = {
+        let contents = contents.clone();
+        move |e: Event| {
+            let file = match e.target_unchecked_into::<HtmlInputElement>().files().and_then(|files| files.get(0)) {
+                Some(file) => file,
+                None => return,
+            };
+            let contents = contents.clone();
+            spawn_local(async move {
+                if let Ok(text) = JsFuture::from(file.text()).await {
+                    contents.set(text.as_string().unwrap_or_default());
+                }
+            });
+        }
+    };
+
+    let on_day = |day: UseStateHandle<u64>, days_after: i32| {
+        move |e: Event| {
+            if let Some(time) = date_input_time(&e.target_unchecked_into::<HtmlInputElement>().value()) {
+                day.set(add_days(time, days_after));
+            }
+        }
+    };
+
+    let edit_rule = |i: usize, apply: fn(&mut CalendarRule, String)| {
+        let rules = rules.clone();
+        move |e: Event| {
+            let mut changed = (*rules).clone();
+            if let Some(rule) = changed.get_mut(i) {
+                apply(rule, e.target_unchecked_into::<HtmlInputElement>().value());
+            }
+            rules.set(changed);
+        }
+    };
+    let remove_rule = |i: usize| {
+        let rules = rules.clone();
+        move |_: MouseEvent| {
+            let mut changed = (*rules).clone();
+            changed.remove(i);
+            rules.set(changed);
+        }
+    };
+    let add_rule = {
+        let rules = rules.clone();
+        move |_: MouseEvent| {
+            let mut changed = (*rules).clone();
+            changed.push(CalendarRule::default());
+            rules.set(changed);
+        }
+    };
+    let on_save_rules = {
+        let rules = rules.clone();
+        let config = props.config.clone();
+        let saved = props.saved.clone();
+        let status = status.clone();
+        move |_: MouseEvent| {
+            let mut new_config = config.clone();
+            new_config.calendar.rules = (*rules).clone();
+            let saved = saved.clone();
+            let status = status.clone();
+            spawn_local(async move {
+                match call("update_config", &UpdateConfigArgs { config: new_config }).await {
+                    Ok(config) => {
+                        saved.emit(config);
+                        status.set("Rules saved".to_string());
+                    }
+                    Err(err) => status.set(err),
+                }
+            });
+        }
+    };
+
+    let with_issue = {
+        let picked = picked.clone();
+        move |suggestion: &SuggestedWorklog| SuggestedWorklog {
+            issue_name: match picked.get(&suggestion.id) {
+                Some(name) => Some(name.clone()).filter(|name| !name.is_empty()),
+                None => suggestion.issue_name.clone(),
+            },
+            ..suggestion.clone()
+        }
+    };
+    let known = |name: &Option<String>| name.as_ref().is_some_and(|name| props.issues.iter().any(|issue| &issue.name == name));
+    let ready: Vec<SuggestedWorklog> = suggestions
+        .iter()
+        .map(&with_issue)
+        .filter(|suggestion| known(&suggestion.issue_name) && suggestion.conflict.is_none())
+        .collect();
+    let on_accept_all = {
+        let accept = accept.clone();
+        let ready = ready.clone();
+        move |_: MouseEvent| accept.emit(ready.clone())
+    };
+
+    html! {
+        <div class="card import">
+            <div>
+                <h4><b>{"Meetings from a calendar"}</b></h4>
+                <div class="row wrap-flex">
+                    <input type="file" accept=".ics,text/calendar" onchange={on_file} />
+                    <input type="date" value={day_key(*from)} onchange={on_day(from.clone(), 0)} />
+                    <input type="date" value={day_key(add_days(*to, -1))} onchange={on_day(to.clone(), 1)} />
+                </div>
+                <div class="column wrap-flex">
+                    { rules.iter().enumerate().map(|(i, rule)| html! {
+                        <div class="row wrap-flex">
+                            <input placeholder="Title contains, e.g. standup" value={rule.contains.clone()} onchange={edit_rule(i, |r, v| r.contains = v)} />
+                            <input placeholder="Issue, e.g. OPS-12" value={rule.issue.clone()} onchange={edit_rule(i, |r, v| r.issue = v)} />
+                            <button onclick={remove_rule(i)}>{"Remove"}</button>
+                        </div>
+                    }).collect::<Html>() }
+                    <div class="row wrap-flex">
+                        <button onclick={add_rule}>{"Add rule"}</button>
+                        <button onclick={on_save_rules}>{"Save rules"}</button>
+                    </div>
+                </div>
+                if !status.is_empty() {
+                    <p class="settings-status">{ &*status }</p>
+                }
+                if !suggestions.is_empty() {
+                    <button onclick={on_accept_all} disabled={ready.is_empty()}>{ format!("Accept {} with an issue", ready.len()) }</button>
+                    <table>
+                        <tr>
+                            <th>{"Start"}</th>
+                            <th>{"Time"}</th>
+                            <th>{"Event"}</th>
+                            <th>{"Issue"}</th>
+                            <th></th>
+                        </tr>
+                        { suggestions.iter().map(|suggestion| {
+                            let shown = with_issue(suggestion);
+                            let on_pick = {
+                                let picked = picked.clone();
+                                let id = suggestion.id.clone();
+                                move |e: Event| {
+                                    let mut changed = (*picked).clone();
+                                    changed.insert(id.clone(), e.target_unchecked_into::<HtmlSelectElement>().value());
+                                    picked.set(changed);
+                                }
+                            };
+                            let on_accept = {
+                                let accept = accept.clone();
+                                let shown = shown.clone();
+                                move |_: MouseEvent| accept.emit(vec![shown.clone()])
+                            };
+                            let on_dismiss = {
+                                let dismiss = dismiss.clone();
+                                let id = suggestion.id.clone();
+                                move |_: MouseEvent| dismiss.emit(id.clone())
+                            };
+                            let missing = shown.issue_name.clone().filter(|_| !known(&shown.issue_name));
+                            let selected = shown.issue_name.clone().filter(|_| missing.is_none()).unwrap_or_default();
+
+                            html! {
+                                <tr class={classes!(suggestion.conflict.is_some().then_some("import-skipped"))}>
+                                    <td>{ local_time(suggestion.start) }</td>
+                                    <td>{ format_duration(suggestion.end - suggestion.start) }</td>
+                                    <td>
+                                        { &suggestion.title }
+                                        if let Some(conflict) = &suggestion.conflict {
+                                            <div class="error">{ conflict }</div>
+                                        }
+                                        if let Some(name) = missing {
+                                            <div class="error">{ format!("there is no issue {}", name) }</div>
+                                        }
+                                    </td>
+                                    <td>
+                                        <select onchange={on_pick}>
+                                            <option value="" selected={selected.is_empty()}>{"pick an issue"}</option>
+                                            { props.issues.iter().map(|issue| html! {
+                                                <option value={issue.name.clone()} selected={issue.name == selected}>{ format!("{}: {}", issue.name, issue.summary) }</option>
+                                            }).collect::<Html>() }
+                                        </select>
+                                    </td>
+                                    <td>
+                                        <button onclick={on_accept} disabled={selected.is_empty()}>{"Accept"}</button>
+                                        <button onclick={on_dismiss}>{"Dismiss"}</button>
+                                    </td>
+                                </tr>
+                            }
+                        }).collect::<Html>() }
+                    </table>
+                }
+            </div>
+        </div>
+    }
+}
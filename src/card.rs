@@ -1,30 +1,52 @@
-use shared::Issue;
+use shared::{format_duration, Issue};
 use yew::prelude::*;
 
 #[derive(Properties, PartialEq)]
 pub struct CardProps {
     pub issue: Issue,
-    pub start_tracking: Callback<Box<String>>,
+    pub start_tracking: Callback<String>,
+    pub open_details: Callback<String>,
 }
 
 #[function_component(Card)]
 pub fn card(props: &CardProps) -> Html {
     let key = props.issue.name.clone();
     let start_tracking = props.start_tracking.clone();
+    let open_details = props.open_details.clone();
 
     let on_clicked = {
         let key2 = key.clone();
         move |_: MouseEvent| {
             let key2 = key2.clone();
-            start_tracking.emit(Box::new(key2))
+            open_details.emit(key2)
+        }
+    };
+
+    let on_start_clicked = {
+        let key2 = key.clone();
+        move |e: MouseEvent| {
+            // the card itself opens the details
+            e.stop_propagation();
+            start_tracking.emit(key2.clone())
         }
     };
 
     html! {
-        <div id={ key } onclick={on_clicked} class="card">
+        <div id={ key } onclick={on_clicked} class={classes!("card", props.issue.stale.then_some("stale"))}>
             <div>
                 <h4><b>{"Name: "} { &*props.issue.name }  </b></h4>
+                if props.issue.stale {
+                    <p>{"No longer assigned in the tracker"}</p>
+                }
+                <p> { &*props.issue.summary } </p>
+                if !props.issue.status.is_empty() {
+                    <p class="issue-meta">{ &*props.issue.project } {" · "} { &*props.issue.issue_type } {" · "} { &*props.issue.status }</p>
+                }
                 <p> {"Time: "} { props.issue.time_tracked_all } </p>
+                if props.issue.remote_time_spent > 0 {
+                    <p class="issue-meta">{"In tracker: "} { format_duration(props.issue.remote_time_spent) }</p>
+                }
+                <button onclick={on_start_clicked}>{"Start"}</button>
             </div>
         </div>
     }
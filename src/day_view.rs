@@ -0,0 +1,319 @@
+use serde::{Deserialize, Serialize};
+use serde_wasm_bindgen::to_value;
+use shared::{format_duration, Issue, Worklog};
+use wasm_bindgen::JsValue;
+use wasm_bindgen_futures::spawn_local;
+use web_sys::HtmlSelectElement;
+use yew::prelude::*;
+
+use crate::backend::{call, try_invoke, EditWorklogArgs, ManualWorklogArgs, RangeArgs};
+use crate::time::{add_days, day_key, day_label, local_time, now};
+
+// one pixel per minute of the day, moves snap to five minutes
+const PX_PER_SECOND: f64 = 1.0 / 60.0;
+const SNAP: i64 = 5 * 60;
+// shorter gaps are not worth filling in
+const MIN_GAP: u64 = 5 * 60;
+
+#[derive(Properties, PartialEq)]
+pub struct DayViewProps {
+    /// the day is loaded again when they change, that is with every finished worklog
+    pub issues: Vec<Issue>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ReassignWorklogArgs {
+    id: String,
+    name: String,
+}
+
+/// Worklogs started on the day.
+fn load_day(day_start: u64, worklogs: UseStateHandle<Vec<Worklog>>, status: UseStateHandle<String>) {
+    spawn_local(async move {
+        match call("get_worklogs_between", &RangeArgs { from: day_start, to: add_days(day_start, 1) }).await {
+            Ok(v) => worklogs.set(v),
+            Err(err) => status.set(err),
+        }
+    });
+}
+
+/// Runs a worklog command and loads the day again.
+fn day_action<T: 'static>(
+    day_start: u64,
+    worklogs: UseStateHandle<Vec<Worklog>>,
+    status: UseStateHandle<String>,
+    command: impl Fn(T) -> (&'static str, JsValue) + 'static,
+) -> Callback<T> {
+    Callback::from(move |value: T| {
+        let (cmd, args) = command(value);
+        let worklogs = worklogs.clone();
+        let status = status.clone();
+        spawn_local(async move {
+            match try_invoke(cmd, args).await {
+                Ok(_) => status.set(String::new()),
+                Err(err) => status.set(err.as_string().unwrap_or_default()),
+            }
+            load_day(day_start, worklogs, status);
+        });
+    })
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum DragMode {
+    Move,
+    Resize,
+}
+
+#[derive(Clone, PartialEq)]
+struct Drag {
+    id: String,
+    mode: DragMode,
+    origin_y: i32,
+    start: u64,
+    end: u64,
+    moved: bool,
+}
+
+impl Drag {
+    /// The span after moving the pointer `dy` pixels.
+    fn span(&self, dy: i32) -> (u64, u64) {
+        let delta = (dy as f64 / PX_PER_SECOND) as i64 / SNAP * SNAP;
+        match self.mode {
+            DragMode::Move => (
+                (self.start as i64 + delta).max(0) as u64,
+                (self.end as i64 + delta).max(0) as u64,
+            ),
+            DragMode::Resize => (self.start, (self.end as i64 + delta).max(self.start as i64 + SNAP) as u64),
+        }
+    }
+}
+
+/// What a click picked, the panel below the timeline acts on it.
+#[derive(Clone, PartialEq)]
+enum Selection {
+    Worklog(String),
+    Gap(u64, u64),
+}
+
+fn gaps(worklogs: &[Worklog]) -> Vec<(u64, u64)> {
+    let mut spans: Vec<(u64, u64)> = worklogs
+        .iter()
+        .map(|worklog| (worklog.start, if worklog.end == 0 { now() } else { worklog.end }))
+        .collect();
+    spans.sort();
+
+    let mut gaps = Vec::new();
+    let mut last_end: Option<u64> = None;
+    for (start, end) in spans {
+        if let Some(last_end) = last_end {
+            if start >= last_end + MIN_GAP {
+                gaps.push((last_end, start));
+            }
+        }
+        last_end = Some(last_end.map_or(end, |last_end| last_end.max(end)));
+    }
+    gaps
+}
+
+#[function_component(DayView)]
+pub fn day_view(props: &DayViewProps) -> Html {
+    let day = use_state(|| add_days(now(), 0));
+    let worklogs = use_state(Vec::new);
+    // result of the last change
+    let status = use_state(String::new);
+    let drag = use_state(|| None::<Drag>);
+    // where the dragged block is shown while dragging
+    let preview = use_state(|| None::<(String, u64, u64)>);
+    let selection = use_state(|| None::<Selection>);
+    let issue_select_ref = use_node_ref();
+    let day_start = *day;
+
+    {
+        let worklogs = worklogs.clone();
+        let status = status.clone();
+        use_effect_with((day_start, props.issues.clone()), move |(day_start, _)| {
+            load_day(*day_start, worklogs, status);
+            || ()
+        });
+    }
+
+    let edit = day_action(day_start, worklogs.clone(), status.clone(), |(id, start, end)| {
+        ("edit_worklog", to_value(&EditWorklogArgs { id, start, end }).unwrap())
+    });
+    let reassign = day_action(day_start, worklogs.clone(), status.clone(), |(id, name)| {
+        ("reassign_worklog", to_value(&ReassignWorklogArgs { id, name }).unwrap())
+    });
+    let fill = day_action(day_start, worklogs.clone(), status.clone(), |(name, start, end)| {
+        ("add_manual_worklog", to_value(&ManualWorklogArgs { name, start, end }).unwrap())
+    });
+
+    let top = |seconds: u64| format!("{}px", (seconds.saturating_sub(day_start) as f64 * PX_PER_SECOND).round());
+    let height = |start: u64, end: u64| format!("{}px", ((end.saturating_sub(start)) as f64 * PX_PER_SECOND).round().max(4.0));
+    let issue_name = |issue_id: &str| {
+        props
+            .issues
+            .iter()
+            .find(|issue| issue.id == issue_id)
+            .map(|issue| issue.name.clone())
+            .unwrap_or_default()
+    };
+
+    let onmousemove = {
+        let drag = drag.clone();
+        let preview = preview.clone();
+        move |e: MouseEvent| {
+            if let Some(current) = (*drag).clone() {
+                let dy = e.client_y() - current.origin_y;
+                let (start, end) = current.span(dy);
+                preview.set(Some((current.id.clone(), start, end)));
+                if !current.moved && dy.abs() > 2 {
+                    drag.set(Some(Drag { moved: true, ..current }));
+                }
+            }
+        }
+    };
+    let onmouseup = {
+        let drag = drag.clone();
+        let preview = preview.clone();
+        let selection = selection.clone();
+        let edit = edit.clone();
+        move |e: MouseEvent| {
+            if let Some(current) = (*drag).clone() {
+                match current.moved {
+                    true => {
+                        let (start, end) = current.span(e.client_y() - current.origin_y);
+                        edit.emit((current.id.clone(), start, end));
+                    }
+                    false => selection.set(Some(Selection::Worklog(current.id.clone()))),
+                }
+                preview.set(None);
+                drag.set(None);
+            }
+        }
+    };
+    let onmouseleave = {
+        let drag = drag.clone();
+        let preview = preview.clone();
+        move |_: MouseEvent| {
+            drag.set(None);
+            preview.set(None);
+        }
+    };
+
+    let blocks = worklogs.iter().map(|worklog| {
+        let running = worklog.end == 0;
+        let (start, end) = match &*preview {
+            Some((id, start, end)) if id == &worklog.id => (*start, *end),
+            _ => (worklog.start, if running { now() } else { worklog.end }),
+        };
+        let start_drag = |mode: DragMode| {
+            let drag = drag.clone();
+            let id = worklog.id.clone();
+            let (start, end) = (worklog.start, worklog.end);
+            move |e: MouseEvent| {
+                e.stop_propagation();
+                e.prevent_default();
+                if !running {
+                    drag.set(Some(Drag { id: id.clone(), mode, origin_y: e.client_y(), start, end, moved: false }));
+                }
+            }
+        };
+        let selected = *selection == Some(Selection::Worklog(worklog.id.clone()));
+
+        html! {
+            <div key={worklog.id.clone()}
+                class={classes!("day-block", running.then_some("running"), selected.then_some("selected"))}
+                style={format!("top: {}; height: {};", top(start), height(start, end))}
+                onmousedown={start_drag(DragMode::Move)}>
+                <b>{ issue_name(&worklog.issue_id) }</b> {" "} { format_duration(end - start) }
+                if !running {
+                    <div class="day-block-handle" onmousedown={start_drag(DragMode::Resize)}></div>
+                }
+            </div>
+        }
+    }).collect::<Html>();
+
+    let gap_blocks = gaps(&worklogs).into_iter().map(|(start, end)| {
+        let onclick = {
+            let selection = selection.clone();
+            move |_: MouseEvent| selection.set(Some(Selection::Gap(start, end)))
+        };
+        let selected = *selection == Some(Selection::Gap(start, end));
+
+        html! {
+            <div class={classes!("day-gap", selected.then_some("selected"))} style={format!("top: {}; height: {};", top(start), height(start, end))} {onclick}>
+                {"Gap "} { format_duration(end - start) }
+            </div>
+        }
+    }).collect::<Html>();
+
+    let hours = (0..24).map(|hour| html! {
+        <div class="day-hour" style={format!("top: {}px;", hour * 60)}>{ format!("{:02}:00", hour) }</div>
+    }).collect::<Html>();
+
+    let apply = {
+        let selection = selection.clone();
+        let issue_select_ref = issue_select_ref.clone();
+        let reassign = reassign.clone();
+        let fill = fill.clone();
+        move |_: MouseEvent| {
+            let name = match issue_select_ref.cast::<HtmlSelectElement>() {
+                Some(select) => select.value(),
+                None => return,
+            };
+            match (*selection).clone() {
+                Some(Selection::Worklog(id)) => reassign.emit((id, name)),
+                Some(Selection::Gap(start, end)) => fill.emit((name, start, end)),
+                None => {}
+            }
+            selection.set(None);
+        }
+    };
+    let panel_title = match &*selection {
+        Some(Selection::Worklog(id)) => worklogs
+            .iter()
+            .find(|worklog| &worklog.id == id)
+            .map(|worklog| format!("Log {} from {} on", issue_name(&worklog.issue_id), local_time(worklog.start))),
+        Some(Selection::Gap(start, end)) => Some(format!("Fill {} to {} with", local_time(*start), local_time(*end))),
+        None => None,
+    };
+
+    let change_day = |days: i32| {
+        let day = day.clone();
+        move |_: MouseEvent| day.set(add_days(*day, days))
+    };
+
+    html! {
+        <div class="card day-view">
+            <div>
+                <div class="row wrap-flex">
+                    <button onclick={change_day(-1)}>{"Previous day"}</button>
+                    <h4><b>{ day_label(day_start) } {" "} { day_key(day_start) }</b></h4>
+                    <button onclick={change_day(1)}>{"Next day"}</button>
+                </div>
+                if let Some(title) = panel_title {
+                    <div class="row wrap-flex">
+                        <span>{ title }</span>
+                        <select ref={issue_select_ref}>
+                            { props.issues.iter().map(|issue| html! {
+                                <option value={issue.name.clone()}>{ &issue.name }</option>
+                            }).collect::<Html>() }
+                        </select>
+                        <button onclick={apply}>{"Apply"}</button>
+                    </div>
+                }
+                if !status.is_empty() {
+                    <p class="error">{ &*status }</p>
+                }
+                <div class="day-scroll">
+                    <div class="day-timeline" style={format!("height: {};", height(day_start, add_days(day_start, 1)))}
+                        {onmousemove} {onmouseup} {onmouseleave}>
+                        { hours }
+                        { gap_blocks }
+                        { blocks }
+                    </div>
+                </div>
+            </div>
+        </div>
+    }
+}
@@ -0,0 +1,121 @@
+use serde::{Deserialize, Serialize};
+use shared::{DurationFormat, ExportColumn, ExportFormat, ExportOptions};
+use wasm_bindgen_futures::spawn_local;
+use web_sys::{HtmlInputElement, HtmlSelectElement};
+use yew::prelude::*;
+
+use crate::backend::call;
+use crate::time::{add_days, date_input_time, day_key, month_start, now};
+
+#[derive(Serialize, Deserialize)]
+struct ExportArgs {
+    options: ExportOptions,
+}
+
+#[function_component(ExportForm)]
+pub fn export_form() -> Html {
+    // where the last export went or why it failed
+    let status = use_state(String::new);
+    // the month so far
+    let options = use_state(|| ExportOptions {
+        from: month_start(now()),
+        to: add_days(now(), 1),
+        ..ExportOptions::default()
+    });
+
+    let edit = |apply: fn(&mut ExportOptions, String)| {
+        let options = options.clone();
+        move |e: Event| {
+            let value = match e.target_dyn_into::<HtmlSelectElement>() {
+                Some(select) => select.value(),
+                None => e.target_unchecked_into::<HtmlInputElement>().value(),
+            };
+            let mut changed = (*options).clone();
+            apply(&mut changed, value);
+            options.set(changed);
+        }
+    };
+
+    let toggle_column = |column: ExportColumn| {
+        let options = options.clone();
+        move |_: Event| {
+            let mut changed = (*options).clone();
+            match changed.columns.contains(&column) {
+                true => changed.columns.retain(|shown| *shown != column),
+                false => {
+                    // in the order they are listed
+                    changed.columns.push(column);
+                    changed.columns.sort_by_key(|shown| ExportColumn::ALL.iter().position(|c| c == shown));
+                }
+            }
+            options.set(changed);
+        }
+    };
+
+    let on_summit = {
+        let options = options.clone();
+        let status = status.clone();
+        move |e: SubmitEvent| {
+            e.prevent_default();
+            let options = (*options).clone();
+            let status = status.clone();
+            spawn_local(async move {
+                status.set("Exporting...".to_string());
+                match call::<String>("export_worklogs", &ExportArgs { options }).await {
+                    Ok(path) => status.set(format!("Written to {}", path)),
+                    Err(err) => status.set(err),
+                }
+            });
+        }
+    };
+
+    html! {
+        <div class="card">
+            <div>
+                <h4><b>{"Export worklogs"}</b></h4>
+                <form class="column wrap-flex" onsubmit={on_summit}>
+                    <div class="row wrap-flex">
+                        <input type="date" value={day_key(options.from)} onchange={edit(|o, v| if let Some(from) = date_input_time(&v) { o.from = from })} />
+                        <input type="date" value={day_key(add_days(options.to, -1))} onchange={edit(|o, v| if let Some(to) = date_input_time(&v) { o.to = add_days(to, 1) })} />
+                        <select onchange={edit(|o, v| o.format = match v.as_str() {
+                            "json" => ExportFormat::Json,
+                            "ics" => ExportFormat::Ics,
+                            _ => ExportFormat::Csv,
+                        })}>
+                            <option value="csv" selected={options.format == ExportFormat::Csv}>{"CSV"}</option>
+                            <option value="json" selected={options.format == ExportFormat::Json}>{"JSON"}</option>
+                            <option value="ics" selected={options.format == ExportFormat::Ics}>{"iCalendar"}</option>
+                        </select>
+                        if options.format != ExportFormat::Ics {
+                            <select onchange={edit(|o, v| o.duration_format = match v.as_str() {
+                                "decimal_hours" => DurationFormat::DecimalHours,
+                                "hours_minutes" => DurationFormat::HoursMinutes,
+                                _ => DurationFormat::Seconds,
+                            })}>
+                                <option value="seconds" selected={options.duration_format == DurationFormat::Seconds}>{"Seconds"}</option>
+                                <option value="decimal_hours" selected={options.duration_format == DurationFormat::DecimalHours}>{"Decimal hours"}</option>
+                                <option value="hours_minutes" selected={options.duration_format == DurationFormat::HoursMinutes}>{"1h 30m"}</option>
+                            </select>
+                            <input placeholder="Time zone: local, utc, Europe/Berlin or +02:00" title="Time zone" value={options.time_zone.clone()} onchange={edit(|o, v| o.time_zone = v)} />
+                        }
+                    </div>
+                    if options.format != ExportFormat::Ics {
+                        <div class="row wrap-flex">
+                            { ExportColumn::ALL.iter().map(|column| html! {
+                                <label>
+                                    <input type="checkbox" checked={options.columns.contains(column)} onchange={toggle_column(*column)} />
+                                    { column.name() }
+                                </label>
+                            }).collect::<Html>() }
+                        </div>
+                    }
+                    <input placeholder="File, empty for the downloads directory" value={options.path.clone().unwrap_or_default()} onchange={edit(|o, v| o.path = Some(v).filter(|v| !v.trim().is_empty()))} />
+                    <button type="submit">{"Export"}</button>
+                </form>
+                if !status.is_empty() {
+                    <p class="settings-status">{ &*status }</p>
+                }
+            </div>
+        </div>
+    }
+}
@@ -0,0 +1,53 @@
+use shared::{format_duration, IdleAction, IdlePeriod, Issue};
+use web_sys::HtmlSelectElement;
+use yew::prelude::*;
+
+#[derive(Properties, PartialEq)]
+pub struct IdleCardProps {
+    pub period: IdlePeriod,
+    /// issues the idle time can be assigned to
+    pub issues: Vec<Issue>,
+    pub tracking_issue: String,
+    pub resolve: Callback<IdleAction>,
+}
+
+#[function_component(IdleCard)]
+pub fn idle_card(props: &IdleCardProps) -> Html {
+    let issue_select_ref = use_node_ref();
+    let away = props.period.end.unwrap_or(props.period.start) - props.period.start;
+
+    let keep = {
+        let resolve = props.resolve.clone();
+        move |_: MouseEvent| resolve.emit(IdleAction::Keep)
+    };
+    let discard = {
+        let resolve = props.resolve.clone();
+        move |_: MouseEvent| resolve.emit(IdleAction::Discard)
+    };
+    let assign = {
+        let resolve = props.resolve.clone();
+        let issue_select_ref = issue_select_ref.clone();
+        move |_: MouseEvent| {
+            if let Some(select) = issue_select_ref.cast::<HtmlSelectElement>() {
+                resolve.emit(IdleAction::Assign(select.value()));
+            }
+        }
+    };
+
+    html! {
+        <div class="card">
+            <div>
+                <h4><b>{"You were away"}</b></h4>
+                <p>{ format_duration(away) } {" while tracking "} { &props.tracking_issue }</p>
+                <button onclick={keep}>{"Keep"}</button>
+                <button onclick={discard}>{"Discard"}</button>
+                <select ref={issue_select_ref}>
+                    { props.issues.iter().filter(|issue| issue.name != props.tracking_issue).map(|issue| html! {
+                        <option value={issue.name.clone()}>{ &issue.name }</option>
+                    }).collect::<Html>() }
+                </select>
+                <button onclick={assign}>{"Assign"}</button>
+            </div>
+        </div>
+    }
+}
@@ -0,0 +1,33 @@
+use yew::prelude::*;
+
+#[derive(Properties, PartialEq)]
+pub struct ImportIssuesProps {
+    pub import_issues: Callback<Option<String>>,
+}
+
+#[function_component(ImportIssuesForm)]
+pub fn import_issues_form(props: &ImportIssuesProps) -> Html {
+    let import_issues = props.import_issues.clone();
+    let query_input_ref = use_node_ref();
+
+    let on_summit = {
+        let query_input_ref = query_input_ref.clone();
+        move |e: SubmitEvent| {
+            e.prevent_default();
+            let query = query_input_ref
+                .cast::<web_sys::HtmlInputElement>()
+                .unwrap()
+                .value();
+            import_issues.emit(Some(query).filter(|query| !query.trim().is_empty()));
+        }
+    };
+
+    html! {
+        <div>
+            <form class="column wrap-flex" onsubmit={on_summit}>
+                <input ref={query_input_ref} placeholder="Query, empty for my open issues" />
+            <button type="submit">{"Import issues"}</button>
+            </form>
+        </div>
+    }
+}
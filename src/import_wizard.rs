@@ -0,0 +1,293 @@
+use serde::{Deserialize, Serialize};
+use shared::{format_duration, ImportBatch, ImportMapping, ImportPreset, ImportPreview, ImportRowStatus, Issue};
+use wasm_bindgen_futures::{spawn_local, JsFuture};
+use web_sys::{HtmlInputElement, HtmlSelectElement};
+use yew::prelude::*;
+
+use crate::backend::{call, EmptyArgs, WorklogIdArgs};
+use crate::time::local_time;
+
+#[derive(Properties, PartialEq)]
+pub struct ImportWizardProps {
+    /// the preview and the imports are loaded again when they change, as after an import or undo
+    pub issues: Vec<Issue>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PreviewImportArgs {
+    contents: String,
+    mapping: ImportMapping,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ImportWorklogsArgs {
+    contents: String,
+    source: String,
+    mapping: ImportMapping,
+}
+
+fn load_batches(batches: UseStateHandle<Vec<ImportBatch>>, status: UseStateHandle<String>) {
+    spawn_local(async move {
+        match call("get_import_batches", &EmptyArgs {}).await {
+            Ok(v) => batches.set(v),
+            Err(err) => status.set(err),
+        }
+    });
+}
+
+fn column_select(label: &'static str, headers: &[String], selected: &str, optional: bool, edit: Callback<String>) -> Html {
+    let onchange = move |e: Event| edit.emit(e.target_unchecked_into::<HtmlSelectElement>().value());
+
+    html! {
+        <label>{ label }
+            <select {onchange}>
+                <option value="" selected={selected.is_empty()}>{ if optional { "(none)" } else { "pick a column" } }</option>
+                { headers.iter().map(|header| html! {
+                    <option value={header.clone()} selected={header.trim() == selected}>{ header }</option>
+                }).collect::<Html>() }
+            </select>
+        </label>
+    }
+}
+
+fn status_name(status: &ImportRowStatus) -> String {
+    match status {
+        ImportRowStatus::New => "new".to_string(),
+        ImportRowStatus::Duplicate => "already there".to_string(),
+        ImportRowStatus::Overlap(conflict) => conflict.clone(),
+        ImportRowStatus::Invalid(reason) => reason.clone(),
+    }
+}
+
+#[function_component(ImportWizard)]
+pub fn import_wizard(props: &ImportWizardProps) -> Html {
+    let file_name = use_state(String::new);
+    let contents = use_state(String::new);
+    let preset = use_state(ImportPreset::default);
+    let mapping = use_state(ImportMapping::default);
+    // what the file would bring with the mapping
+    let preview = use_state(ImportPreview::default);
+    let batches = use_state(Vec::new);
+    let status = use_state(String::new);
+
+    {
+        let batches = batches.clone();
+        let status = status.clone();
+        use_effect_with(props.issues.clone(), move |_| {
+            load_batches(batches, status);
+            || ()
+        });
+    }
+
+    {
+        // every change of the file or the mapping is previewed, nothing is written until import;
+        // the worklogs decide what is already there, so it is previewed again once they changed
+        let preview = preview.clone();
+        let status = status.clone();
+        use_effect_with(((*contents).clone(), (*mapping).clone(), props.issues.clone()), move |(contents, mapping, _)| {
+            if !contents.is_empty() {
+                let args = PreviewImportArgs { contents: contents.clone(), mapping: mapping.clone() };
+                spawn_local(async move {
+                    match call("preview_import", &args).await {
+                        Ok(v) => preview.set(v),
+                        Err(err) => status.set(err),
+                    }
+                });
+            }
+            || ()
+        });
+    }
+
+    let on_file = {
+        let file_name = file_name.clone();
+        let contents = contents.clone();
+        move |e: Event| {
+            let file = match e.target_unchecked_into::<HtmlInputElement>().files().and_then(|files| files.get(0)) {
+                Some(file) => file,
+                None => return,
+            };
+            let file_name = file_name.clone();
+            let contents = contents.clone();
+            spawn_local(async move {
+                if let Ok(text) = JsFuture::from(file.text()).await {
+                    file_name.set(file.name());
+                    contents.set(text.as_string().unwrap_or_default());
+                }
+            });
+        }
+    };
+
+    let on_preset = {
+        let preset = preset.clone();
+        let mapping = mapping.clone();
+        move |e: Event| {
+            let value = e.target_unchecked_into::<HtmlSelectElement>().value();
+            let chosen = ImportPreset::ALL.into_iter().find(|p| p.name() == value).unwrap_or_default();
+            if let Some(columns) = chosen.mapping() {
+                // the columns come from the preset, the rest stays as set
+                mapping.set(ImportMapping {
+                    delimiter: mapping.delimiter,
+                    time_zone: mapping.time_zone.clone(),
+                    push_to_tracker: mapping.push_to_tracker,
+                    import_overlapping: mapping.import_overlapping,
+                    ..columns
+                });
+            }
+            preset.set(chosen);
+        }
+    };
+
+    let edit = |apply: fn(&mut ImportMapping, String)| {
+        let mapping = mapping.clone();
+        Callback::from(move |value: String| {
+            let mut changed = (*mapping).clone();
+            apply(&mut changed, value);
+            mapping.set(changed);
+        })
+    };
+    let on_input = |edit: Callback<String>| move |e: Event| edit.emit(e.target_unchecked_into::<HtmlInputElement>().value());
+    let on_check = |edit: Callback<String>| {
+        move |e: Event| edit.emit(e.target_unchecked_into::<HtmlInputElement>().checked().to_string())
+    };
+
+    // issues-changed brings the preview and the imports up to date afterwards
+    let on_import = {
+        let file_name = file_name.clone();
+        let contents = contents.clone();
+        let mapping = mapping.clone();
+        let status = status.clone();
+        move |_: MouseEvent| {
+            let args = ImportWorklogsArgs { contents: (*contents).clone(), source: (*file_name).clone(), mapping: (*mapping).clone() };
+            let status = status.clone();
+            spawn_local(async move {
+                match call::<ImportBatch>("import_worklogs", &args).await {
+                    Ok(batch) => status.set(format!("Imported {} worklogs and {} new issues", batch.worklogs, batch.issues)),
+                    Err(err) => status.set(err),
+                }
+            });
+        }
+    };
+
+    let undo = {
+        let status = status.clone();
+        Callback::from(move |id: String| {
+            let status = status.clone();
+            spawn_local(async move {
+                match call::<()>("undo_import", &WorklogIdArgs { id }).await {
+                    Ok(_) => status.set("The import is undone".to_string()),
+                    Err(err) => status.set(err),
+                }
+            });
+        })
+    };
+
+    let count = |wanted: fn(&ImportRowStatus) -> bool| preview.rows.iter().filter(|row| wanted(&row.status)).count();
+    let new = count(|status| *status == ImportRowStatus::New);
+    let duplicates = count(|status| *status == ImportRowStatus::Duplicate);
+    let overlapping = count(|status| matches!(status, ImportRowStatus::Overlap(_)));
+    let invalid = count(|status| matches!(status, ImportRowStatus::Invalid(_)));
+    let headers = &preview.headers;
+
+    html! {
+        <div class="card import">
+            <div>
+                <h4><b>{"Import worklogs"}</b></h4>
+                <div class="row wrap-flex">
+                    <input type="file" accept=".csv,text/csv" onchange={on_file} />
+                    <select onchange={on_preset}>
+                        { ImportPreset::ALL.iter().map(|p| html! {
+                            <option value={p.name()} selected={*p == *preset}>{ p.name() }</option>
+                        }).collect::<Html>() }
+                    </select>
+                    <select onchange={on_input(edit(|m, v| m.delimiter = v.chars().next().unwrap_or(',')))}>
+                        { [(',', "comma"), (';', "semicolon"), ('\t', "tab")].iter().map(|(delimiter, name)| html! {
+                            <option value={delimiter.to_string()} selected={*delimiter == mapping.delimiter}>{ *name }</option>
+                        }).collect::<Html>() }
+                    </select>
+                </div>
+                if !contents.is_empty() {
+                    <div class="row wrap-flex">
+                        { column_select("Issue key ", headers, &mapping.issue_key, false, edit(|m, v| m.issue_key = v)) }
+                        <label>
+                            <input type="checkbox" checked={mapping.extract_key} onchange={on_check(edit(|m, v| m.extract_key = v == "true"))} />
+                            {" Find the key like ABC-123 in it"}
+                        </label>
+                        { column_select("Summary ", headers, mapping.summary.as_deref().unwrap_or_default(), true, edit(|m, v| m.summary = Some(v).filter(|v| !v.is_empty()))) }
+                    </div>
+                    <div class="row wrap-flex">
+                        { column_select("Start date ", headers, &mapping.start_date, false, edit(|m, v| m.start_date = v)) }
+                        { column_select("Start time ", headers, mapping.start_time.as_deref().unwrap_or_default(), true, edit(|m, v| m.start_time = Some(v).filter(|v| !v.is_empty()))) }
+                        { column_select("End date ", headers, mapping.end_date.as_deref().unwrap_or_default(), true, edit(|m, v| m.end_date = Some(v).filter(|v| !v.is_empty()))) }
+                        { column_select("End time ", headers, mapping.end_time.as_deref().unwrap_or_default(), true, edit(|m, v| m.end_time = Some(v).filter(|v| !v.is_empty()))) }
+                        { column_select("Duration ", headers, mapping.duration.as_deref().unwrap_or_default(), true, edit(|m, v| m.duration = Some(v).filter(|v| !v.is_empty()))) }
+                    </div>
+                    <div class="row wrap-flex">
+                        <input placeholder="Date format, e.g. %Y-%m-%d" title="Date format" value={mapping.date_format.clone()} onchange={on_input(edit(|m, v| m.date_format = v))} />
+                        <input placeholder="Time zone: local, utc, Europe/Berlin or +02:00" title="Time zone" value={mapping.time_zone.clone()} onchange={on_input(edit(|m, v| m.time_zone = v))} />
+                        <label>
+                            <input type="checkbox" checked={mapping.push_to_tracker} onchange={on_check(edit(|m, v| m.push_to_tracker = v == "true"))} />
+                            {" Send to the tracker"}
+                        </label>
+                        <label>
+                            <input type="checkbox" checked={mapping.import_overlapping} onchange={on_check(edit(|m, v| m.import_overlapping = v == "true"))} />
+                            {" Import overlapping worklogs too"}
+                        </label>
+                    </div>
+                    if let Some(error) = &preview.error {
+                        <p class="error">{ error }</p>
+                    } else {
+                        <p>
+                            { format!("{} new, {} already there, {} overlapping, {} invalid", new, duplicates, overlapping, invalid) }
+                            if !preview.new_issues.is_empty() {
+                                { format!(". Creates {}", preview.new_issues.join(", ")) }
+                            }
+                        </p>
+                        <button onclick={on_import} disabled={new == 0}>{ format!("Import {} worklogs", new) }</button>
+                        <table>
+                            <tr>
+                                <th>{"Line"}</th>
+                                <th>{"Issue"}</th>
+                                <th>{"Start"}</th>
+                                <th>{"End"}</th>
+                                <th>{"Time"}</th>
+                                <th></th>
+                            </tr>
+                            { preview.rows.iter().map(|row| html! {
+                                <tr class={classes!((row.status != ImportRowStatus::New).then_some("import-skipped"))}>
+                                    <td>{ row.line }</td>
+                                    <td>{ &row.issue_key }</td>
+                                    if row.end > row.start {
+                                        <td>{ local_time(row.start) }</td>
+                                        <td>{ local_time(row.end) }</td>
+                                        <td>{ format_duration(row.end - row.start) }</td>
+                                    } else {
+                                        <td></td>
+                                        <td></td>
+                                        <td></td>
+                                    }
+                                    <td>{ status_name(&row.status) }</td>
+                                </tr>
+                            }).collect::<Html>() }
+                        </table>
+                    }
+                }
+                if !status.is_empty() {
+                    <p class="settings-status">{ &*status }</p>
+                }
+                if !batches.is_empty() {
+                    <h4><b>{"Imports"}</b></h4>
+                    { batches.iter().map(|batch| {
+                        let undo = undo.clone();
+                        let id = batch.id.clone();
+                        html! {
+                            <div class="row wrap-flex import-batch">
+                                <span>{ format!("{} on {}: {} worklogs, {} new issues", batch.source, local_time(batch.created), batch.worklogs, batch.issues) }</span>
+                                <button onclick={move |_: MouseEvent| undo.emit(id.clone())}>{"Undo"}</button>
+                            </div>
+                        }
+                    }).collect::<Html>() }
+                }
+            </div>
+        </div>
+    }
+}
@@ -0,0 +1,182 @@
+use serde::{Deserialize, Serialize};
+use serde_wasm_bindgen::to_value;
+use shared::{format_duration, Issue, Worklog};
+use wasm_bindgen::JsValue;
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+
+use crate::backend::{call, try_invoke, EditWorklogArgs, ManualWorklogArgs, WorklogIdArgs};
+use crate::time::{input_time, input_value, local_time};
+
+#[derive(Properties, PartialEq)]
+pub struct IssueDetailsProps {
+    /// its worklogs are loaded again when it changes, that is with every finished worklog
+    pub issue: Issue,
+    pub close: Callback<()>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct IssueNameArgs {
+    name: String,
+}
+
+fn load_worklogs(name: String, worklogs: UseStateHandle<Vec<Worklog>>, status: UseStateHandle<String>) {
+    spawn_local(async move {
+        match call("get_issue_worklogs", &IssueNameArgs { name }).await {
+            Ok(v) => worklogs.set(v),
+            Err(err) => status.set(err),
+        }
+    });
+}
+
+/// Runs a worklog command for the issue and loads its worklogs again.
+fn worklog_action<T: 'static>(
+    name: String,
+    worklogs: UseStateHandle<Vec<Worklog>>,
+    status: UseStateHandle<String>,
+    command: impl Fn(String, T) -> (&'static str, JsValue) + 'static,
+) -> Callback<T> {
+    Callback::from(move |value: T| {
+        let (cmd, args) = command(name.clone(), value);
+        let name = name.clone();
+        let worklogs = worklogs.clone();
+        let status = status.clone();
+        spawn_local(async move {
+            match try_invoke(cmd, args).await {
+                Ok(_) => status.set(String::new()),
+                Err(err) => status.set(err.as_string().unwrap_or_default()),
+            }
+            load_worklogs(name, worklogs, status);
+        });
+    })
+}
+
+#[derive(Properties, PartialEq)]
+struct WorklogRowProps {
+    worklog: Worklog,
+    edit: Callback<(String, u64, u64)>,
+    delete: Callback<String>,
+}
+
+#[function_component(WorklogRow)]
+fn worklog_row(props: &WorklogRowProps) -> Html {
+    let start_input_ref = use_node_ref();
+    let end_input_ref = use_node_ref();
+    let worklog = &props.worklog;
+
+    if worklog.end == 0 {
+        return html! {
+            <tr>
+                <td>{ local_time(worklog.start) }</td>
+                <td>{"running"}</td>
+                <td></td>
+                <td></td>
+            </tr>
+        };
+    }
+
+    let save = {
+        let edit = props.edit.clone();
+        let id = worklog.id.clone();
+        let start_input_ref = start_input_ref.clone();
+        let end_input_ref = end_input_ref.clone();
+        move |_: MouseEvent| {
+            if let (Some(start), Some(end)) = (input_time(&start_input_ref), input_time(&end_input_ref)) {
+                edit.emit((id.clone(), start, end));
+            }
+        }
+    };
+    let delete = {
+        let delete = props.delete.clone();
+        let id = worklog.id.clone();
+        move |_: MouseEvent| delete.emit(id.clone())
+    };
+
+    html! {
+        <tr>
+            <td><input type="datetime-local" ref={start_input_ref} value={input_value(worklog.start)} /></td>
+            <td><input type="datetime-local" ref={end_input_ref} value={input_value(worklog.end)} /></td>
+            <td>{ format_duration(worklog.total_time) } if worklog.synced { {" ✓"} }</td>
+            <td>
+                <button onclick={save}>{"Save"}</button>
+                <button onclick={delete}>{"Delete"}</button>
+            </td>
+        </tr>
+    }
+}
+
+#[function_component(IssueDetails)]
+pub fn issue_details(props: &IssueDetailsProps) -> Html {
+    let start_input_ref = use_node_ref();
+    let end_input_ref = use_node_ref();
+    let worklogs = use_state(Vec::new);
+    // result of the last change
+    let status = use_state(String::new);
+    let name = props.issue.name.to_string();
+
+    {
+        let worklogs = worklogs.clone();
+        let status = status.clone();
+        use_effect_with(props.issue.clone(), move |issue| {
+            load_worklogs(issue.name.to_string(), worklogs, status);
+            || ()
+        });
+    }
+
+    // worklog id, start and end
+    let edit = worklog_action(name.clone(), worklogs.clone(), status.clone(), |_, (id, start, end)| {
+        ("edit_worklog", to_value(&EditWorklogArgs { id, start, end }).unwrap())
+    });
+    let delete = worklog_action(name.clone(), worklogs.clone(), status.clone(), |_, id| {
+        ("delete_worklog", to_value(&WorklogIdArgs { id }).unwrap())
+    });
+    // start and end of time logged by hand
+    let add_manual = worklog_action(name, worklogs.clone(), status.clone(), |name, (start, end)| {
+        ("add_manual_worklog", to_value(&ManualWorklogArgs { name, start, end }).unwrap())
+    });
+
+    let close = {
+        let close = props.close.clone();
+        move |_: MouseEvent| close.emit(())
+    };
+    let add = {
+        let add = add_manual.clone();
+        let start_input_ref = start_input_ref.clone();
+        let end_input_ref = end_input_ref.clone();
+        move |e: SubmitEvent| {
+            e.prevent_default();
+            if let (Some(start), Some(end)) = (input_time(&start_input_ref), input_time(&end_input_ref)) {
+                add.emit((start, end));
+            }
+        }
+    };
+
+    html! {
+        <div class="card issue-details">
+            <div>
+                <button onclick={close}>{"Back"}</button>
+                <h4><b>{ &*props.issue.name }</b></h4>
+                <p>{ &*props.issue.summary }</p>
+                <p>{"Tracked: "} { format_duration(props.issue.time_tracked_all) }</p>
+                <table>
+                    <thead>
+                        <tr><th>{"Start"}</th><th>{"End"}</th><th>{"Time"}</th><th></th></tr>
+                    </thead>
+                    <tbody>
+                        { worklogs.iter().map(|worklog| html! {
+                            <WorklogRow key={worklog.id.clone()} worklog={worklog.clone()} edit={edit.clone()} delete={delete.clone()} />
+                        }).collect::<Html>() }
+                    </tbody>
+                </table>
+                <form class="row wrap-flex" onsubmit={add}>
+                    <input type="datetime-local" ref={start_input_ref} />
+                    <input type="datetime-local" ref={end_input_ref} />
+                    <button type="submit">{"Add time"}</button>
+                </form>
+                if !status.is_empty() {
+                    <p class="error">{ &*status }</p>
+                }
+            </div>
+        </div>
+    }
+}
@@ -1,6 +1,20 @@
 mod add_issue_form;
 mod app;
+mod backend;
+mod calendar_import;
 mod card;
+mod day_view;
+mod export_form;
+mod idle_card;
+mod import_issues_form;
+mod import_wizard;
+mod issue_details;
+mod outbox_card;
+mod recovery_card;
+mod report_view;
+mod settings_form;
+mod time;
+mod timesheet;
 mod tracking_card;
 
 use app::App;
@@ -0,0 +1,47 @@
+use shared::OutboxStatus;
+use yew::prelude::*;
+
+#[derive(Properties, PartialEq)]
+pub struct OutboxCardProps {
+    pub status: OutboxStatus,
+    pub retry: Callback<i64>,
+    pub discard: Callback<i64>,
+}
+
+#[function_component(OutboxCard)]
+pub fn outbox_card(props: &OutboxCardProps) -> Html {
+    let failed = props.status.failed.iter().map(|item| {
+        let retry = {
+            let retry = props.retry.clone();
+            let id = item.id;
+            move |_: MouseEvent| retry.emit(id)
+        };
+        let discard = {
+            let discard = props.discard.clone();
+            let id = item.id;
+            move |_: MouseEvent| discard.emit(id)
+        };
+
+        html! {
+            <div class="outbox-item">
+                <p><b>{ &item.kind } {" "} { &item.issue_key }</b></p>
+                <p class="error">{ item.last_error.clone().unwrap_or_default() }</p>
+                <button onclick={retry}>{"Retry"}</button>
+                <button onclick={discard}>{"Discard"}</button>
+            </div>
+        }
+    }).collect::<Html>();
+
+    html! {
+        <div class="card">
+            <div>
+                <h4><b>{"Tracker sync"}</b></h4>
+                <p>{"Pending: "} { props.status.pending }</p>
+                if !props.status.failed.is_empty() {
+                    <p>{"Failed: "} { props.status.failed.len() }</p>
+                    { failed }
+                }
+            </div>
+        </div>
+    }
+}
@@ -0,0 +1,77 @@
+use shared::OpenWorklog;
+use yew::prelude::*;
+
+use crate::time::{input_time, local_time};
+
+#[derive(Properties, PartialEq)]
+pub struct RecoveryCardProps {
+    pub open_worklogs: Vec<OpenWorklog>,
+    /// worklog id and the end, the last heartbeat when `None`
+    pub close: Callback<(String, Option<u64>)>,
+    pub discard: Callback<String>,
+    pub status: String,
+}
+
+#[derive(Properties, PartialEq)]
+struct OpenWorklogItemProps {
+    open: OpenWorklog,
+    close: Callback<(String, Option<u64>)>,
+    discard: Callback<String>,
+}
+
+#[function_component(OpenWorklogItem)]
+fn open_worklog_item(props: &OpenWorklogItemProps) -> Html {
+    let end_input_ref = use_node_ref();
+    let open = &props.open;
+
+    let close_at_heartbeat = {
+        let close = props.close.clone();
+        let id = open.worklog.id.clone();
+        move |_: MouseEvent| close.emit((id.clone(), None))
+    };
+    let close_at = {
+        let close = props.close.clone();
+        let id = open.worklog.id.clone();
+        let end_input_ref = end_input_ref.clone();
+        move |_: MouseEvent| {
+            if let Some(end) = input_time(&end_input_ref) {
+                close.emit((id.clone(), Some(end)));
+            }
+        }
+    };
+    let discard = {
+        let discard = props.discard.clone();
+        let id = open.worklog.id.clone();
+        move |_: MouseEvent| discard.emit(id.clone())
+    };
+
+    html! {
+        <div class="outbox-item">
+            <p><b>{ &open.issue_name }</b> {" "} { &open.issue_summary }</p>
+            <p class="issue-meta">{"Started: "} { local_time(open.worklog.start) }</p>
+            <p class="issue-meta">{"Last seen: "} { local_time(open.last_heartbeat) }</p>
+            <button onclick={close_at_heartbeat}>{"Close at last seen"}</button>
+            <input type="datetime-local" ref={end_input_ref} />
+            <button onclick={close_at}>{"Close at"}</button>
+            <button onclick={discard}>{"Discard"}</button>
+        </div>
+    }
+}
+
+#[function_component(RecoveryCard)]
+pub fn recovery_card(props: &RecoveryCardProps) -> Html {
+    html! {
+        <div class="card">
+            <div>
+                <h4><b>{"Unfinished worklogs"}</b></h4>
+                <p>{"These were still running when the app last stopped."}</p>
+                { props.open_worklogs.iter().map(|open| html! {
+                    <OpenWorklogItem open={open.clone()} close={props.close.clone()} discard={props.discard.clone()} />
+                }).collect::<Html>() }
+                if !props.status.is_empty() {
+                    <p class="error">{ &props.status }</p>
+                }
+            </div>
+        </div>
+    }
+}
@@ -0,0 +1,124 @@
+use serde::{Deserialize, Serialize};
+use shared::{format_duration, Issue, Report, ReportGrouping};
+use wasm_bindgen_futures::spawn_local;
+use web_sys::{HtmlInputElement, HtmlSelectElement};
+use yew::prelude::*;
+
+use crate::backend::call;
+use crate::time::{add_days, date_input_time, day_key, now, week_start};
+
+#[derive(Properties, PartialEq)]
+pub struct ReportViewProps {
+    /// the report shown is run again with the latest worklogs when they change
+    pub issues: Vec<Issue>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ReportArgs {
+    from: u64,
+    to: u64,
+    grouping: ReportGrouping,
+}
+
+fn run_report(args: ReportArgs, report: UseStateHandle<Report>, status: UseStateHandle<String>) {
+    spawn_local(async move {
+        match call("get_report", &args).await {
+            Ok(v) => {
+                report.set(v);
+                status.set(String::new());
+            }
+            Err(err) => status.set(err),
+        }
+    });
+}
+
+#[function_component(ReportView)]
+pub fn report_view(props: &ReportViewProps) -> Html {
+    let report = use_state(|| {
+        let from = week_start(now());
+        Report { from, to: add_days(from, 7), ..Report::default() }
+    });
+    let status = use_state(String::new);
+    let from_input_ref = use_node_ref();
+    let to_input_ref = use_node_ref();
+    let grouping_select_ref = use_node_ref();
+
+    {
+        let report = report.clone();
+        let status = status.clone();
+        use_effect_with(props.issues.clone(), move |_| {
+            run_report(ReportArgs { from: report.from, to: report.to, grouping: report.grouping }, report, status);
+            || ()
+        });
+    }
+
+    let on_summit = {
+        let report = report.clone();
+        let status = status.clone();
+        let from_input_ref = from_input_ref.clone();
+        let to_input_ref = to_input_ref.clone();
+        let grouping_select_ref = grouping_select_ref.clone();
+        move |e: SubmitEvent| {
+            e.prevent_default();
+            let value = |input: &NodeRef| input.cast::<HtmlInputElement>().map(|input| input.value()).unwrap_or_default();
+            let grouping = grouping_select_ref
+                .cast::<HtmlSelectElement>()
+                .and_then(|select| ReportGrouping::ALL.into_iter().find(|grouping| grouping.name() == select.value()))
+                .unwrap_or_default();
+
+            // the last day is part of the report
+            if let (Some(from), Some(to)) = (date_input_time(&value(&from_input_ref)), date_input_time(&value(&to_input_ref))) {
+                run_report(ReportArgs { from, to: add_days(to, 1), grouping }, report.clone(), status.clone());
+            }
+        }
+    };
+
+    let report = &*report;
+    let last_day = add_days(report.to, -1);
+
+    html! {
+        <div class="card report">
+            <div>
+                <form class="row wrap-flex" onsubmit={on_summit}>
+                    <input type="date" ref={from_input_ref} value={day_key(report.from)} />
+                    <input type="date" ref={to_input_ref} value={day_key(last_day)} />
+                    <select ref={grouping_select_ref}>
+                        { ReportGrouping::ALL.iter().map(|grouping| html! {
+                            <option value={grouping.name()} selected={*grouping == report.grouping}>{"By "} { grouping.name() }</option>
+                        }).collect::<Html>() }
+                    </select>
+                    <button type="submit">{"Show"}</button>
+                </form>
+                if !status.is_empty() {
+                    <p class="error">{ &*status }</p>
+                }
+                <table>
+                    <thead>
+                        <tr><th>{ report.grouping.name() }</th><th>{"Time"}</th><th>{"%"}</th><th></th></tr>
+                    </thead>
+                    <tbody>
+                        { report.rows.iter().map(|row| html! {
+                            <tr key={row.key.clone()}>
+                                <td>{ &row.key }</td>
+                                <td>{ format_duration(row.seconds) }</td>
+                                <td>{ format!("{:.1}", row.percent) }</td>
+                                <td class="report-bar-cell"><div class="report-bar" style={format!("width: {:.1}%;", row.percent.min(100.0))}></div></td>
+                            </tr>
+                        }).collect::<Html>() }
+                    </tbody>
+                    <tfoot>
+                        <tr>
+                            <td><b>{"Total"}</b></td>
+                            <td><b>{ format_duration(report.total) }</b></td>
+                            <td></td>
+                            <td></td>
+                        </tr>
+                    </tfoot>
+                </table>
+                if report.grouping == ReportGrouping::Tag {
+                    <p class="issue-meta">{"Issues with several tags count for each, the percentages can add up to more than 100."}</p>
+                }
+            </div>
+        </div>
+    }
+}
@@ -0,0 +1,224 @@
+use shared::{
+    AppConfig, GithubConfig, GithubWorklogMode, GitlabConfig, JiraConfig, RoundingMode, TrackerConfig, TrayTick,
+};
+use web_sys::{HtmlInputElement, HtmlSelectElement};
+use yew::prelude::*;
+
+#[derive(Properties, PartialEq)]
+pub struct SettingsFormProps {
+    pub config: AppConfig,
+    pub save: Callback<AppConfig>,
+    pub test_connection: Callback<TrackerConfig>,
+    /// compares the issue totals with the worklogs, repairs them when true
+    pub check_totals: Callback<bool>,
+    /// result of the last save or connection test
+    pub status: String,
+}
+
+/// Changes the draft with the value of the input.
+fn edit(draft: &UseStateHandle<AppConfig>, apply: impl Fn(&mut AppConfig, String) + 'static) -> Callback<String> {
+    let draft = draft.clone();
+    Callback::from(move |value: String| {
+        let mut config = (*draft).clone();
+        apply(&mut config, value);
+        draft.set(config);
+    })
+}
+
+fn input(placeholder: &'static str, value: String, password: bool, edit: Callback<String>) -> Html {
+    let oninput = move |e: InputEvent| edit.emit(e.target_unchecked_into::<HtmlInputElement>().value());
+    let kind = if password { "password" } else { "text" };
+
+    html! {
+        <input type={kind} {placeholder} title={placeholder} {value} {oninput} />
+    }
+}
+
+fn select(options: &[&'static str], selected: &str, edit: Callback<String>) -> Html {
+    let onchange = move |e: Event| edit.emit(e.target_unchecked_into::<HtmlSelectElement>().value());
+
+    html! {
+        <select {onchange}>
+            { options.iter().map(|option| html! {
+                <option value={*option} selected={*option == selected}>{ *option }</option>
+            }).collect::<Html>() }
+        </select>
+    }
+}
+
+fn tracker_inputs(draft: &UseStateHandle<AppConfig>) -> Html {
+    match &draft.tracker {
+        Some(TrackerConfig::Jira(jira)) => html! {
+            <>
+                { input("Jira url", jira.url.clone(), false, edit(draft, |c, v| if let Some(TrackerConfig::Jira(j)) = &mut c.tracker { j.url = v })) }
+                { input("Jira user email", jira.user.clone(), false, edit(draft, |c, v| if let Some(TrackerConfig::Jira(j)) = &mut c.tracker { j.user = v })) }
+                { input("Jira api key", jira.api_key.clone(), true, edit(draft, |c, v| if let Some(TrackerConfig::Jira(j)) = &mut c.tracker { j.api_key = v })) }
+                { input("JQL, empty for my open issues", jira.jql.clone().unwrap_or_default(), false, edit(draft, |c, v| if let Some(TrackerConfig::Jira(j)) = &mut c.tracker { j.jql = Some(v).filter(|v| !v.is_empty()) })) }
+            </>
+        },
+        Some(TrackerConfig::Github(github)) => html! {
+            <>
+                { input("GitHub user", github.user.clone(), false, edit(draft, |c, v| if let Some(TrackerConfig::Github(g)) = &mut c.tracker { g.user = v })) }
+                { input("GitHub token", github.token.clone(), true, edit(draft, |c, v| if let Some(TrackerConfig::Github(g)) = &mut c.tracker { g.token = v })) }
+                { input("Repositories, owner/repo comma separated", github.repositories.join(","), false, edit(draft, |c, v| if let Some(TrackerConfig::Github(g)) = &mut c.tracker {
+                    g.repositories = v.split(',').map(|repo| repo.trim().to_string()).filter(|repo| !repo.is_empty()).collect();
+                })) }
+                { input("Api url, empty for github.com", github.api_url.clone().unwrap_or_default(), false, edit(draft, |c, v| if let Some(TrackerConfig::Github(g)) = &mut c.tracker { g.api_url = Some(v).filter(|v| !v.is_empty()) })) }
+                <label>{"Worklogs as "}
+                    { select(&["comment", "summary", "off"], worklog_mode_name(github.worklogs), edit(draft, |c, v| if let Some(TrackerConfig::Github(g)) = &mut c.tracker {
+                        g.worklogs = match v.as_str() {
+                            "summary" => GithubWorklogMode::Summary,
+                            "off" => GithubWorklogMode::Off,
+                            _ => GithubWorklogMode::Comment,
+                        };
+                    })) }
+                </label>
+            </>
+        },
+        Some(TrackerConfig::Gitlab(gitlab)) => html! {
+            <>
+                { input("GitLab url", gitlab.url.clone(), false, edit(draft, |c, v| if let Some(TrackerConfig::Gitlab(g)) = &mut c.tracker { g.url = v })) }
+                { input("GitLab user", gitlab.user.clone(), false, edit(draft, |c, v| if let Some(TrackerConfig::Gitlab(g)) = &mut c.tracker { g.user = v })) }
+                { input("GitLab token", gitlab.token.clone(), true, edit(draft, |c, v| if let Some(TrackerConfig::Gitlab(g)) = &mut c.tracker { g.token = v })) }
+            </>
+        },
+        None => html! {},
+    }
+}
+
+fn worklog_mode_name(mode: GithubWorklogMode) -> &'static str {
+    match mode {
+        GithubWorklogMode::Off => "off",
+        GithubWorklogMode::Comment => "comment",
+        GithubWorklogMode::Summary => "summary",
+    }
+}
+
+fn rounding_mode_name(mode: RoundingMode) -> &'static str {
+    match mode {
+        RoundingMode::Nearest => "nearest",
+        RoundingMode::Up => "up",
+        RoundingMode::Down => "down",
+    }
+}
+
+#[function_component(SettingsForm)]
+pub fn settings_form(props: &SettingsFormProps) -> Html {
+    let draft = use_state(|| props.config.clone());
+
+    {
+        // start over from the saved config whenever it changes
+        let draft = draft.clone();
+        use_effect_with(props.config.clone(), move |config| {
+            draft.set(config.clone());
+            || ()
+        });
+    }
+
+    let kind = draft.tracker.as_ref().map(|tracker| tracker.kind()).unwrap_or("none");
+    let change_kind = edit(&draft, |c, v| {
+        if c.tracker.as_ref().map(|tracker| tracker.kind()) == Some(v.as_str()) {
+            return;
+        }
+        c.tracker = match v.as_str() {
+            "jira" => Some(TrackerConfig::Jira(JiraConfig::default())),
+            "github" => Some(TrackerConfig::Github(GithubConfig::default())),
+            "gitlab" => Some(TrackerConfig::Gitlab(GitlabConfig::default())),
+            _ => None,
+        };
+    });
+
+    let on_summit = {
+        let draft = draft.clone();
+        let save = props.save.clone();
+        move |e: SubmitEvent| {
+            e.prevent_default();
+            save.emit((*draft).clone());
+        }
+    };
+
+    let on_test = {
+        let draft = draft.clone();
+        let test_connection = props.test_connection.clone();
+        move |_: MouseEvent| {
+            if let Some(tracker) = &draft.tracker {
+                test_connection.emit(tracker.clone());
+            }
+        }
+    };
+
+    let on_check = {
+        let check_totals = props.check_totals.clone();
+        move |_: MouseEvent| check_totals.emit(false)
+    };
+    let on_repair = {
+        let check_totals = props.check_totals.clone();
+        move |_: MouseEvent| check_totals.emit(true)
+    };
+
+    html! {
+        <div>
+            <form class="column wrap-flex" onsubmit={on_summit}>
+                <label>{"Tracker "}
+                    { select(&["none", "jira", "github", "gitlab"], kind, change_kind) }
+                </label>
+                { tracker_inputs(&draft) }
+                if draft.tracker.is_some() {
+                    <button type="button" onclick={on_test}>{"Test connection"}</button>
+                }
+                { input("Database url, empty for the default", draft.database_url.clone().unwrap_or_default(), false, edit(&draft, |c, v| c.database_url = Some(v).filter(|v| !v.is_empty()))) }
+                <label>{"Round to minutes, 0 for off "}
+                    { input("Round to minutes", draft.rounding.minutes.to_string(), false, edit(&draft, |c, v| c.rounding.minutes = v.parse().unwrap_or(0))) }
+                </label>
+                <label>{"Rounding "}
+                    { select(&["nearest", "up", "down"], rounding_mode_name(draft.rounding.mode), edit(&draft, |c, v| {
+                        c.rounding.mode = match v.as_str() {
+                            "up" => RoundingMode::Up,
+                            "down" => RoundingMode::Down,
+                            _ => RoundingMode::Nearest,
+                        };
+                    })) }
+                </label>
+                <label>
+                    <input type="checkbox" checked={draft.idle.enabled} onchange={{
+                        let edit = edit(&draft, |c, v| c.idle.enabled = v == "true");
+                        move |e: Event| edit.emit(e.target_unchecked_into::<HtmlInputElement>().checked().to_string())
+                    }} />
+                    {" Ask about idle time"}
+                </label>
+                <label>{"Idle after minutes "}
+                    { input("Idle after minutes", draft.idle.threshold_minutes.to_string(), false, edit(&draft, |c, v| c.idle.threshold_minutes = v.parse().unwrap_or(0))) }
+                </label>
+                <label>{"Tray time moves every "}
+                    { select(&["minute", "second"], if draft.tray.tick == TrayTick::Second { "second" } else { "minute" }, edit(&draft, |c, v| {
+                        c.tray.tick = if v == "second" { TrayTick::Second } else { TrayTick::Minute };
+                    })) }
+                </label>
+                <label>
+                    <input type="checkbox" checked={draft.calendar.feed} onchange={{
+                        let edit = edit(&draft, |c, v| c.calendar.feed = v == "true");
+                        move |e: Event| edit.emit(e.target_unchecked_into::<HtmlInputElement>().checked().to_string())
+                    }} />
+                    {" Serve a calendar feed"}
+                </label>
+                <label>{"Feed port "}
+                    { input("Feed port", draft.calendar.port.to_string(), false, edit(&draft, |c, v| c.calendar.port = v.parse().unwrap_or(0))) }
+                </label>
+                <label>{"Feed days "}
+                    { input("Feed days", draft.calendar.days.to_string(), false, edit(&draft, |c, v| c.calendar.days = v.parse().unwrap_or(0))) }
+                </label>
+                if props.config.calendar.feed {
+                    <p>{"Subscribe to "}<code>{ props.config.calendar.url() }</code></p>
+                }
+                <button type="submit">{"Save settings"}</button>
+            </form>
+            <div class="row wrap-flex">
+                <button onclick={on_check}>{"Check totals"}</button>
+                <button onclick={on_repair}>{"Repair totals"}</button>
+            </div>
+            if !props.status.is_empty() {
+                <p class="settings-status">{ &props.status }</p>
+            }
+        </div>
+    }
+}
@@ -0,0 +1,85 @@
+use wasm_bindgen::JsValue;
+use web_sys::HtmlInputElement;
+use yew::NodeRef;
+
+pub fn local_time(seconds: u64) -> String {
+    date(seconds)
+        .to_locale_string("default", &JsValue::UNDEFINED)
+        .into()
+}
+
+/// Value for a `datetime-local` input, in local time.
+pub fn input_value(seconds: u64) -> String {
+    let date = date(seconds);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}",
+        date.get_full_year(),
+        date.get_month() + 1,
+        date.get_date(),
+        date.get_hours(),
+        date.get_minutes()
+    )
+}
+
+/// Reads a `datetime-local` input, the browser takes it as local time.
+pub fn input_time(input: &NodeRef) -> Option<u64> {
+    let value = input.cast::<HtmlInputElement>()?.value();
+    let millis = js_sys::Date::new(&JsValue::from_str(&value)).get_time();
+
+    match millis.is_nan() {
+        true => None,
+        false => Some((millis / 1000.0) as u64),
+    }
+}
+
+fn date(seconds: u64) -> js_sys::Date {
+    js_sys::Date::new(&JsValue::from_f64(seconds as f64 * 1000.0))
+}
+
+/// Local midnight of the monday of the week `seconds` is in.
+pub fn week_start(seconds: u64) -> u64 {
+    let since_monday = (date(seconds).get_day() as i32 + 6) % 7;
+    add_days(seconds, -since_monday)
+}
+
+/// Local midnight `days` days after the day `seconds` is in, days vary in length around DST.
+pub fn add_days(seconds: u64, days: i32) -> u64 {
+    let date = date(seconds);
+    let midnight = js_sys::Date::new_with_year_month_day(date.get_full_year(), date.get_month() as i32, date.get_date() as i32 + days);
+    (midnight.get_time() / 1000.0) as u64
+}
+
+/// `YYYY-MM-DD` in local time, as the storage names days.
+pub fn day_key(seconds: u64) -> String {
+    let date = date(seconds);
+    format!("{:04}-{:02}-{:02}", date.get_full_year(), date.get_month() + 1, date.get_date())
+}
+
+/// Short local name of the day, e.g. `Mon 13`.
+pub fn day_label(seconds: u64) -> String {
+    let date = date(seconds);
+    let names = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+    format!("{} {}", names[date.get_day() as usize], date.get_date())
+}
+
+pub fn now() -> u64 {
+    (js_sys::Date::now() / 1000.0) as u64
+}
+
+/// Local midnight of a `date` input value, `YYYY-MM-DD`.
+pub fn date_input_time(value: &str) -> Option<u64> {
+    // without a time the browser would take the date as UTC
+    let millis = js_sys::Date::new(&JsValue::from_str(&format!("{}T00:00", value))).get_time();
+
+    match millis.is_nan() {
+        true => None,
+        false => Some((millis / 1000.0) as u64),
+    }
+}
+
+/// Local midnight of the first day of the month `seconds` is in.
+pub fn month_start(seconds: u64) -> u64 {
+    let date = date(seconds);
+    let first = js_sys::Date::new_with_year_month_day(date.get_full_year(), date.get_month() as i32, 1);
+    (first.get_time() / 1000.0) as u64
+}
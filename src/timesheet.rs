@@ -0,0 +1,200 @@
+use serde::{Deserialize, Serialize};
+use shared::{format_duration, parse_duration, DailyTotal, Issue};
+use wasm_bindgen_futures::spawn_local;
+use web_sys::{HtmlInputElement, HtmlSelectElement};
+use yew::prelude::*;
+
+use crate::backend::{call, RangeArgs};
+use crate::time::{add_days, day_key, day_label, now, week_start};
+
+#[derive(Properties, PartialEq)]
+pub struct TimesheetProps {
+    /// issues rows can be added for, the week is loaded again when they change
+    pub issues: Vec<Issue>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TimesheetCellArgs {
+    name: String,
+    day_start: u64,
+    day_end: u64,
+    seconds: u64,
+}
+
+fn load_week(week_start: u64, totals: UseStateHandle<Vec<DailyTotal>>, status: UseStateHandle<String>) {
+    spawn_local(async move {
+        match call("get_timesheet", &RangeArgs { from: week_start, to: add_days(week_start, 7) }).await {
+            Ok(v) => totals.set(v),
+            Err(err) => status.set(err),
+        }
+    });
+}
+
+#[derive(Properties, PartialEq)]
+struct CellProps {
+    name: String,
+    day_start: u64,
+    day_end: u64,
+    seconds: u64,
+    set_cell: Callback<(String, u64, u64, u64)>,
+}
+
+#[function_component(Cell)]
+fn cell(props: &CellProps) -> Html {
+    let onchange = {
+        let set_cell = props.set_cell.clone();
+        let name = props.name.clone();
+        let (day_start, day_end, current) = (props.day_start, props.day_end, props.seconds);
+        move |e: Event| {
+            let input = e.target_unchecked_into::<HtmlInputElement>();
+            match parse_duration(&input.value()) {
+                Some(seconds) if seconds != current => set_cell.emit((name.clone(), day_start, day_end, seconds)),
+                Some(_) => {}
+                None => input.set_value(&shown(current)),
+            }
+        }
+    };
+
+    html! {
+        <td><input class="timesheet-cell" value={shown(props.seconds)} placeholder="-" {onchange} /></td>
+    }
+}
+
+fn shown(seconds: u64) -> String {
+    match seconds {
+        0 => String::new(),
+        seconds => format_duration(seconds),
+    }
+}
+
+#[function_component(Timesheet)]
+pub fn timesheet(props: &TimesheetProps) -> Html {
+    // local midnight of the monday
+    let week = use_state(|| week_start(now()));
+    let totals = use_state(Vec::<DailyTotal>::new);
+    // result of the last change
+    let status = use_state(String::new);
+    // issues without time this week that got a row to fill in
+    let added_rows = use_state(Vec::<String>::new);
+    let issue_select_ref = use_node_ref();
+
+    {
+        let totals = totals.clone();
+        let status = status.clone();
+        use_effect_with((*week, props.issues.clone()), move |(week, _)| {
+            load_week(*week, totals, status);
+            || ()
+        });
+    }
+
+    // issue name, start and end of the day, the new time of the cell
+    let set_cell = {
+        let shown_week = *week;
+        let totals = totals.clone();
+        let status = status.clone();
+        Callback::from(move |(name, day_start, day_end, seconds): (String, u64, u64, u64)| {
+            let totals = totals.clone();
+            let status = status.clone();
+            spawn_local(async move {
+                match call::<()>("set_timesheet_cell", &TimesheetCellArgs { name, day_start, day_end, seconds }).await {
+                    Ok(_) => status.set(String::new()),
+                    Err(err) => status.set(err),
+                }
+                load_week(shown_week, totals, status);
+            });
+        })
+    };
+
+    let day_starts: Vec<u64> = (0..=7).map(|i| add_days(*week, i)).collect();
+    let days: Vec<String> = day_starts[..7].iter().map(|start| day_key(*start)).collect();
+
+    let mut rows: Vec<(String, String)> = Vec::new();
+    for total in totals.iter() {
+        if !rows.iter().any(|(name, _)| name == &total.issue_name) {
+            rows.push((total.issue_name.clone(), total.issue_summary.clone()));
+        }
+    }
+    for name in added_rows.iter() {
+        if !rows.iter().any(|(row, _)| row == name) {
+            let summary = props.issues.iter().find(|issue| &issue.name == name).map(|issue| issue.summary.to_string());
+            rows.push((name.clone(), summary.unwrap_or_default()));
+        }
+    }
+
+    let seconds = |name: &str, day: &str| {
+        totals
+            .iter()
+            .filter(|total| total.issue_name == name && total.day == day)
+            .map(|total| total.seconds)
+            .sum::<u64>()
+    };
+    let day_total = |day: &str| totals.iter().filter(|total| total.day == day).map(|total| total.seconds).sum::<u64>();
+    let week_total: u64 = totals.iter().map(|total| total.seconds).sum();
+
+    let change_week = |weeks: i32| {
+        let week = week.clone();
+        move |_: MouseEvent| week.set(add_days(*week, weeks * 7))
+    };
+    let add_row = {
+        let added_rows = added_rows.clone();
+        let issue_select_ref = issue_select_ref.clone();
+        move |_: MouseEvent| {
+            if let Some(select) = issue_select_ref.cast::<HtmlSelectElement>() {
+                let mut rows = (*added_rows).clone();
+                rows.push(select.value());
+                added_rows.set(rows);
+            }
+        }
+    };
+
+    html! {
+        <div class="card timesheet">
+            <div>
+                <div class="row wrap-flex">
+                    <button onclick={change_week(-1)}>{"Previous week"}</button>
+                    <h4><b>{"Week of "} { &days[0] }</b></h4>
+                    <button onclick={change_week(1)}>{"Next week"}</button>
+                </div>
+                <table>
+                    <thead>
+                        <tr>
+                            <th>{"Issue"}</th>
+                            { day_starts[..7].iter().map(|start| html! { <th>{ day_label(*start) }</th> }).collect::<Html>() }
+                            <th>{"Total"}</th>
+                        </tr>
+                    </thead>
+                    <tbody>
+                        { rows.iter().map(|(name, summary)| html! {
+                            <tr key={name.clone()}>
+                                <td title={summary.clone()}>{ name }</td>
+                                { (0..7).map(|i| html! {
+                                    <Cell name={name.clone()} day_start={day_starts[i]} day_end={day_starts[i + 1]} seconds={seconds(name, &days[i])} set_cell={set_cell.clone()} />
+                                }).collect::<Html>() }
+                                <td><b>{ format_duration(days.iter().map(|day| seconds(name, day)).sum()) }</b></td>
+                            </tr>
+                        }).collect::<Html>() }
+                    </tbody>
+                    <tfoot>
+                        <tr>
+                            <td><b>{"Total"}</b></td>
+                            { days.iter().map(|day| html! { <td><b>{ format_duration(day_total(day)) }</b></td> }).collect::<Html>() }
+                            <td><b>{ format_duration(week_total) }</b></td>
+                        </tr>
+                    </tfoot>
+                </table>
+                <div class="row wrap-flex">
+                    <select ref={issue_select_ref}>
+                        { props.issues.iter().filter(|issue| !rows.iter().any(|(name, _)| name == &*issue.name)).map(|issue| html! {
+                            <option value={issue.name.clone()}>{ &issue.name }</option>
+                        }).collect::<Html>() }
+                    </select>
+                    <button onclick={add_row}>{"Add row"}</button>
+                </div>
+                if !status.is_empty() {
+                    <p class="error">{ &*status }</p>
+                }
+            </div>
+        </div>
+    }
+}
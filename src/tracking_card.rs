@@ -6,13 +6,16 @@ use yew::prelude::*;
 pub struct TrackingCardProps {
     pub name: String,
     pub duration: Duration,
-    pub stop_tracking: Callback<()>
+    pub paused: bool,
+    pub stop_tracking: Callback<()>,
+    pub toggle_pause: Callback<()>,
 }
 
 #[function_component(TracingCard)]
 pub fn tracking_card(props: &TrackingCardProps) -> Html {
     let name = props.name.clone();
     let stop_tracking = props.stop_tracking.clone();
+    let toggle_pause = props.toggle_pause.clone();
 
     let on_clicked = {
         move |_: MouseEvent| {
@@ -20,16 +23,25 @@ pub fn tracking_card(props: &TrackingCardProps) -> Html {
         }
     };
 
-    let duration = props.duration.clone();
+    let on_pause_clicked = {
+        move |e: MouseEvent| {
+            // the card itself stops tracking
+            e.stop_propagation();
+            toggle_pause.emit(())
+        }
+    };
+
+    let duration = props.duration;
     let seconds = duration.as_secs() % 60;
     let minutes = (duration.as_secs() / 60) % 60;
     let hours = (duration.as_secs() / 60) / 60;
 
     html! {
-        <div class="card" onclick={on_clicked} >
+        <div class={classes!("card", props.paused.then_some("paused"))} onclick={on_clicked} >
             <div class="">
                 <h4><b>{"Name: "} { name }  </b></h4>
-                <p>{"Time: "} {hours} {":"} {minutes} {":"} {seconds} </p>
+                <p>{"Time: "} {hours} {":"} {minutes} {":"} {seconds} if props.paused { {" (paused)"} } </p>
+                <button onclick={on_pause_clicked}>{ if props.paused { "Resume" } else { "Pause" } }</button>
             </div>
         </div>
     }